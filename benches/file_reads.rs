@@ -0,0 +1,60 @@
+//! Compare reading a batch of large captcha images into `Vec`s with the
+//! shared reads used for uploads (memory-mapped with the `mmap` feature)
+//!
+//! Run with `cargo bench --bench file_reads --features mmap`, and without
+//! the feature for the baseline. Peak memory is best compared from the
+//! outside, e.g. with `/usr/bin/time -v`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use twocaptcha::FileAccess;
+
+const FILES: usize = 32;
+const FILE_SIZE: usize = 8 * 1024 * 1024;
+const ROUNDS: u32 = 5;
+
+#[tokio::main]
+async fn main() {
+    let dir = std::env::temp_dir().join("twocaptcha_file_reads");
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut files = HashMap::new();
+    for i in 1..=FILES {
+        let path = dir.join(format!("{i}.png"));
+        std::fs::write(&path, vec![i as u8; FILE_SIZE]).unwrap();
+        files.insert(format!("file_{i}"), path.to_string_lossy().into_owned());
+    }
+
+    let access = Arc::new(FileAccess::default());
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let mut total = 0;
+        for path in files.values() {
+            total += access.read(path).await.unwrap().len();
+        }
+        assert_eq!(total, FILES * FILE_SIZE);
+    }
+    report("read to Vec", start);
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let contents = access.read_all(files.clone()).await.unwrap();
+        let total: usize = contents.values().map(|content| content.len()).sum();
+        assert_eq!(total, FILES * FILE_SIZE);
+    }
+    let mode = if cfg!(feature = "mmap") {
+        "read_all (mmap)"
+    } else {
+        "read_all"
+    };
+    report(mode, start);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn report(name: &str, start: Instant) {
+    let per_round = start.elapsed() / ROUNDS;
+    let mib = (FILES * FILE_SIZE) as f64 / (1024.0 * 1024.0);
+    println!("{name:>16}: {per_round:?} per batch of {FILES} files ({mib:.0} MiB)");
+}
@@ -1,5 +1,4 @@
-use std::collections::HashMap;
-use twocaptcha::{RecaptchaVersion, TwoCaptcha, TwoCaptchaConfig};
+use twocaptcha::{Extras, RecaptchaVersion, TwoCaptcha, TwoCaptchaConfig};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -52,9 +51,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Example 4: reCAPTCHA v3 with custom parameters
     println!("\n4. Solving reCAPTCHA v3...");
-    let mut params = HashMap::new();
-    params.insert("min_score".to_string(), "0.3".to_string());
-    params.insert("action".to_string(), "verify".to_string());
+    let params = Extras::new().min_score(0.3).action("verify");
 
     match solver
         .recaptcha(
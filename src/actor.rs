@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinSet;
+
+use crate::error::{Result, TwoCaptchaError};
+use crate::solver::TwoCaptcha;
+use crate::types::CaptchaResult;
+
+/// Messages accepted by the solver actor
+enum Message {
+    Solve {
+        params: HashMap<String, String>,
+        reply: oneshot::Sender<Result<CaptchaResult>>,
+    },
+    Shutdown,
+}
+
+/// Message-passing facade over [`TwoCaptcha`]
+///
+/// The handle is cheap to clone and can be shared between tasks. Every
+/// submitted task is solved concurrently by a background actor and the answer
+/// is delivered through a oneshot channel.
+#[derive(Debug, Clone)]
+pub struct SolverHandle {
+    sender: mpsc::UnboundedSender<Message>,
+}
+
+impl SolverHandle {
+    /// Spawn the solver actor on the current tokio runtime
+    pub fn spawn(solver: TwoCaptcha) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(Self::run(solver, receiver));
+
+        Self { sender }
+    }
+
+    /// Submit a task, returning a receiver for its result
+    ///
+    /// `params` are the raw request parameters, as accepted by
+    /// [`TwoCaptcha::solve`].
+    pub fn submit(
+        &self,
+        params: HashMap<String, String>,
+    ) -> oneshot::Receiver<Result<CaptchaResult>> {
+        let (reply, receiver) = oneshot::channel();
+
+        if let Err(mpsc::error::SendError(Message::Solve { reply, .. })) =
+            self.sender.send(Message::Solve { params, reply })
+        {
+            let _ = reply.send(Err(TwoCaptchaError::Shutdown));
+        }
+
+        receiver
+    }
+
+    /// Stop accepting new tasks
+    ///
+    /// Tasks already submitted keep running and still deliver their results.
+    pub fn shutdown(&self) {
+        let _ = self.sender.send(Message::Shutdown);
+    }
+
+    async fn run(solver: TwoCaptcha, mut receiver: mpsc::UnboundedReceiver<Message>) {
+        let mut in_flight = JoinSet::new();
+
+        loop {
+            tokio::select! {
+                // Reap finished solves so a long-lived actor does not hold
+                // on to every task it ever ran
+                Some(_) = in_flight.join_next(), if !in_flight.is_empty() => {}
+                message = receiver.recv() => match message {
+                    Some(Message::Solve { params, reply }) => {
+                        let solver = solver.clone();
+                        in_flight.spawn(async move {
+                            let _ = reply.send(solver.solve(None, None, params).await);
+                        });
+                    }
+                    Some(Message::Shutdown) | None => break,
+                },
+            }
+        }
+
+        receiver.close();
+        while let Some(message) = receiver.recv().await {
+            if let Message::Solve { reply, .. } = message {
+                let _ = reply.send(Err(TwoCaptchaError::Shutdown));
+            }
+        }
+
+        while in_flight.join_next().await.is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::TwoCaptchaConfig;
+
+    #[tokio::test]
+    async fn test_submit_after_shutdown() {
+        let handle = SolverHandle::spawn(TwoCaptcha::new(
+            "test_key".to_string(),
+            TwoCaptchaConfig::default(),
+        ));
+        handle.shutdown();
+
+        let result = handle.submit(HashMap::new()).await.unwrap();
+        assert!(matches!(result, Err(TwoCaptchaError::Shutdown)));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_in_flight() {
+        use crate::scripted::Scripted;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        // Keeps the first polls waiting so the tasks are still in flight
+        // when the actor is shut down
+        let transport = Scripted::new(|request| {
+            Ok(match (request.poll, request.count) {
+                (false, count) => format!("OK|{}", count + 1),
+                (true, 0..4) => "CAPCHA_NOT_READY".to_string(),
+                (true, _) => "OK|token".to_string(),
+            })
+        });
+        let handle = SolverHandle::spawn(TwoCaptcha::new(
+            "test_key".to_string(),
+            TwoCaptchaConfig {
+                transport: Some(Arc::new(transport)),
+                polling_interval: Some(Duration::from_millis(1)),
+                ..Default::default()
+            },
+        ));
+
+        let receivers: Vec<_> = (0..4)
+            .map(|i| {
+                handle.submit(HashMap::from([
+                    ("method".to_string(), "text".to_string()),
+                    ("textcaptcha".to_string(), format!("question {i}")),
+                ]))
+            })
+            .collect();
+        handle.shutdown();
+
+        for receiver in receivers {
+            let result = receiver.await.unwrap().unwrap();
+            assert_eq!(result.code.as_deref(), Some("token"));
+        }
+    }
+}
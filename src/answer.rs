@@ -0,0 +1,54 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// How letter case is treated when comparing answers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaseMode {
+    /// Answers must match case exactly
+    Sensitive,
+    /// Case is ignored (the API default unless `regsense=1` is sent)
+    #[default]
+    Insensitive,
+}
+
+/// Normalize a text answer the way workers are expected to type it
+///
+/// Applies Unicode NFC, trims the ends and collapses inner runs of
+/// whitespace to a single space.
+pub fn normalize_answer(answer: &str) -> String {
+    answer
+        .nfc()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Compare two answers after normalization
+pub fn answers_match(answer: &str, expected: &str, mode: CaseMode) -> bool {
+    let answer = normalize_answer(answer);
+    let expected = normalize_answer(expected);
+
+    match mode {
+        CaseMode::Sensitive => answer == expected,
+        CaseMode::Insensitive => answer.to_lowercase() == expected.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_answer() {
+        assert_eq!(normalize_answer("  ab \t  cd\n"), "ab cd");
+        // "e" followed by a combining acute accent composes to "é"
+        assert_eq!(normalize_answer("caf\u{0065}\u{0301}"), "caf\u{00e9}");
+    }
+
+    #[test]
+    fn test_answers_match() {
+        assert!(answers_match(" AbC ", "abc", CaseMode::Insensitive));
+        assert!(!answers_match("AbC", "abc", CaseMode::Sensitive));
+        assert!(answers_match("a  b", "a b", CaseMode::Sensitive));
+    }
+}
@@ -1,102 +1,727 @@
 use crate::error::{Result, TwoCaptchaError};
-use reqwest::{Client, Response, multipart::Form};
+use crate::metrics::{BandwidthLedger, UsageCounters};
+use async_trait::async_trait;
+use bytes::Bytes;
+use indexmap::IndexMap;
+use reqwest::dns::{Name, Resolve, Resolving};
+use reqwest::header::{CONTENT_TYPE, HeaderMap};
+use reqwest::multipart::{Form, Part};
+use reqwest::{Body, Client, ClientBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use zeroize::Zeroize;
+
+#[cfg(feature = "pinning")]
+use crate::pinning::{self, Pin};
+#[cfg(feature = "middleware")]
+use reqwest_middleware::ClientWithMiddleware;
+
+/// Status, headers and body of an HTTP response
+#[derive(Debug, Clone)]
+pub struct TransportResponse {
+    pub status: StatusCode,
+    /// May be left empty by transports without headers
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+/// Response headers quoted in network errors, to trace a failed request
+/// through the proxies and CDN in front of the API
+const HEADERS_OF_INTEREST: &[&str] = &[
+    "server",
+    "via",
+    "cf-ray",
+    "x-request-id",
+    "retry-after",
+    "content-type",
+];
+
+/// Headers of `headers` worth quoting in an error
+pub(crate) fn headers_of_interest(headers: &HeaderMap) -> Vec<(String, String)> {
+    HEADERS_OF_INTEREST
+        .iter()
+        .filter_map(|name| {
+            let value = headers.get(*name)?.to_str().ok()?;
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Parameters of a request, in the order they are sent
+///
+/// [`ApiClient`] sorts them by name, so the same request is encoded
+/// byte for byte the same on every run.
+pub type Params = IndexMap<String, String>;
+
+/// `params` in the order they are sent, see [`Params`]
+fn ordered<V>(params: HashMap<String, V>) -> IndexMap<String, V> {
+    let mut params: IndexMap<String, V> = params.into_iter().collect();
+    params.sort_unstable_keys();
+    params
+}
+
+/// HTTP layer [`ApiClient`] sends its requests through
+///
+/// Implemented for `reqwest::Client` (the default) and, with the
+/// `middleware` feature, `reqwest_middleware::ClientWithMiddleware`. Custom
+/// implementations can use another HTTP stack or answer without a network,
+/// see [`ApiClient::with_transport`].
+#[async_trait]
+pub trait HttpTransport: fmt::Debug + Send + Sync {
+    /// POST `params` as `application/x-www-form-urlencoded`
+    async fn post_form(&self, url: &str, params: &Params) -> Result<TransportResponse>;
+
+    /// POST `params` and `files` as `multipart/form-data`, files being
+    /// keyed by form field
+    async fn post_multipart(
+        &self,
+        url: &str,
+        params: &Params,
+        files: IndexMap<String, Bytes>,
+    ) -> Result<TransportResponse>;
+
+    /// POST a JSON `body`
+    async fn post_json(&self, url: &str, body: String) -> Result<TransportResponse>;
+
+    /// GET `url` with `query` as its query string
+    async fn get(&self, url: &str, query: &Params) -> Result<TransportResponse>;
+}
+
+/// Multipart form of text parameters and uploaded files
+///
+/// Files are streamed from their buffers rather than copied into the form.
+fn multipart_form(params: &Params, files: IndexMap<String, Bytes>) -> Form {
+    let mut form = Form::new();
+    for (key, value) in params {
+        form = form.text(key.clone(), value.clone());
+    }
+    for (key, content) in files {
+        let length = content.len() as u64;
+        let part = Part::stream_with_length(Body::from(content), length).file_name("file");
+        form = form.part(key, part);
+    }
+    form
+}
+
+/// Read a reqwest response, keeping the status of failed non-200 reads
+async fn transport_response(response: Response) -> Result<TransportResponse> {
+    let status = response.status();
+    let headers = response.headers().clone();
+    match response.text().await {
+        Ok(body) => Ok(TransportResponse {
+            status,
+            headers,
+            body,
+        }),
+        Err(e) if status != StatusCode::OK => Err(TwoCaptchaError::Network {
+            status: Some(status),
+            body_snippet: String::new(),
+            headers: headers_of_interest(&headers),
+            source: Some(redact_url(e)),
+        }),
+        Err(e) => Err(redact_url(e).into()),
+    }
+}
+
+#[async_trait]
+impl HttpTransport for Client {
+    async fn post_form(&self, url: &str, params: &Params) -> Result<TransportResponse> {
+        transport_response(self.post(url).form(params).send().await?).await
+    }
+
+    async fn post_multipart(
+        &self,
+        url: &str,
+        params: &Params,
+        files: IndexMap<String, Bytes>,
+    ) -> Result<TransportResponse> {
+        let form = multipart_form(params, files);
+        transport_response(self.post(url).multipart(form).send().await?).await
+    }
+
+    async fn post_json(&self, url: &str, body: String) -> Result<TransportResponse> {
+        let response = self
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await?;
+        transport_response(response).await
+    }
+
+    async fn get(&self, url: &str, query: &Params) -> Result<TransportResponse> {
+        let response = self
+            .get(url)
+            .query(query)
+            .send()
+            .await
+            .map_err(redact_url)?;
+        transport_response(response).await
+    }
+}
+
+#[cfg(feature = "middleware")]
+#[async_trait]
+impl HttpTransport for ClientWithMiddleware {
+    async fn post_form(&self, url: &str, params: &Params) -> Result<TransportResponse> {
+        transport_response(self.post(url).form(params).send().await?).await
+    }
+
+    async fn post_multipart(
+        &self,
+        url: &str,
+        params: &Params,
+        files: IndexMap<String, Bytes>,
+    ) -> Result<TransportResponse> {
+        let form = multipart_form(params, files);
+        transport_response(self.post(url).multipart(form).send().await?).await
+    }
+
+    async fn post_json(&self, url: &str, body: String) -> Result<TransportResponse> {
+        let response = self
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await?;
+        transport_response(response).await
+    }
+
+    async fn get(&self, url: &str, query: &Params) -> Result<TransportResponse> {
+        let response = self
+            .get(url)
+            .query(query)
+            .send()
+            .await
+            .map_err(|e| match e {
+                reqwest_middleware::Error::Reqwest(e) => {
+                    reqwest_middleware::Error::Reqwest(redact_url(e))
+                }
+                e => e,
+            })?;
+        transport_response(response).await
+    }
+}
+
+/// DNS resolution overrides for the API host
+///
+/// Useful where the 2captcha domains are filtered or DNS is unreliable:
+/// hosts listed in `static_addrs` are pinned to the given addresses and all
+/// other lookups go through `resolver` when one is set.
+#[derive(Clone, Default)]
+pub struct DnsConfig {
+    pub static_addrs: HashMap<String, Vec<SocketAddr>>,
+    pub resolver: Option<Arc<dyn Resolve>>,
+}
+
+impl fmt::Debug for DnsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsConfig")
+            .field("static_addrs", &self.static_addrs)
+            .field("resolver", &self.resolver.as_ref().map(|_| "custom"))
+            .finish()
+    }
+}
+
+/// Regional presets for the API server and the captchas it solves
+///
+/// `Cn` keeps the API host but has reCAPTCHA loaded from `recaptcha.net`,
+/// which is reachable from mainland China where `google.com` is not. Pair it
+/// with a [`DnsConfig`] when the API host itself needs another route.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endpoint {
+    #[default]
+    Global,
+    Cn,
+    /// Self-hosted or compatible server, with Global captcha defaults
+    Custom(url::Url),
+}
+
+impl Endpoint {
+    /// API host or base URL to send requests to
+    pub fn server(&self) -> String {
+        match self {
+            Endpoint::Global | Endpoint::Cn => "2captcha.com".to_string(),
+            Endpoint::Custom(url) => url.as_str().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Domain to load reCAPTCHA from, when it differs from the default
+    pub fn recaptcha_domain(&self) -> Option<&'static str> {
+        match self {
+            Endpoint::Cn => Some("recaptcha.net"),
+            Endpoint::Global | Endpoint::Custom(_) => None,
+        }
+    }
+}
+
+/// Paths of the `in.php` and `res.php` endpoints, relative to the server
+///
+/// Only needed for 2captcha-compatible gateways that serve the legacy API
+/// elsewhere; the scheme, port and any path prefix go in the server URL,
+/// e.g. `http://gateway.internal:8080/captcha`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiPaths {
+    pub submit: String,
+    pub result: String,
+}
+
+impl Default for ApiPaths {
+    fn default() -> Self {
+        Self {
+            submit: "in.php".to_string(),
+            result: "res.php".to_string(),
+        }
+    }
+}
+
+/// Adapter passing lookups to a shared `dyn Resolve`
+struct SharedResolver(Arc<dyn Resolve>);
+
+impl Resolve for SharedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        self.0.resolve(name)
+    }
+}
 
 /// API client for communicating with 2captcha service
 #[derive(Debug, Clone)]
 pub struct ApiClient {
     post_url: String,
-    client: Client,
+    client: Arc<dyn HttpTransport>,
+    ledger: Arc<BandwidthLedger>,
+    paths: ApiPaths,
+    body_echo: usize,
+    secondary: Option<String>,
 }
 
 impl ApiClient {
     /// Create a new API client
     pub fn new(post_url: Option<String>) -> Self {
         let post_url = post_url.unwrap_or_else(|| "2captcha.com".to_string());
-        let client = Client::new();
+        let client = Arc::new(Client::new());
+
+        Self {
+            post_url,
+            client,
+            ledger: Arc::default(),
+            paths: ApiPaths::default(),
+            body_echo: BODY_SNIPPET_LEN,
+            secondary: None,
+        }
+    }
+
+    /// Create a new API client on top of a pre-configured `reqwest` client
+    ///
+    /// Proxies, TLS settings and the connection pool of `client` are used
+    /// as is.
+    pub fn with_client(client: Client, post_url: Option<String>) -> Self {
+        Self::with_transport(Arc::new(client), post_url)
+    }
+
+    /// Create a new API client sending its requests through `transport`
+    pub fn with_transport(transport: Arc<dyn HttpTransport>, post_url: Option<String>) -> Self {
+        let post_url = post_url.unwrap_or_else(|| "2captcha.com".to_string());
+
+        Self {
+            post_url,
+            client: transport,
+            ledger: Arc::default(),
+            paths: ApiPaths::default(),
+            body_echo: BODY_SNIPPET_LEN,
+            secondary: None,
+        }
+    }
+
+    /// Create a new API client with DNS overrides for the API host
+    pub fn with_dns(post_url: Option<String>, dns: DnsConfig) -> Self {
+        let post_url = post_url.unwrap_or_else(|| "2captcha.com".to_string());
+        let builder = Self::resolve_with(Client::builder(), dns);
+        let client = Arc::new(builder.build().expect("failed to build HTTP client"));
+
+        Self {
+            post_url,
+            client,
+            ledger: Arc::default(),
+            paths: ApiPaths::default(),
+            body_echo: BODY_SNIPPET_LEN,
+            secondary: None,
+        }
+    }
+
+    /// Create a new API client that only talks to servers matching `pins`
+    ///
+    /// Plain `http://` servers are refused, so the API key is never sent
+    /// over a connection whose certificate was not checked.
+    #[cfg(feature = "pinning")]
+    pub fn with_tls_pins(post_url: Option<String>, pins: Vec<Pin>, dns: Option<DnsConfig>) -> Self {
+        let post_url = post_url.unwrap_or_else(|| "2captcha.com".to_string());
+
+        let mut builder = Client::builder()
+            .https_only(true)
+            .use_preconfigured_tls(pinning::tls_config(pins));
+        if let Some(dns) = dns {
+            builder = Self::resolve_with(builder, dns);
+        }
+        let client = Arc::new(builder.build().expect("failed to build HTTP client"));
+
+        Self {
+            post_url,
+            client,
+            ledger: Arc::default(),
+            paths: ApiPaths::default(),
+            body_echo: BODY_SNIPPET_LEN,
+            secondary: None,
+        }
+    }
+
+    /// Apply DNS overrides to a client builder
+    fn resolve_with(mut builder: ClientBuilder, dns: DnsConfig) -> ClientBuilder {
+        for (host, addrs) in &dns.static_addrs {
+            builder = builder.resolve_to_addrs(host, addrs);
+        }
+        if let Some(resolver) = dns.resolver {
+            builder = builder.dns_resolver(Arc::new(SharedResolver(resolver)));
+        }
+        builder
+    }
+
+    /// Create a new API client on top of an existing `reqwest-middleware` client
+    ///
+    /// Retry, tracing and other middlewares registered on `client` apply to
+    /// every request sent to the API.
+    #[cfg(feature = "middleware")]
+    pub fn with_middleware(client: ClientWithMiddleware, post_url: Option<String>) -> Self {
+        let post_url = post_url.unwrap_or_else(|| "2captcha.com".to_string());
+        let client = Arc::new(client);
+
+        Self {
+            post_url,
+            client,
+            ledger: Arc::default(),
+            paths: ApiPaths::default(),
+            body_echo: BODY_SNIPPET_LEN,
+            secondary: None,
+        }
+    }
+
+    /// Send requests to other endpoint paths, see [`ApiPaths`]
+    pub fn with_paths(mut self, paths: ApiPaths) -> Self {
+        self.paths = paths;
+        self
+    }
+
+    /// Quote at most `limit` bytes of unexpected response bodies in errors
+    /// (default: 512), with anything looking like a key or token masked
+    pub fn with_body_echo(mut self, limit: usize) -> Self {
+        self.body_echo = limit;
+        self
+    }
+
+    /// Resend `in.php`/`res.php` requests to `server` when the primary one
+    /// cannot be connected to, e.g. `rucaptcha.com` for `2captcha.com`
+    ///
+    /// Both hosts must serve the same accounts and captcha ids. The JSON
+    /// API is not failed over.
+    pub fn with_secondary(mut self, server: impl Into<String>) -> Self {
+        self.secondary = Some(server.into());
+        self
+    }
+
+    /// Part of a response body to quote in an error
+    pub(crate) fn echo(&self, body: &str) -> String {
+        redact_secrets(&snippet(body, self.body_echo))
+    }
+
+    /// Full URL of an endpoint path on `server`
+    fn url(server: &str, path: &str) -> String {
+        format!("{}/{}", base_url(server), path.trim_start_matches('/'))
+    }
+
+    /// Base URL of the JSON API (v2), which 2captcha serves on its own host
+    fn v2_base_url(&self) -> String {
+        if self.post_url == "2captcha.com" {
+            "https://api.2captcha.com".to_string()
+        } else {
+            base_url(&self.post_url)
+        }
+    }
+
+    /// API host or base URL as configured
+    pub(crate) fn server(&self) -> &str {
+        &self.post_url
+    }
 
-        Self { post_url, client }
+    /// Traffic exchanged with the API so far
+    pub fn bandwidth(&self) -> Arc<BandwidthLedger> {
+        Arc::clone(&self.ledger)
+    }
+
+    /// Keep accounting traffic in the ledger of another client
+    pub(crate) fn share_ledger(&mut self, other: &ApiClient) {
+        self.ledger = Arc::clone(&other.ledger);
     }
 
     /// Send POST request to solve captcha
+    ///
+    /// Files are uploaded from `files`, keyed by form field; a `file`
+    /// parameter is sent as text, not read from disk.
     pub async fn in_(
         &self,
-        files: Option<HashMap<String, Vec<u8>>>,
+        files: Option<HashMap<String, Bytes>>,
         params: HashMap<String, String>,
     ) -> Result<String> {
-        let url = format!("https://{}/in.php", self.post_url);
-
-        let response = if let Some(files) = files {
-            // Handle file uploads with multipart form
-            let mut form = Form::new();
+        Ok(self.submit(files, params).await?.0)
+    }
 
-            // Add regular parameters
-            for (key, value) in params {
-                form = form.text(key, value);
-            }
+    /// Send a submission like [`ApiClient::in_`], returning the server that
+    /// answered it
+    pub(crate) async fn submit(
+        &self,
+        files: Option<HashMap<String, Bytes>>,
+        params: HashMap<String, String>,
+    ) -> Result<(String, String)> {
+        let Some(secondary) = &self.secondary else {
+            let response = self.submit_to(&self.post_url, files, params).await?;
+            return Ok((response, self.post_url.clone()));
+        };
 
-            // Add files
-            for (key, content) in files {
-                let part = reqwest::multipart::Part::bytes(content).file_name("file");
-                form = form.part(key, part);
+        match self
+            .submit_to(&self.post_url, files.clone(), params.clone())
+            .await
+        {
+            Err(e) if e.is_connect_failure() => {
+                let response = self.submit_to(secondary, files, params).await?;
+                Ok((response, secondary.clone()))
             }
-
-            self.client.post(&url).multipart(form).send().await?
-        } else if params.contains_key("file") {
-            // Handle single file upload
-            let file_path = params.get("file").unwrap().clone();
-            let mut form_params = params.clone();
-            form_params.remove("file");
-
-            let file_content = tokio::fs::read(&file_path).await?;
-
-            let mut form = Form::new();
-            for (key, value) in form_params {
-                form = form.text(key, value);
+            response => {
+                wipe(params);
+                Ok((response?, self.post_url.clone()))
             }
+        }
+    }
 
-            let part = reqwest::multipart::Part::bytes(file_content).file_name("file");
-            form = form.part("file", part);
+    async fn submit_to(
+        &self,
+        server: &str,
+        files: Option<HashMap<String, Bytes>>,
+        params: HashMap<String, String>,
+    ) -> Result<String> {
+        let url = Self::url(server, &self.paths.submit);
+        let params = ordered(params);
+        let mut bytes_sent = params_size(&params);
 
-            self.client.post(&url).multipart(form).send().await?
+        let response = if let Some(files) = files {
+            // Handle file uploads with multipart form
+            bytes_sent += files
+                .values()
+                .map(|content| content.len() as u64)
+                .sum::<u64>();
+            self.client
+                .post_multipart(&url, &params, ordered(files))
+                .await
         } else {
             // Handle regular form data
-            self.client.post(&url).form(&params).send().await?
+            self.client.post_form(&url, &params).await
         };
+        wipe(params);
+        let response = response?;
 
-        self.handle_response(response).await
+        self.handle_response(response, &self.ledger.submit, bytes_sent)
     }
 
     /// Send GET request for additional operations (get result, balance, report etc.)
     pub async fn res(&self, params: HashMap<String, String>) -> Result<String> {
-        let url = format!("https://{}/res.php", self.post_url);
-        let response = self.client.get(&url).query(&params).send().await?;
+        let Some(secondary) = &self.secondary else {
+            return self.res_to(&self.post_url, params).await;
+        };
+
+        match self.res_to(&self.post_url, params.clone()).await {
+            Err(e) if e.is_connect_failure() => self.res_to(secondary, params).await,
+            response => {
+                wipe(params);
+                response
+            }
+        }
+    }
+
+    async fn res_to(&self, server: &str, params: HashMap<String, String>) -> Result<String> {
+        let url = Self::url(server, &self.paths.result);
+        let params = ordered(params);
+        let bytes_sent = params_size(&params);
+        let response = self.client.get(&url, &params).await;
+        wipe(params);
+
+        self.handle_response(response?, &self.ledger.result, bytes_sent)
+    }
+
+    /// POST a JSON body to an endpoint of the JSON API (v2), e.g. `createTask`
+    ///
+    /// Errors reported in the response body are left to the caller.
+    pub async fn post_v2(&self, endpoint: &str, body: String) -> Result<String> {
+        let url = format!("{}/{endpoint}", self.v2_base_url());
+        let bytes_sent = body.len() as u64;
+        let usage = if endpoint == "createTask" {
+            &self.ledger.submit
+        } else {
+            &self.ledger.result
+        };
 
-        self.handle_response(response).await
+        let response = self.client.post_json(&url, body).await?;
+        self.read_response(response, usage, bytes_sent)
     }
 
     /// Handle HTTP response and check for errors
-    async fn handle_response(&self, response: Response) -> Result<String> {
-        if response.status() != 200 {
-            return Err(TwoCaptchaError::Network(format!(
-                "bad response: {}",
-                response.status()
-            )));
+    fn handle_response(
+        &self,
+        response: TransportResponse,
+        usage: &UsageCounters,
+        bytes_sent: u64,
+    ) -> Result<String> {
+        let text = self.read_response(response, usage, bytes_sent)?;
+        // JSON bodies carry their own status, checked by the caller
+        if text.contains("ERROR") && !text.trim_start().starts_with('{') {
+            return Err(TwoCaptchaError::api_from_body(&text, self.echo(&text)));
         }
 
-        let text = response.text().await?;
+        // Some setups (e.g. `header_acao=1` behind proxies) pad the body
+        Ok(text.trim().to_string())
+    }
 
-        if text.contains("ERROR") {
-            return Err(TwoCaptchaError::Api(text));
+    /// Read the body of a successful response, accounting its traffic
+    fn read_response(
+        &self,
+        response: TransportResponse,
+        usage: &UsageCounters,
+        bytes_sent: u64,
+    ) -> Result<String> {
+        let TransportResponse {
+            status,
+            headers,
+            body,
+        } = response;
+        usage.record(bytes_sent, body.len() as u64);
+        if status != StatusCode::OK {
+            return Err(TwoCaptchaError::Network {
+                status: Some(status),
+                body_snippet: self.echo(&body),
+                headers: headers_of_interest(&headers),
+                source: None,
+            });
         }
+        Ok(body)
+    }
+}
 
-        Ok(text)
+/// Base URL of an API server, defaulting to https when no scheme is given
+fn base_url(server: &str) -> String {
+    if server.contains("://") {
+        server.trim_end_matches('/').to_string()
+    } else {
+        format!("https://{server}")
     }
 }
 
+/// Bytes of a response body kept in errors by default
+const BODY_SNIPPET_LEN: usize = 512;
+
+/// Shortest run of token characters taken for a secret
+const MIN_SECRET_LEN: usize = 32;
+
+/// Start of a response body, cut on a character boundary
+fn snippet(body: &str, limit: usize) -> String {
+    let mut end = body.len().min(limit);
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    body[..end].to_string()
+}
+
+/// Mask long runs of token characters, such as API keys and captcha tokens
+///
+/// Runs made only of uppercase letters, digits and underscores are kept, so
+/// error codes like `ERROR_RECAPTCHA_INVALID_SITEKEY` stay readable.
+fn redact_secrets(text: &str) -> String {
+    let is_token = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(is_token) {
+        redacted.push_str(&rest[..start]);
+        let run = &rest[start..];
+        let len = run.find(|c: char| !is_token(c)).unwrap_or(run.len());
+        let (token, tail) = run.split_at(len);
+        let is_code = token
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        if len >= MIN_SECRET_LEN && !is_code {
+            redacted.push_str("[redacted]");
+        } else {
+            redacted.push_str(token);
+        }
+        rest = tail;
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// Drop the query string, which carries the API key, from request errors
+fn redact_url(mut error: reqwest::Error) -> reqwest::Error {
+    if let Some(url) = error.url_mut() {
+        url.set_query(None);
+    }
+    error
+}
+
+/// Overwrite parameter values (including the API key) once they were sent
+fn wipe(params: impl IntoIterator<Item = (String, String)>) {
+    params
+        .into_iter()
+        .for_each(|(_, mut value)| value.zeroize());
+}
+
+/// Approximate encoded size of request parameters
+fn params_size(params: &Params) -> u64 {
+    params
+        .iter()
+        .map(|(key, value)| (key.len() + value.len() + 2) as u64)
+        .sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::scripted::Scripted;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_custom_transport() {
+        let client = ApiClient::with_transport(Arc::new(Scripted::canned("OK|12345")), None);
+        assert_eq!(client.in_(None, HashMap::new()).await.unwrap(), "OK|12345");
+
+        let client =
+            ApiClient::with_transport(Arc::new(Scripted::canned("ERROR_ZERO_BALANCE")), None);
+        let error = client.res(HashMap::new()).await.unwrap_err();
+        assert_eq!(error.attempt_code(), "ERROR_ZERO_BALANCE");
+    }
+
+    #[tokio::test]
+    async fn test_error_code_survives_body_echo() {
+        let canned = Arc::new(Scripted::canned("ERROR_CAPTCHA_UNSOLVABLE"));
+        for limit in [0, 10] {
+            let client = ApiClient::with_transport(canned.clone(), None).with_body_echo(limit);
+            let error = client.res(HashMap::new()).await.unwrap_err();
+            assert_eq!(
+                error.api_code(),
+                Some(crate::ApiErrorCode::CaptchaUnsolvable)
+            );
+            assert!(error.to_string().len() <= "API error: ".len() + limit);
+        }
+    }
 
     #[test]
     fn test_api_client_creation() {
@@ -105,5 +730,174 @@ mod tests {
 
         let client = ApiClient::new(Some("custom.domain.com".to_string()));
         assert_eq!(client.post_url, "custom.domain.com");
+        assert_eq!(base_url(&client.post_url), "https://custom.domain.com");
+
+        let client = ApiClient::new(Some("http://127.0.0.1:8080/".to_string()));
+        assert_eq!(base_url(&client.post_url), "http://127.0.0.1:8080");
+        assert_eq!(
+            ApiClient::url(&client.post_url, &client.paths.submit),
+            "http://127.0.0.1:8080/in.php"
+        );
+
+        let client =
+            ApiClient::new(Some("http://gateway:8080/captcha".to_string())).with_paths(ApiPaths {
+                submit: "/submit".to_string(),
+                result: "poll".to_string(),
+            });
+        assert_eq!(
+            ApiClient::url(&client.post_url, &client.paths.submit),
+            "http://gateway:8080/captcha/submit"
+        );
+        assert_eq!(
+            ApiClient::url(&client.post_url, &client.paths.result),
+            "http://gateway:8080/captcha/poll"
+        );
+    }
+
+    #[test]
+    fn test_api_client_with_client() {
+        let client = ApiClient::with_client(Client::new(), None);
+        assert_eq!(client.post_url, "2captcha.com");
+        assert!(format!("{:?}", client.client).starts_with("Client"));
+    }
+
+    #[test]
+    fn test_endpoint_presets() {
+        assert_eq!(Endpoint::Cn.server(), "2captcha.com");
+        assert_eq!(Endpoint::Cn.recaptcha_domain(), Some("recaptcha.net"));
+        assert_eq!(Endpoint::Global.recaptcha_domain(), None);
+
+        let custom = Endpoint::Custom("http://127.0.0.1:8080/".parse().unwrap());
+        let client = ApiClient::new(Some(custom.server()));
+        assert_eq!(base_url(&client.post_url), "http://127.0.0.1:8080");
+    }
+
+    #[test]
+    fn test_api_client_with_dns() {
+        let mut dns = DnsConfig::default();
+        dns.static_addrs.insert(
+            "2captcha.com".to_string(),
+            vec!["203.0.113.10:443".parse().unwrap()],
+        );
+        let client = ApiClient::with_dns(None, dns);
+        assert_eq!(client.post_url, "2captcha.com");
+    }
+
+    #[tokio::test]
+    async fn test_request_errors_hide_api_key() {
+        let client = ApiClient::new(Some("http://127.0.0.1:9".to_string()));
+        let mut params = HashMap::new();
+        params.insert("key".to_string(), "SECRET_KEY".to_string());
+
+        let error = client.res(params).await.unwrap_err();
+        assert!(!error.to_string().contains("SECRET_KEY"));
+    }
+
+    #[tokio::test]
+    async fn test_failover_to_secondary() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let secondary = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let _ = stream.read(&mut request).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 8\r\n\r\nOK|12345")
+                .await
+                .unwrap();
+        });
+
+        let client = ApiClient::new(Some("http://127.0.0.1:9".to_string()))
+            .with_secondary(secondary.clone());
+        let (response, server) = client.submit(None, HashMap::new()).await.unwrap();
+        assert_eq!(response, "OK|12345");
+        assert_eq!(server, secondary);
+    }
+
+    #[tokio::test]
+    async fn test_network_error_keeps_response_details() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let _ = stream.read(&mut request).await.unwrap();
+            stream
+                .write_all(
+                    b"HTTP/1.1 403 Forbidden\r\nServer: cloudflare\r\nCF-RAY: 8f1e2d3c4b5a\r\n\
+                      X-Other: 1\r\nContent-Length: 13\r\n\r\nAccess denied",
+                )
+                .await
+                .unwrap();
+        });
+
+        let error = ApiClient::new(Some(server))
+            .res(HashMap::new())
+            .await
+            .unwrap_err();
+        let TwoCaptchaError::Network {
+            status, headers, ..
+        } = &error
+        else {
+            panic!("unexpected error {error:?}");
+        };
+        assert_eq!(*status, Some(StatusCode::FORBIDDEN));
+        assert_eq!(
+            headers,
+            &[
+                ("server".to_string(), "cloudflare".to_string()),
+                ("cf-ray".to_string(), "8f1e2d3c4b5a".to_string()),
+            ]
+        );
+        assert!(
+            error
+                .to_string()
+                .contains("403 Forbidden: Access denied (server: cloudflare")
+        );
+    }
+
+    #[test]
+    fn test_params_are_sent_in_name_order() {
+        let params: HashMap<String, String> = ["method", "key", "pageurl", "json", "sitekey"]
+            .iter()
+            .map(|name| (name.to_string(), String::new()))
+            .collect();
+        let names: Vec<String> = ordered(params).into_keys().collect();
+        assert_eq!(names, ["json", "key", "method", "pageurl", "sitekey"]);
+    }
+
+    #[test]
+    fn test_snippet() {
+        assert_eq!(snippet("502 Bad Gateway", 512), "502 Bad Gateway");
+        let body = "é".repeat(BODY_SNIPPET_LEN);
+        assert!(snippet(&body, BODY_SNIPPET_LEN).len() <= BODY_SNIPPET_LEN);
+
+        let body = "OK|03AGdBq24PBCbwiDRaS_MJ7Z-aPXK3cdxJ9ZfrW ERROR_RECAPTCHA_INVALID_SITEKEY_X";
+        let client = ApiClient::new(None);
+        assert_eq!(
+            client.echo(body),
+            "OK|[redacted] ERROR_RECAPTCHA_INVALID_SITEKEY_X"
+        );
+        assert_eq!(client.with_body_echo(48).echo(body), "OK|[redacted] ERROR");
+    }
+
+    #[cfg(feature = "pinning")]
+    #[tokio::test]
+    async fn test_api_client_with_tls_pins_refuses_http() {
+        let pin = Pin::public_key("47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=").unwrap();
+        let client =
+            ApiClient::with_tls_pins(Some("http://127.0.0.1:9".to_string()), vec![pin], None);
+
+        let error = client.res(HashMap::new()).await.unwrap_err();
+        assert!(matches!(error, TwoCaptchaError::Request(_)));
+    }
+
+    #[cfg(feature = "middleware")]
+    #[test]
+    fn test_api_client_with_middleware() {
+        let middleware = reqwest_middleware::ClientBuilder::new(Client::new()).build();
+        let client = ApiClient::with_middleware(middleware, None);
+        assert_eq!(client.post_url, "2captcha.com");
+        assert!(format!("{:?}", client.client).contains("ClientWithMiddleware"));
     }
 }
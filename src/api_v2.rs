@@ -0,0 +1,318 @@
+//! Client side of the 2captcha JSON API (v2): `createTask` and
+//! `getTaskResult` on `api.2captcha.com`
+//!
+//! Enabled for a whole client with `TwoCaptchaConfig::api_version`. Tasks
+//! are built from the usual `in.php` parameters with
+//! [`Task::to_official_json`](crate::Task::to_official_json), so every
+//! solver method works unchanged on either protocol.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::api::ApiClient;
+use crate::clock::{Clock, SystemClock};
+use crate::error::{ApiErrorCode, Result, TwoCaptchaError};
+use crate::types::{BalanceDetails, ReportOutcome};
+
+/// Protocol used to talk to the API
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiVersion {
+    /// Legacy `in.php`/`res.php` endpoints
+    #[default]
+    V1,
+    /// JSON `createTask`/`getTaskResult` endpoints
+    V2,
+    /// JSON endpoints, falling back to the legacy ones for tasks without a
+    /// JSON API type or servers without the JSON API
+    Auto,
+}
+
+/// API error codes of task types a server does not offer on the JSON API
+const UNSUPPORTED_CODES: &[ApiErrorCode] =
+    &[ApiErrorCode::TaskNotSupported, ApiErrorCode::TaskAbsent];
+
+/// Ids remembered as submitted through the JSON API
+const V2_IDS_CAPACITY: usize = 1024;
+
+/// How long new tasks skip the JSON API after it answered 404
+const V2_REPROBE_AFTER: Duration = Duration::from_secs(600);
+
+/// Protocol bookkeeping of [`ApiVersion::Auto`]
+///
+/// Results must be fetched with the protocol the task was created with, so
+/// the ids of tasks created through the JSON API are remembered (the most
+/// recent ones, at least) to be polled on `getTaskResult`. Any other id,
+/// e.g. one submitted elsewhere and passed to `resume`, is polled on
+/// `res.php`.
+///
+/// A server answering 404 on the JSON API gets new tasks on the legacy
+/// endpoints for a while, after which the JSON API is tried again. Tasks
+/// already created through it keep being polled on `getTaskResult`.
+#[derive(Debug)]
+pub(crate) struct AutoVersion {
+    clock: Arc<dyn Clock>,
+    v2_ids: Mutex<VecDeque<String>>,
+    v2_unavailable_since: Mutex<Option<Instant>>,
+}
+
+impl Default for AutoVersion {
+    fn default() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+}
+
+impl AutoVersion {
+    pub(crate) fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            v2_ids: Mutex::default(),
+            v2_unavailable_since: Mutex::default(),
+        }
+    }
+
+    /// Whether the server was recently found not to serve the JSON API, so
+    /// new tasks go to the legacy endpoints
+    pub(crate) fn v2_unavailable(&self) -> bool {
+        self.v2_unavailable_since
+            .lock()
+            .unwrap()
+            .is_some_and(|since| self.clock.now() < since + V2_REPROBE_AFTER)
+    }
+
+    /// Decide whether a failed JSON API submission should be retried on
+    /// the legacy endpoints
+    pub(crate) fn should_fall_back(&self, error: &TwoCaptchaError) -> bool {
+        match error {
+            TwoCaptchaError::Network {
+                status: Some(status),
+                ..
+            } if status.as_u16() == 404 => {
+                *self.v2_unavailable_since.lock().unwrap() = Some(self.clock.now());
+                true
+            }
+            TwoCaptchaError::Validation(_) => true,
+            TwoCaptchaError::Api {
+                code: Some(code), ..
+            } => UNSUPPORTED_CODES.contains(code),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn insert_v2(&self, id: &str) {
+        let mut ids = self.v2_ids.lock().unwrap();
+        if ids.len() == V2_IDS_CAPACITY {
+            ids.pop_front();
+        }
+        ids.push_back(id.to_string());
+    }
+
+    /// Protocol to fetch the result of `id` with
+    pub(crate) fn version_of(&self, id: &str) -> ApiVersion {
+        if self.v2_ids.lock().unwrap().iter().any(|v2| v2 == id) {
+            ApiVersion::V2
+        } else {
+            ApiVersion::V1
+        }
+    }
+}
+
+/// Solution fields holding the answer, in order of preference
+const ANSWER_FIELDS: &[&str] = &["gRecaptchaResponse", "token", "text", "code"];
+
+/// Create a task, returning its id
+pub(crate) async fn create_task(
+    api: &ApiClient,
+    key: &str,
+    task: Value,
+    soft_id: Option<u32>,
+    callback: Option<&str>,
+) -> Result<String> {
+    let mut body = json!({ "clientKey": key, "task": task });
+    if let Some(soft_id) = soft_id {
+        body["softId"] = soft_id.into();
+    }
+    if let Some(callback) = callback {
+        body["callbackUrl"] = callback.into();
+    }
+
+    let response = parse(&api.post_v2("createTask", body.to_string()).await?)?;
+    match &response["taskId"] {
+        Value::Number(id) => Ok(id.to_string()),
+        Value::String(id) => Ok(id.clone()),
+        _ => Err(TwoCaptchaError::api(format!(
+            "createTask response without taskId: {}",
+            api.echo(&response.to_string())
+        ))),
+    }
+}
+
+/// Get the result of a task, `None` while it is still being solved
+///
+/// The raw response is returned so it can be shared between waiters and
+/// turned into a result with [`solution`].
+pub(crate) async fn get_task_result(
+    api: &ApiClient,
+    key: &str,
+    id: &str,
+) -> Result<Option<String>> {
+    let body = json!({ "clientKey": key, "taskId": task_id(id) });
+
+    let text = api.post_v2("getTaskResult", body.to_string()).await?;
+    let response = parse(&text)?;
+    match response["status"].as_str() {
+        Some("ready") => Ok(Some(text)),
+        Some("processing") => Ok(None),
+        _ => Err(TwoCaptchaError::api(format!(
+            "unexpected getTaskResult response: {}",
+            api.echo(&text)
+        ))),
+    }
+}
+
+/// Account balance, with the other fields of the `getBalance` response
+pub(crate) async fn get_balance(api: &ApiClient, key: &str) -> Result<BalanceDetails> {
+    let body = json!({ "clientKey": key });
+    let response = parse(&api.post_v2("getBalance", body.to_string()).await?)?;
+    let Value::Object(mut fields) = response else {
+        return Err(TwoCaptchaError::api(format!(
+            "unexpected getBalance response: {}",
+            api.echo(&response.to_string())
+        )));
+    };
+
+    let balance = fields
+        .remove("balance")
+        .and_then(|balance| match balance {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        })
+        .ok_or_else(|| TwoCaptchaError::api("getBalance response without balance".to_string()))?;
+    fields.remove("errorId");
+
+    Ok(BalanceDetails {
+        balance,
+        extra: fields.into_iter().collect(),
+    })
+}
+
+/// Report the answer of a task as correct or incorrect
+pub(crate) async fn report(
+    api: &ApiClient,
+    key: &str,
+    id: &str,
+    correct: bool,
+) -> Result<ReportOutcome> {
+    let endpoint = if correct {
+        "reportCorrect"
+    } else {
+        "reportIncorrect"
+    };
+    let body = json!({ "clientKey": key, "taskId": task_id(id) });
+
+    let text = api.post_v2(endpoint, body.to_string()).await?;
+    let response = parse(&text)?;
+    Ok(match response["status"].as_str() {
+        Some("success") => ReportOutcome::Recorded,
+        _ => ReportOutcome::Unrecognized(text),
+    })
+}
+
+/// Task ids are numbers on the JSON API
+fn task_id(id: &str) -> Value {
+    id.parse::<u64>().map_or_else(|_| json!(id), Value::from)
+}
+
+/// Answer and structured fields of a ready `getTaskResult` response
+///
+/// The answer is the token or text of the solution, or the whole solution
+/// as JSON for task types answering with several values. The structured
+/// fields are those of the solution plus the task's `cost`, `ip`,
+/// `createTime`, `endTime` and `solveCount`.
+pub(crate) fn solution(response: &str) -> Result<(String, Map<String, Value>)> {
+    let mut response = parse(response)?;
+    let Some(Value::Object(mut fields)) = response.get_mut("solution").map(Value::take) else {
+        return Err(TwoCaptchaError::api(
+            "getTaskResult response without solution".to_string(),
+        ));
+    };
+
+    let answer = ANSWER_FIELDS
+        .iter()
+        .find_map(|field| fields.get(*field)?.as_str())
+        .map_or_else(|| Value::Object(fields.clone()).to_string(), str::to_string);
+
+    for key in ["cost", "ip", "createTime", "endTime", "solveCount"] {
+        if let Some(value) = response.get_mut(key).map(Value::take) {
+            fields.insert(key.to_string(), value);
+        }
+    }
+    Ok((answer, fields))
+}
+
+/// Parse a response, turning a non-zero `errorId` into an API error
+fn parse(text: &str) -> Result<Value> {
+    let response: Value = serde_json::from_str(text)?;
+    if response["errorId"].as_i64().unwrap_or(0) != 0 {
+        let code = response["errorCode"].as_str().unwrap_or("ERROR_UNKNOWN");
+        return Err(TwoCaptchaError::api(code));
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_version() {
+        let clock = Arc::new(crate::clock::ManualClock::new());
+        let auto = AutoVersion::with_clock(clock.clone());
+        assert!(auto.should_fall_back(&TwoCaptchaError::Validation("no v2 task type".into())));
+        assert!(!auto.should_fall_back(&TwoCaptchaError::api("ERROR_ZERO_BALANCE")));
+        assert!(!auto.v2_unavailable());
+
+        let not_found = TwoCaptchaError::Network {
+            status: Some(reqwest::StatusCode::NOT_FOUND),
+            body_snippet: String::new(),
+            headers: Vec::new(),
+            source: None,
+        };
+        auto.insert_v2("123");
+        assert_eq!(auto.version_of("123"), ApiVersion::V2);
+        assert_eq!(auto.version_of("456"), ApiVersion::V1);
+
+        assert!(auto.should_fall_back(&not_found));
+        assert!(auto.v2_unavailable());
+        assert_eq!(auto.version_of("123"), ApiVersion::V2);
+
+        clock.advance(V2_REPROBE_AFTER);
+        assert!(!auto.v2_unavailable());
+    }
+
+    #[test]
+    fn test_solution() {
+        let response = r#"{"errorId":0,"status":"ready","solution":{"gRecaptchaResponse":"03AFcWeA","token":"03AFcWeA"},"cost":"0.00299","ip":"1.2.3.4","createTime":1692863536,"endTime":1692863556,"solveCount":1}"#;
+        let (answer, fields) = solution(response).unwrap();
+        assert_eq!(answer, "03AFcWeA");
+        assert_eq!(fields["cost"], "0.00299");
+
+        let response =
+            r#"{"errorId":0,"status":"ready","solution":{"captcha_id":"1","lot_number":"2"}}"#;
+        let (answer, _) = solution(response).unwrap();
+        assert!(answer.contains(r#""lot_number":"2""#));
+
+        let error = r#"{"errorId":1,"errorCode":"ERROR_KEY_DOES_NOT_EXIST"}"#;
+        assert!(matches!(
+            solution(error),
+            Err(TwoCaptchaError::Api {
+                code: Some(ApiErrorCode::KeyDoesNotExist),
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Source of randomness for the jitter added to retry delays
+///
+/// The default, [`RandomJitter`], is seeded differently in every process.
+/// Tests can pass a [`SeededJitter`] to get the same delays on every run.
+pub trait JitterSource: fmt::Debug + Send + Sync {
+    /// Next value, uniformly distributed in `[0, 1)`
+    fn next_unit(&self) -> f64;
+}
+
+/// xorshift64* generator behind [`RandomJitter`] and [`SeededJitter`]
+#[derive(Debug)]
+struct XorShift(Mutex<u64>);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // xorshift never leaves the all-zero state
+        Self(Mutex::new(seed.max(1)))
+    }
+
+    fn next_unit(&self) -> f64 {
+        let mut state = self.0.lock().unwrap();
+        *state ^= *state >> 12;
+        *state ^= *state << 25;
+        *state ^= *state >> 27;
+        let value = state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        // The 53 high bits fill the mantissa of an f64 in [0, 1)
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Jitter seeded from the process' random hasher keys
+#[derive(Debug)]
+pub struct RandomJitter(XorShift);
+
+impl Default for RandomJitter {
+    fn default() -> Self {
+        Self(XorShift::new(RandomState::new().build_hasher().finish()))
+    }
+}
+
+impl JitterSource for RandomJitter {
+    fn next_unit(&self) -> f64 {
+        self.0.next_unit()
+    }
+}
+
+/// Jitter repeating the same sequence for the same seed
+#[derive(Debug)]
+pub struct SeededJitter(XorShift);
+
+impl SeededJitter {
+    pub fn new(seed: u64) -> Self {
+        Self(XorShift::new(seed))
+    }
+}
+
+impl JitterSource for SeededJitter {
+    fn next_unit(&self) -> f64 {
+        self.0.next_unit()
+    }
+}
+
+/// `delay` scaled by a random factor in `[0.5, 1)`, so clients retrying at
+/// the same time spread out
+pub(crate) fn jittered(delay: Duration, source: &dyn JitterSource) -> Duration {
+    delay.mul_f64(0.5 + source.next_unit() / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_jitter_is_reproducible() {
+        let (a, b) = (SeededJitter::new(42), SeededJitter::new(42));
+        for _ in 0..100 {
+            let value = a.next_unit();
+            assert_eq!(value, b.next_unit());
+            assert!((0.0..1.0).contains(&value));
+        }
+
+        let delay = jittered(Duration::from_secs(2), &SeededJitter::new(7));
+        assert!(delay >= Duration::from_secs(1) && delay < Duration::from_secs(2));
+        assert_eq!(
+            delay,
+            jittered(Duration::from_secs(2), &SeededJitter::new(7))
+        );
+    }
+}
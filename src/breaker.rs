@@ -0,0 +1,132 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SystemClock};
+use crate::config::CircuitBreaker;
+use crate::error::{ErrorKind, Result, TwoCaptchaError};
+
+/// Consecutive auth and network failures of a client, shared by its clones
+///
+/// Once `failures` requests in a row failed, requests fail fast for the
+/// cool-down. After it a single request goes through as a probe while the
+/// others keep failing fast: a success closes the circuit, a failure opens
+/// it again right away. A probe that never reports back is replaced by
+/// another one after a cool-down.
+#[derive(Debug)]
+pub(crate) struct Breaker {
+    clock: Arc<dyn Clock>,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+    /// Start of the probe request let through after the cool-down
+    probe: Option<Instant>,
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+}
+
+impl Breaker {
+    pub(crate) fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Fail fast while the circuit is open, or while a probe is out
+    pub(crate) fn check(&self, config: &CircuitBreaker) -> Result<()> {
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+        let Some(open_until) = state.open_until else {
+            return Ok(());
+        };
+
+        let closed_at = match state.probe {
+            Some(probe) => probe + config.cool_down(),
+            None => open_until,
+        };
+        if closed_at > now {
+            return Err(TwoCaptchaError::CircuitOpen {
+                retry_after: closed_at - now,
+            });
+        }
+        state.probe = Some(now);
+        Ok(())
+    }
+
+    /// Count the outcome of a request let through by [`Breaker::check`]
+    pub(crate) fn record<T>(&self, config: &CircuitBreaker, result: &Result<T>) {
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+        match result {
+            Err(e) if matches!(e.kind(), ErrorKind::Auth | ErrorKind::Network) => {
+                state.failures += 1;
+                if state.failures >= config.failures() {
+                    state.open_until = Some(now + config.cool_down());
+                    state.probe = None;
+                }
+            }
+            _ => *state = BreakerState::default(),
+        }
+    }
+
+    /// Time left before requests are let through again
+    pub(crate) fn open_for(&self) -> Option<Duration> {
+        let now = self.clock.now();
+        let state = self.state.lock().unwrap();
+        state
+            .open_until
+            .and_then(|until| until.checked_duration_since(now))
+            .filter(|left| !left.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn test_breaker_opens_and_closes() {
+        let clock = Arc::new(ManualClock::new());
+        let breaker = Breaker::with_clock(clock.clone());
+        let config = CircuitBreaker {
+            failures: Some(2),
+            cool_down: Some(Duration::from_secs(30)),
+        };
+        let failure: Result<()> = Err(TwoCaptchaError::api("ERROR_WRONG_USER_KEY"));
+
+        breaker.record(&config, &failure);
+        breaker.record(
+            &config,
+            &Err::<(), _>(TwoCaptchaError::api("ERROR_ZERO_BALANCE")),
+        );
+        breaker.record(&config, &failure);
+        assert!(breaker.check(&config).is_ok());
+        breaker.record(&config, &failure);
+        let error = breaker.check(&config).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::CircuitOpen);
+        assert_eq!(breaker.open_for(), Some(Duration::from_secs(30)));
+
+        // A single probe after the cool-down, failing
+        clock.advance(Duration::from_secs(30));
+        assert!(breaker.check(&config).is_ok());
+        assert!(breaker.check(&config).is_err());
+        breaker.record(&config, &failure);
+        assert!(breaker.check(&config).is_err());
+
+        // A successful probe closes the circuit
+        clock.advance(Duration::from_secs(30));
+        assert!(breaker.check(&config).is_ok());
+        breaker.record(&config, &Ok(()));
+        assert!(breaker.check(&config).is_ok() && breaker.check(&config).is_ok());
+        assert!(breaker.open_for().is_none());
+    }
+}
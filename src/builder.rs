@@ -0,0 +1,307 @@
+use std::time::Duration;
+
+use crate::api::Endpoint;
+use crate::api_v2::ApiVersion;
+use crate::error::{Result, TwoCaptchaError};
+use crate::secret::ApiKey;
+use crate::solver::{
+    DEFAULT_TIMEOUT, POLLING_INTERVAL, RECAPTCHA_TIMEOUT, TwoCaptcha, TwoCaptchaConfig,
+};
+use crate::utils::Utils;
+
+/// Chained construction of a [`TwoCaptcha`], see [`TwoCaptcha::builder`]
+///
+/// Common settings have their own setters; any other field of
+/// [`TwoCaptchaConfig`] can be set with [`TwoCaptchaBuilder::configure`].
+///
+/// ```
+/// use std::time::Duration;
+/// use twocaptcha::TwoCaptcha;
+///
+/// let solver = TwoCaptcha::builder("YOUR_API_KEY")
+///     .soft_id(4580)
+///     .timeouts(Duration::from_secs(90), Duration::from_secs(300))
+///     .polling_interval(Duration::from_secs(5))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct TwoCaptchaBuilder {
+    api_key: ApiKey,
+    config: TwoCaptchaConfig,
+}
+
+impl TwoCaptchaBuilder {
+    pub fn new(api_key: impl Into<ApiKey>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            config: TwoCaptchaConfig::default(),
+        }
+    }
+
+    pub fn soft_id(mut self, soft_id: u32) -> Self {
+        self.config.soft_id = Some(soft_id);
+        self
+    }
+
+    /// URL the API sends answers to (`pingback`)
+    pub fn callback(mut self, url: impl Into<String>) -> Self {
+        self.config.callback = Some(url.into());
+        self
+    }
+
+    /// Timeouts of regular captchas and of reCAPTCHA
+    pub fn timeouts(mut self, default: Duration, recaptcha: Duration) -> Self {
+        self.config.default_timeout = Some(default);
+        self.config.recaptcha_timeout = Some(recaptcha);
+        self
+    }
+
+    pub fn polling_interval(mut self, interval: Duration) -> Self {
+        self.config.polling_interval = Some(interval);
+        self
+    }
+
+    /// API host or base URL, see [`TwoCaptchaConfig::server`]
+    pub fn server(mut self, server: impl Into<String>) -> Self {
+        self.config.server = Some(server.into());
+        self
+    }
+
+    pub fn endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.config.endpoint = Some(endpoint);
+        self
+    }
+
+    pub fn api_version(mut self, version: ApiVersion) -> Self {
+        self.config.api_version = Some(version);
+        self
+    }
+
+    pub fn extended_response(mut self, enabled: bool) -> Self {
+        self.config.extended_response = Some(enabled);
+        self
+    }
+
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.config.max_attempts = Some(attempts);
+        self
+    }
+
+    pub fn max_solve_attempts(mut self, attempts: u32) -> Self {
+        self.config.max_solve_attempts = Some(attempts);
+        self
+    }
+
+    pub fn max_in_flight(mut self, solves: usize) -> Self {
+        self.config.max_in_flight = Some(solves);
+        self
+    }
+
+    /// Set any other configuration field
+    pub fn configure(mut self, configure: impl FnOnce(&mut TwoCaptchaConfig)) -> Self {
+        configure(&mut self.config);
+        self
+    }
+
+    /// Create the client, failing on settings it could not work with, see
+    /// [`TwoCaptchaConfig::validate`]
+    pub fn build(self) -> Result<TwoCaptcha> {
+        if self.api_key.expose().trim().is_empty() {
+            return Err(invalid("the API key is empty"));
+        }
+        self.config.validate()?;
+
+        Ok(TwoCaptcha::new(self.api_key, self.config))
+    }
+}
+
+impl TwoCaptchaConfig {
+    /// Reject settings the client could not work with
+    ///
+    /// Run by [`TwoCaptchaBuilder::build`]; call it directly when creating
+    /// the client with [`TwoCaptcha::new`] or [`TwoCaptcha::update_config`].
+    pub fn validate(&self) -> Result<()> {
+        if let Some(server) = &self.server {
+            Utils::check_api_server("server", server)?;
+        }
+        if let Some(server) = &self.secondary_server {
+            Utils::check_api_server("secondary_server", server)?;
+        }
+        if let Some(callback) = &self.callback {
+            Utils::check_url("callback", callback).map_err(|_| {
+                invalid("callback must be an http(s) URL such as https://example.com/pingback")
+            })?;
+        }
+        if self.soft_id == Some(0) {
+            return Err(invalid(
+                "soft_id must not be 0; leave it unset to use the library's default",
+            ));
+        }
+
+        let durations = [
+            ("default_timeout", self.default_timeout),
+            ("recaptcha_timeout", self.recaptcha_timeout),
+            ("polling_interval", self.polling_interval),
+        ];
+        if let Some((name, _)) = durations
+            .iter()
+            .find(|(_, duration)| *duration == Some(Duration::ZERO))
+        {
+            return Err(invalid(&format!("{name} must not be zero")));
+        }
+
+        // A timeout shorter than one polling interval ends before the first poll
+        let interval = self.polling_interval.unwrap_or(POLLING_INTERVAL);
+        let timeouts = [
+            (
+                "default_timeout",
+                self.default_timeout.unwrap_or(DEFAULT_TIMEOUT),
+            ),
+            (
+                "recaptcha_timeout",
+                self.recaptcha_timeout.unwrap_or(RECAPTCHA_TIMEOUT),
+            ),
+        ];
+        let profiles = self.timeout_profiles.iter().flatten();
+        for (method, profile) in profiles {
+            if profile.timeout == Some(Duration::ZERO)
+                || profile.polling_interval == Some(Duration::ZERO)
+            {
+                return Err(invalid(&format!(
+                    "the timeout profile of `{method}` must not have zero durations"
+                )));
+            }
+            let interval = profile.polling_interval.unwrap_or(interval);
+            if let Some(timeout) = profile.timeout
+                && timeout < interval
+            {
+                return Err(invalid(&format!(
+                    "the timeout of `{method}` ({timeout:?}) is shorter than its polling interval ({interval:?})"
+                )));
+            }
+        }
+        if let Some((name, timeout)) = timeouts.iter().find(|(_, timeout)| *timeout < interval) {
+            return Err(invalid(&format!(
+                "{name} ({timeout:?}) is shorter than polling_interval ({interval:?}), \
+                 so no result would ever be polled; raise {name} or lower polling_interval"
+            )));
+        }
+
+        for (name, profile) in self.profiles.iter().flatten() {
+            let durations = [
+                profile.default_timeout,
+                profile.recaptcha_timeout,
+                profile.polling_interval,
+            ];
+            if durations.contains(&Some(Duration::ZERO))
+                || profile.soft_id == Some(0)
+                || profile.max_in_flight == Some(0)
+                || profile.max_attempts == Some(0)
+            {
+                return Err(invalid(&format!(
+                    "profile `{name}` has a zero duration, soft_id, max_in_flight or max_attempts"
+                )));
+            }
+            if let Some(callback) = &profile.callback {
+                Utils::check_url("callback", callback).map_err(|_| {
+                    invalid(&format!(
+                        "the callback of profile `{name}` must be an http(s) URL"
+                    ))
+                })?;
+            }
+        }
+
+        if let Some(retry) = &self.submit_retry
+            && (retry.max_attempts == Some(0)
+                || retry.delay == Some(Duration::ZERO)
+                || retry.max_delay == Some(Duration::ZERO))
+        {
+            return Err(invalid(
+                "submit_retry must have at least 1 attempt and non-zero delays",
+            ));
+        }
+        if let Some(breaker) = &self.circuit_breaker
+            && (breaker.failures == Some(0) || breaker.cool_down == Some(Duration::ZERO))
+        {
+            return Err(invalid(
+                "circuit_breaker must have at least 1 failure and a non-zero cool-down",
+            ));
+        }
+        if let Some(cool_down) = &self.cool_down
+            && (cool_down.duration == Some(Duration::ZERO) || cool_down.poll_slowdown == Some(0))
+        {
+            return Err(invalid(
+                "cool_down must have a non-zero duration and poll_slowdown",
+            ));
+        }
+        if self.max_attempts == Some(0) {
+            return Err(invalid("max_attempts must be at least 1"));
+        }
+        if self.max_solve_attempts == Some(0) {
+            return Err(invalid("max_solve_attempts must be at least 1"));
+        }
+        // A semaphore without permits would never let a solve through
+        if self.max_in_flight == Some(0) {
+            return Err(invalid("max_in_flight must be at least 1"));
+        }
+        if self.max_file_reads == Some(0) {
+            return Err(invalid("max_file_reads must be at least 1"));
+        }
+        Ok(())
+    }
+}
+
+fn invalid(message: &str) -> TwoCaptchaError {
+    TwoCaptchaError::Validation(format!("Invalid configuration: {message}"))
+}
+
+impl TwoCaptcha {
+    /// Start building a client, see [`TwoCaptchaBuilder`]
+    pub fn builder(api_key: impl Into<ApiKey>) -> TwoCaptchaBuilder {
+        TwoCaptchaBuilder::new(api_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_validates() {
+        let solver = TwoCaptcha::builder("key")
+            .server("http://127.0.0.1:8080")
+            .max_attempts(3)
+            .configure(|config| config.json_submit = Some(true))
+            .build()
+            .unwrap();
+        assert_eq!(solver.redacted_config().max_attempts, 3);
+
+        assert!(TwoCaptcha::builder(" ").build().is_err());
+        let builder = TwoCaptcha::builder("0123456789abcdef");
+        assert!(!format!("{builder:?} {:?}", builder.clone().build().unwrap()).contains("0123"));
+        assert!(TwoCaptcha::builder("key").server("a b").build().is_err());
+        assert!(TwoCaptcha::builder("key").max_in_flight(0).build().is_err());
+        assert!(
+            TwoCaptcha::builder("key")
+                .polling_interval(Duration::ZERO)
+                .build()
+                .is_err()
+        );
+
+        let error = TwoCaptchaConfig {
+            default_timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        }
+        .validate()
+        .unwrap_err();
+        assert!(error.to_string().contains("shorter than polling_interval"));
+        assert!(TwoCaptcha::builder("key").soft_id(0).build().is_err());
+        assert!(
+            TwoCaptcha::builder("key")
+                .callback("ftp://example.com")
+                .build()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Fault injection for testing retry and budget policies
+//!
+//! Enabled with the `test-util` feature. [`ChaosLayer`] wraps a transport
+//! and, at configurable probabilities, delays requests, drops responses,
+//! garbles bodies or answers with API error codes:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use twocaptcha::{ChaosLayer, TwoCaptcha, TwoCaptchaConfig};
+//!
+//! let chaos = ChaosLayer::new(Arc::new(reqwest::Client::new()))
+//!     .delay(0.2, Duration::from_secs(3))
+//!     .drop_responses(0.05)
+//!     .api_error(0.1, "ERROR_NO_SLOT_AVAILABLE");
+//! let config = TwoCaptchaConfig {
+//!     transport: Some(Arc::new(chaos)),
+//!     ..Default::default()
+//! };
+//! let solver = TwoCaptcha::new("YOUR_API_KEY".to_string(), config);
+//! ```
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use indexmap::IndexMap;
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::api::{HttpTransport, Params, TransportResponse};
+use crate::backoff::{JitterSource, RandomJitter};
+use crate::error::{Result, TwoCaptchaError};
+
+/// Body returned in place of a garbled response, as sent by a broken proxy
+const MALFORMED_BODY: &str = "<html><body><h1>502 Bad Gateway</h1>";
+
+/// Transport injecting delays, dropped responses, malformed bodies and API
+/// errors into the requests of another one
+///
+/// Each fault is rolled independently for every request. Pass a
+/// [`SeededJitter`](crate::backoff::SeededJitter) to
+/// [`ChaosLayer::with_random`] to replay the same faults on every run.
+#[derive(Debug)]
+pub struct ChaosLayer {
+    inner: Arc<dyn HttpTransport>,
+    delay: Option<(f64, Duration)>,
+    drop_rate: f64,
+    malformed_rate: f64,
+    api_errors: Vec<(f64, String)>,
+    random: Arc<dyn JitterSource>,
+}
+
+impl ChaosLayer {
+    /// Wrap `inner`, injecting nothing until faults are added
+    pub fn new(inner: Arc<dyn HttpTransport>) -> Self {
+        Self {
+            inner,
+            delay: None,
+            drop_rate: 0.0,
+            malformed_rate: 0.0,
+            api_errors: Vec::new(),
+            random: Arc::new(RandomJitter::default()),
+        }
+    }
+
+    /// Delay requests by `delay` with the given probability
+    pub fn delay(mut self, probability: f64, delay: Duration) -> Self {
+        self.delay = Some((probability, delay));
+        self
+    }
+
+    /// Send requests but fail as if their response was lost
+    pub fn drop_responses(mut self, probability: f64) -> Self {
+        self.drop_rate = probability;
+        self
+    }
+
+    /// Replace response bodies with something no endpoint would return
+    pub fn malformed_bodies(mut self, probability: f64) -> Self {
+        self.malformed_rate = probability;
+        self
+    }
+
+    /// Answer with the API error `code`, e.g. `ERROR_NO_SLOT_AVAILABLE`,
+    /// without sending the request; can be called once per code
+    pub fn api_error(mut self, probability: f64, code: impl Into<String>) -> Self {
+        self.api_errors.push((probability, code.into()));
+        self
+    }
+
+    /// Source of the random rolls (default: seeded per process)
+    pub fn with_random(mut self, random: Arc<dyn JitterSource>) -> Self {
+        self.random = random;
+        self
+    }
+
+    fn roll(&self, probability: f64) -> bool {
+        probability > 0.0 && self.random.next_unit() < probability
+    }
+
+    /// Run `request` with the faults rolled for it
+    async fn inject(
+        &self,
+        json: bool,
+        request: impl Future<Output = Result<TransportResponse>>,
+    ) -> Result<TransportResponse> {
+        if let Some((probability, delay)) = self.delay
+            && self.roll(probability)
+        {
+            tokio::time::sleep(delay).await;
+        }
+
+        if let Some((_, code)) = self.api_errors.iter().find(|(p, _)| self.roll(*p)) {
+            let body = if json {
+                serde_json::json!({ "errorId": 1, "errorCode": code }).to_string()
+            } else {
+                code.clone()
+            };
+            return Ok(TransportResponse {
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+                body,
+            });
+        }
+
+        let mut response = request.await?;
+        if self.roll(self.drop_rate) {
+            return Err(TwoCaptchaError::network("response dropped by ChaosLayer"));
+        }
+        if self.roll(self.malformed_rate) {
+            response.body = MALFORMED_BODY.to_string();
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ChaosLayer {
+    async fn post_form(&self, url: &str, params: &Params) -> Result<TransportResponse> {
+        self.inject(false, self.inner.post_form(url, params)).await
+    }
+
+    async fn post_multipart(
+        &self,
+        url: &str,
+        params: &Params,
+        files: IndexMap<String, Bytes>,
+    ) -> Result<TransportResponse> {
+        self.inject(false, self.inner.post_multipart(url, params, files))
+            .await
+    }
+
+    async fn post_json(&self, url: &str, body: String) -> Result<TransportResponse> {
+        self.inject(true, self.inner.post_json(url, body)).await
+    }
+
+    async fn get(&self, url: &str, query: &Params) -> Result<TransportResponse> {
+        self.inject(false, self.inner.get(url, query)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ApiClient;
+    use crate::backoff::SeededJitter;
+    use crate::scripted::Scripted;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_chaos_layer() {
+        let client = |chaos: ChaosLayer| ApiClient::with_transport(Arc::new(chaos), None);
+
+        let error = client(
+            ChaosLayer::new(Arc::new(Scripted::canned("OK|12345")))
+                .api_error(1.0, "ERROR_NO_SLOT_AVAILABLE"),
+        )
+        .in_(None, HashMap::new())
+        .await
+        .unwrap_err();
+        assert_eq!(error.attempt_code(), "ERROR_NO_SLOT_AVAILABLE");
+
+        let dropped =
+            client(ChaosLayer::new(Arc::new(Scripted::canned("OK|12345"))).drop_responses(1.0));
+        assert!(dropped.res(HashMap::new()).await.is_err());
+
+        // Half the requests fail, the same ones for the same seed
+        let outcomes = |seed| async move {
+            let flaky = client(
+                ChaosLayer::new(Arc::new(Scripted::canned("OK|12345")))
+                    .drop_responses(0.5)
+                    .with_random(Arc::new(SeededJitter::new(seed))),
+            );
+            let mut outcomes = Vec::new();
+            for _ in 0..20 {
+                outcomes.push(flaky.res(HashMap::new()).await.is_ok());
+            }
+            outcomes
+        };
+        let first = outcomes(7).await;
+        assert_eq!(first, outcomes(7).await);
+        assert!(first.contains(&true) && first.contains(&false));
+    }
+}
@@ -0,0 +1,102 @@
+use std::fmt::Debug;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Source of monotonic time, replaced in tests to control its passing
+pub(crate) trait Clock: Debug + Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// The system's monotonic clock
+#[derive(Debug, Default)]
+pub(crate) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock that only moves when told to
+#[cfg(test)]
+#[derive(Debug)]
+pub(crate) struct ManualClock(std::sync::Mutex<Instant>);
+
+#[cfg(test)]
+impl ManualClock {
+    pub(crate) fn new() -> Self {
+        Self(std::sync::Mutex::new(Instant::now()))
+    }
+
+    pub(crate) fn advance(&self, by: Duration) {
+        *self.0.lock().unwrap() += by;
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// A point in time on both the monotonic and the wall clock
+///
+/// The monotonic clock is immune to NTP jumps and manual changes, but on
+/// some platforms it stops while the machine is suspended. Ages are taken
+/// as the larger of both clocks, ignoring a wall clock that went back, so
+/// an entry never looks younger than it is after a suspend or a clock step.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Timestamp {
+    monotonic: Instant,
+    wall: SystemTime,
+}
+
+impl Timestamp {
+    pub(crate) fn now() -> Self {
+        Self {
+            monotonic: Instant::now(),
+            wall: SystemTime::now(),
+        }
+    }
+
+    /// Time elapsed since this timestamp
+    pub(crate) fn age(&self) -> Duration {
+        let wall = SystemTime::now()
+            .duration_since(self.wall)
+            .unwrap_or_default();
+        self.monotonic.elapsed().max(wall)
+    }
+
+    /// Whether at least `ttl` elapsed since this timestamp
+    pub(crate) fn expired(&self, ttl: Duration) -> bool {
+        self.age() >= ttl
+    }
+
+    /// Unix time (seconds) of this timestamp, for display only
+    pub(crate) fn unix_secs(&self) -> u64 {
+        self.wall
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_age_takes_the_larger_clock() {
+        let mut stamp = Timestamp::now();
+        assert!(!stamp.expired(Duration::from_secs(60)));
+
+        // As after a suspend the monotonic clock did not see
+        stamp.wall -= Duration::from_secs(120);
+        assert!(stamp.age() >= Duration::from_secs(120));
+        assert!(stamp.expired(Duration::from_secs(60)));
+
+        // A wall clock stepped back is ignored
+        stamp.wall += Duration::from_secs(3600);
+        assert!(stamp.age() < Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,479 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::error::{Result, TwoCaptchaError};
+use crate::solver::{TwoCaptcha, TwoCaptchaConfig};
+use crate::types::Proxy;
+
+/// Timeout and polling interval of one captcha method, see
+/// [`TwoCaptchaConfig::timeout_profiles`]
+///
+/// Unset fields fall back to `default_timeout` and `polling_interval`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutProfile {
+    #[serde(with = "duration_serde")]
+    pub timeout: Option<Duration>,
+    #[serde(with = "duration_serde")]
+    pub polling_interval: Option<Duration>,
+}
+
+impl TimeoutProfile {
+    pub fn new(timeout: Duration, polling_interval: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            polling_interval: Some(polling_interval),
+        }
+    }
+
+    /// Built-in profiles, keyed by `method`, for token captchas that take
+    /// longer than the 120s default
+    ///
+    /// reCAPTCHA (`userrecaptcha`) uses `recaptcha_timeout` instead.
+    pub fn defaults() -> HashMap<String, TimeoutProfile> {
+        let slow = Self::new(Duration::from_secs(300), Duration::from_secs(10));
+        [
+            ("hcaptcha", slow),
+            ("funcaptcha", slow),
+            ("geetest", slow),
+            ("geetest_v4", slow),
+            (
+                "turnstile",
+                Self::new(Duration::from_secs(180), Duration::from_secs(5)),
+            ),
+        ]
+        .into_iter()
+        .map(|(method, profile)| (method.to_string(), profile))
+        .collect()
+    }
+}
+
+/// Resubmission of tasks refused with `ERROR_NO_SLOT_AVAILABLE`, see
+/// [`TwoCaptchaConfig::submit_retry`]
+///
+/// Delays double after each refusal, with random jitter so clients
+/// refused together do not come back together.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SubmitRetry {
+    /// Submissions in total, the first included (default: 5)
+    pub max_attempts: Option<u32>,
+    /// Delay before the first resubmission (default: 5s)
+    #[serde(with = "duration_serde")]
+    pub delay: Option<Duration>,
+    /// Longest delay between resubmissions (default: 60s)
+    #[serde(with = "duration_serde")]
+    pub max_delay: Option<Duration>,
+}
+
+impl SubmitRetry {
+    pub(crate) fn attempts(&self) -> u32 {
+        self.max_attempts.unwrap_or(5).max(1)
+    }
+
+    /// Delay before resubmission number `retry`, starting at 1
+    pub(crate) fn delay(&self, retry: u32) -> Duration {
+        let delay = self.delay.unwrap_or(Duration::from_secs(5));
+        let max_delay = self.max_delay.unwrap_or(Duration::from_secs(60));
+        delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(max_delay)
+    }
+}
+
+/// Fail-fast after repeated auth or network failures, see
+/// [`TwoCaptchaConfig::circuit_breaker`]
+///
+/// While the circuit is open, submissions and result polls fail with
+/// [`TwoCaptchaError::CircuitOpen`] without reaching the API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitBreaker {
+    /// Consecutive failures opening the circuit (default: 5)
+    pub failures: Option<u32>,
+    /// How long the circuit stays open (default: 30s)
+    #[serde(with = "duration_serde")]
+    pub cool_down: Option<Duration>,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn failures(&self) -> u32 {
+        self.failures.unwrap_or(5).max(1)
+    }
+
+    pub(crate) fn cool_down(&self) -> Duration {
+        self.cool_down.unwrap_or(Duration::from_secs(30))
+    }
+}
+
+/// Pause after the API throttles or bans the client (`MAX_USER_TURN`,
+/// `ERROR_IP_NOT_ALLOWED`, `IP_BANNED`), see [`TwoCaptchaConfig::cool_down`]
+///
+/// During the pause new submissions wait and result polls slow down,
+/// instead of every task failing at once. The current state is available
+/// from [`TwoCaptcha::cool_down`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CoolDown {
+    /// Length of the pause (default: 60s)
+    #[serde(with = "duration_serde")]
+    pub duration: Option<Duration>,
+    /// Factor result polls are slowed down by during the pause (default: 3)
+    pub poll_slowdown: Option<u32>,
+    /// Pauses a submission waits out before failing (default: 3)
+    pub max_waits: Option<u32>,
+}
+
+impl CoolDown {
+    pub(crate) fn duration(&self) -> Duration {
+        self.duration.unwrap_or(Duration::from_secs(60))
+    }
+
+    pub(crate) fn poll_slowdown(&self) -> u32 {
+        self.poll_slowdown.unwrap_or(3).max(1)
+    }
+
+    pub(crate) fn max_waits(&self) -> u32 {
+        self.max_waits.unwrap_or(3)
+    }
+}
+
+/// Named set of overrides, selected with [`TwoCaptcha::with_profile`]
+///
+/// ```toml
+/// polling_interval = 10
+///
+/// [profiles.prod]
+/// polling_interval = 3
+/// max_in_flight = 50
+///
+/// [profiles.dev]
+/// default_timeout = "30s"
+/// callback = "https://staging.example.com/pingback"
+/// ```
+///
+/// Unset fields keep the value of the base configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigProfile {
+    pub soft_id: Option<u32>,
+    pub callback: Option<String>,
+    #[serde(with = "duration_serde")]
+    pub default_timeout: Option<Duration>,
+    #[serde(with = "duration_serde")]
+    pub recaptcha_timeout: Option<Duration>,
+    #[serde(with = "duration_serde")]
+    pub polling_interval: Option<Duration>,
+    /// Cap on concurrent solves through clients of this profile, counted
+    /// apart from the base client's
+    pub max_in_flight: Option<usize>,
+    pub max_attempts: Option<u32>,
+}
+
+/// Per-scope defaults of a child client, see [`TwoCaptcha::scoped`]
+///
+/// Unset fields keep the value of the parent client.
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    pub(crate) profile: ConfigProfile,
+    pub(crate) proxy: Option<Proxy>,
+}
+
+impl Scope {
+    pub fn soft_id(mut self, soft_id: u32) -> Self {
+        self.profile.soft_id = Some(soft_id);
+        self
+    }
+
+    pub fn callback(mut self, url: impl Into<String>) -> Self {
+        self.profile.callback = Some(url.into());
+        self
+    }
+
+    /// Timeouts of regular captchas and of reCAPTCHA
+    pub fn timeouts(mut self, default: Duration, recaptcha: Duration) -> Self {
+        self.profile.default_timeout = Some(default);
+        self.profile.recaptcha_timeout = Some(recaptcha);
+        self
+    }
+
+    pub fn polling_interval(mut self, interval: Duration) -> Self {
+        self.profile.polling_interval = Some(interval);
+        self
+    }
+
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.profile.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Proxy sent with token captchas (those with a page URL) that do not
+    /// set one themselves
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+}
+
+/// Environment variable holding the API key, read by [`TwoCaptcha::from_env`]
+pub const API_KEY_VAR: &str = "TWOCAPTCHA_API_KEY";
+
+impl TwoCaptchaConfig {
+    /// Read settings from `TWOCAPTCHA_*` environment variables
+    ///
+    /// | Variable | Setting |
+    /// |---|---|
+    /// | `TWOCAPTCHA_SERVER` | `server` |
+    /// | `TWOCAPTCHA_SOFT_ID` | `soft_id` |
+    /// | `TWOCAPTCHA_CALLBACK` | `callback` |
+    /// | `TWOCAPTCHA_DEFAULT_TIMEOUT` | `default_timeout` |
+    /// | `TWOCAPTCHA_RECAPTCHA_TIMEOUT` | `recaptcha_timeout` |
+    /// | `TWOCAPTCHA_POLLING_INTERVAL` | `polling_interval` |
+    /// | `TWOCAPTCHA_MAX_ATTEMPTS` | `max_attempts` |
+    /// | `TWOCAPTCHA_MAX_SOLVE_ATTEMPTS` | `max_solve_attempts` |
+    ///
+    /// Durations are seconds, or a number with an `ms`, `s`, `m` or `h`
+    /// suffix. Unset or empty variables leave the default; malformed ones
+    /// are a validation error naming the variable.
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let var = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+        let duration = |name: &str| {
+            var(name)
+                .map(|value| {
+                    parse_duration(&value).ok_or_else(|| {
+                        invalid_var(name, "a duration such as `90`, `90s` or `2m`", &value)
+                    })
+                })
+                .transpose()
+        };
+
+        Ok(Self {
+            server: var("TWOCAPTCHA_SERVER"),
+            soft_id: parse_var(&var, "TWOCAPTCHA_SOFT_ID")?,
+            callback: var("TWOCAPTCHA_CALLBACK"),
+            default_timeout: duration("TWOCAPTCHA_DEFAULT_TIMEOUT")?,
+            recaptcha_timeout: duration("TWOCAPTCHA_RECAPTCHA_TIMEOUT")?,
+            polling_interval: duration("TWOCAPTCHA_POLLING_INTERVAL")?,
+            max_attempts: parse_var(&var, "TWOCAPTCHA_MAX_ATTEMPTS")?,
+            max_solve_attempts: parse_var(&var, "TWOCAPTCHA_MAX_SOLVE_ATTEMPTS")?,
+            ..Self::default()
+        })
+    }
+}
+
+#[cfg(feature = "config-file")]
+impl TwoCaptchaConfig {
+    /// Read settings from a TOML (`.toml`) or YAML (`.yaml`, `.yml`) file
+    ///
+    /// ```toml
+    /// server = "2captcha.com"
+    /// soft_id = 4580
+    /// callback = "https://example.com/pingback"
+    /// default_timeout = "120s"
+    /// recaptcha_timeout = "10m"
+    /// polling_interval = 5
+    /// max_in_flight = 8
+    /// max_file_reads = 4
+    /// max_attempts = 3
+    /// ```
+    ///
+    /// Every field is optional and read like the serde representation of
+    /// [`TwoCaptchaConfig`]. Unknown fields are refused, so a misspelled
+    /// setting does not go unnoticed.
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let invalid = |e: &dyn std::fmt::Display| {
+            TwoCaptchaError::Validation(format!("{}: {e}", path.display()))
+        };
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&text).map_err(|e| invalid(&e)),
+            Some("yaml" | "yml") => serde_yaml::from_str(&text).map_err(|e| invalid(&e)),
+            _ => Err(invalid(&"expected a .toml, .yaml or .yml file")),
+        }
+    }
+}
+
+/// Optional durations written as seconds, read from seconds or from strings
+/// like `"90s"`
+pub(crate) mod duration_serde {
+    use serde::{Deserialize, Deserializer, Serializer, de};
+    use std::time::Duration;
+
+    pub(crate) fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            None => serializer.serialize_none(),
+            Some(duration) if duration.subsec_nanos() == 0 => {
+                serializer.serialize_some(&duration.as_secs())
+            }
+            Some(duration) => serializer.serialize_some(&duration.as_secs_f64()),
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Seconds(f64),
+        Text(String),
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        let duration = match Option::<Raw>::deserialize(deserializer)? {
+            None => return Ok(None),
+            Some(Raw::Seconds(seconds)) => Duration::try_from_secs_f64(seconds).ok(),
+            Some(Raw::Text(text)) => super::parse_duration(&text),
+        };
+        duration
+            .map(Some)
+            .ok_or_else(|| de::Error::custom("expected a duration such as 90, \"90s\" or \"2m\""))
+    }
+}
+
+impl TwoCaptcha {
+    /// Create a client from `TWOCAPTCHA_API_KEY` and the settings read by
+    /// [`TwoCaptchaConfig::from_env`], validated like
+    /// [`TwoCaptchaBuilder::build`](crate::TwoCaptchaBuilder::build)
+    pub fn from_env() -> Result<Self> {
+        let api_key = std::env::var(API_KEY_VAR)
+            .map_err(|_| TwoCaptchaError::Validation(format!("{API_KEY_VAR} is not set")))?;
+        let config = TwoCaptchaConfig::from_env()?;
+        TwoCaptcha::builder(api_key)
+            .configure(|current| *current = config)
+            .build()
+    }
+}
+
+/// Parse `90`, `1.5`, `90s`, `1500ms`, `2m` or `1h` into a duration
+pub(crate) fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let seconds = match unit.trim() {
+        "" | "s" => number,
+        "ms" => number / 1000.0,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+fn parse_var<T: FromStr>(var: &impl Fn(&str) -> Option<String>, name: &str) -> Result<Option<T>> {
+    var(name)
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| invalid_var(name, "an integer", &value))
+        })
+        .transpose()
+}
+
+fn invalid_var(name: &str, expected: &str, value: &str) -> TwoCaptchaError {
+    TwoCaptchaError::Validation(format!("{name} must be {expected}, got `{value}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_from_vars() {
+        let vars = HashMap::from([
+            ("TWOCAPTCHA_SERVER", "http://127.0.0.1:8080"),
+            ("TWOCAPTCHA_SOFT_ID", "123"),
+            ("TWOCAPTCHA_CALLBACK", ""),
+            ("TWOCAPTCHA_DEFAULT_TIMEOUT", "90s"),
+            ("TWOCAPTCHA_POLLING_INTERVAL", "1500ms"),
+        ]);
+        let config =
+            TwoCaptchaConfig::from_vars(|name| vars.get(name).map(|v| v.to_string())).unwrap();
+        assert_eq!(config.server.as_deref(), Some("http://127.0.0.1:8080"));
+        assert_eq!(config.soft_id, Some(123));
+        assert_eq!(config.callback, None);
+        assert_eq!(config.default_timeout, Some(Duration::from_secs(90)));
+        assert_eq!(config.polling_interval, Some(Duration::from_millis(1500)));
+
+        let error = TwoCaptchaConfig::from_vars(|name| {
+            (name == "TWOCAPTCHA_RECAPTCHA_TIMEOUT").then(|| "soon".to_string())
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("TWOCAPTCHA_RECAPTCHA_TIMEOUT"));
+
+        assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_duration("-5"), None);
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let config = TwoCaptchaConfig {
+            soft_id: Some(4580),
+            default_timeout: Some(Duration::from_secs(90)),
+            polling_interval: Some(Duration::from_millis(2500)),
+            endpoint: Some(crate::Endpoint::Cn),
+            api_version: Some(crate::ApiVersion::Auto),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["default_timeout"], 90);
+        assert_eq!(json["polling_interval"], 2.5);
+        assert_eq!(json["endpoint"], "cn");
+
+        let back: TwoCaptchaConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(back.soft_id, Some(4580));
+        assert_eq!(back.default_timeout, Some(Duration::from_secs(90)));
+        assert_eq!(back.polling_interval, Some(Duration::from_millis(2500)));
+        assert_eq!(back.endpoint, Some(crate::Endpoint::Cn));
+        assert_eq!(back.api_version, Some(crate::ApiVersion::Auto));
+
+        let embedded: TwoCaptchaConfig =
+            serde_json::from_str(r#"{"recaptcha_timeout": "10m"}"#).unwrap();
+        assert_eq!(embedded.recaptcha_timeout, Some(Duration::from_secs(600)));
+    }
+
+    #[cfg(feature = "config-file")]
+    #[test]
+    fn test_from_file() {
+        let dir = std::env::temp_dir().join(format!("twocaptcha_config_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let toml = dir.join("config.toml");
+        std::fs::write(
+            &toml,
+            "soft_id = 7\ndefault_timeout = \"90s\"\npolling_interval = 2.5\n",
+        )
+        .unwrap();
+        let config = TwoCaptchaConfig::from_file(&toml).unwrap();
+        assert_eq!(config.soft_id, Some(7));
+        assert_eq!(config.default_timeout, Some(Duration::from_secs(90)));
+        assert_eq!(config.polling_interval, Some(Duration::from_millis(2500)));
+
+        let yaml = dir.join("config.yaml");
+        std::fs::write(&yaml, "max_attempts: 3\nrecaptcha_timeout: 10m\n").unwrap();
+        let config = TwoCaptchaConfig::from_file(&yaml).unwrap();
+        assert_eq!(config.max_attempts, Some(3));
+        assert_eq!(config.recaptcha_timeout, Some(Duration::from_secs(600)));
+
+        std::fs::write(&yaml, "polling_interval: soon\n").unwrap();
+        assert!(TwoCaptchaConfig::from_file(&yaml).is_err());
+        std::fs::write(&yaml, "max_attempt: 3\n").unwrap();
+        assert!(TwoCaptchaConfig::from_file(&yaml).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
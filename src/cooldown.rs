@@ -0,0 +1,113 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SystemClock};
+use crate::config::CoolDown;
+use crate::error::{ApiErrorCode, TwoCaptchaError};
+
+/// Where a client's cool-down stands, see
+/// [`TwoCaptcha::cool_down`](crate::TwoCaptcha::cool_down)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoolDownStatus {
+    /// Error code that started the cool-down, e.g. `MAX_USER_TURN`
+    pub reason: ApiErrorCode,
+    /// Time left before submissions resume
+    pub remaining: Duration,
+}
+
+/// Pause of a client the API throttled or banned, shared by its clones
+#[derive(Debug)]
+pub(crate) struct CoolDownState {
+    clock: Arc<dyn Clock>,
+    state: Mutex<Option<(Instant, ApiErrorCode)>>,
+}
+
+impl Default for CoolDownState {
+    fn default() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+}
+
+impl CoolDownState {
+    pub(crate) fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Start or extend a cool-down if `error` is a throttle or IP ban,
+    /// returning its length
+    pub(crate) fn enter(&self, config: &CoolDown, error: &TwoCaptchaError) -> Option<Duration> {
+        let code = error.api_code().filter(|code| {
+            matches!(
+                code,
+                ApiErrorCode::MaxUserTurn | ApiErrorCode::IpNotAllowed | ApiErrorCode::IpBanned
+            )
+        })?;
+        let until = self.clock.now() + config.duration();
+        let mut state = self.state.lock().unwrap();
+        if state.as_ref().is_none_or(|(current, _)| *current < until) {
+            *state = Some((until, code));
+        }
+        Some(config.duration())
+    }
+
+    pub(crate) fn status(&self) -> Option<CoolDownStatus> {
+        let state = self.state.lock().unwrap();
+        let (until, reason) = state.as_ref()?;
+        let remaining = until.checked_duration_since(self.clock.now())?;
+        (!remaining.is_zero()).then(|| CoolDownStatus {
+            reason: reason.clone(),
+            remaining,
+        })
+    }
+
+    /// Delay between result polls, slowed down during a cool-down
+    pub(crate) fn poll_interval(&self, config: Option<&CoolDown>, interval: Duration) -> Duration {
+        match config {
+            Some(config) if self.status().is_some() => {
+                interval.saturating_mul(config.poll_slowdown())
+            }
+            _ => interval,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn test_cool_down() {
+        let clock = Arc::new(ManualClock::new());
+        let state = CoolDownState::with_clock(clock.clone());
+        let config = CoolDown {
+            duration: Some(Duration::from_secs(60)),
+            poll_slowdown: Some(3),
+            max_waits: None,
+        };
+        let interval = Duration::from_secs(5);
+
+        assert_eq!(
+            state.enter(&config, &TwoCaptchaError::api("ERROR_ZERO_BALANCE")),
+            None
+        );
+        assert_eq!(state.poll_interval(Some(&config), interval), interval);
+
+        let pause = state.enter(&config, &TwoCaptchaError::api("MAX_USER_TURN"));
+        assert_eq!(pause, Some(Duration::from_secs(60)));
+        let status = state.status().unwrap();
+        assert_eq!(status.reason, ApiErrorCode::MaxUserTurn);
+        assert_eq!(status.remaining, Duration::from_secs(60));
+        assert_eq!(
+            state.poll_interval(Some(&config), interval),
+            Duration::from_secs(15)
+        );
+
+        clock.advance(Duration::from_secs(60));
+        assert!(state.status().is_none());
+        assert_eq!(state.poll_interval(Some(&config), interval), interval);
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::clock::Timestamp;
+use crate::types::CaptchaResult;
+
+/// Recently solved payloads, keyed by payload hash
+///
+/// Used to answer resubmissions of an identical image from memory instead
+/// of paying for a second solve.
+#[derive(Debug, Default)]
+pub(crate) struct Deduper {
+    entries: Mutex<HashMap<String, (Timestamp, CaptchaResult)>>,
+}
+
+impl Deduper {
+    /// Get the result of a payload solved less than `ttl` ago
+    pub(crate) fn get(&self, hash: &str, ttl: Duration) -> Option<CaptchaResult> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(hash)
+            .filter(|(solved_at, _)| !solved_at.expired(ttl))
+            .map(|(_, result)| result.clone())
+    }
+
+    /// Remember the result of a payload, dropping entries older than `ttl`
+    pub(crate) fn insert(&self, hash: String, result: CaptchaResult, ttl: Duration) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (solved_at, _)| !solved_at.expired(ttl));
+        entries.insert(hash, (Timestamp::now(), result));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deduper_expiry() {
+        let deduper = Deduper::default();
+        let result = CaptchaResult {
+            captcha_id: "123".to_string(),
+            code: Some("answer".to_string()),
+            ..Default::default()
+        };
+        deduper.insert("hash".to_string(), result, Duration::from_secs(60));
+
+        let cached = deduper.get("hash", Duration::from_secs(60)).unwrap();
+        assert_eq!(cached.code.as_deref(), Some("answer"));
+        assert!(deduper.get("hash", Duration::ZERO).is_none());
+        assert!(deduper.get("other", Duration::from_secs(60)).is_none());
+    }
+}
@@ -0,0 +1,200 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::clock::Timestamp;
+use crate::metrics::BandwidthUsage;
+use crate::solver::TwoCaptcha;
+
+/// Number of errors kept for diagnostics
+const MAX_RECENT_ERRORS: usize = 20;
+
+/// Snapshot of a client's state to attach to bug reports
+///
+/// The API key is masked and error messages are scrubbed of it, so the
+/// JSON produced by [`DiagnosticsBundle::to_json`] is safe to share.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsBundle {
+    /// Version of this crate
+    pub version: String,
+    /// Unix timestamp (seconds) of the collection
+    pub collected_at: u64,
+    pub config: RedactedConfig,
+    pub timings: SolveTimings,
+    pub bandwidth: BandwidthUsage,
+    /// Responses that only parsed after a JSON repair
+    pub json_repairs: u64,
+    /// Latest errors, oldest first
+    pub recent_errors: Vec<ErrorRecord>,
+}
+
+impl DiagnosticsBundle {
+    /// Gather diagnostics from a client
+    pub fn collect(client: &TwoCaptcha) -> Self {
+        let telemetry = client.telemetry().lock().unwrap();
+
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            collected_at: Timestamp::now().unix_secs(),
+            config: client.redacted_config(),
+            timings: telemetry.timings.clone(),
+            bandwidth: client.bandwidth().total(),
+            json_repairs: client.json_repairs(),
+            recent_errors: telemetry.errors.iter().cloned().collect(),
+        }
+    }
+
+    /// Render the bundle as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("diagnostics are always serializable")
+    }
+}
+
+/// Client configuration with secrets masked
+#[derive(Debug, Clone, Serialize)]
+pub struct RedactedConfig {
+    /// Last characters of the API key only
+    pub api_key: String,
+    pub server: String,
+    pub soft_id: Option<u32>,
+    /// Whether a callback URL is configured (the URL itself is not included)
+    pub callback: bool,
+    pub default_timeout_ms: u64,
+    pub recaptcha_timeout_ms: u64,
+    pub polling_interval_ms: u64,
+    pub extended_response: bool,
+    pub detect_base64: bool,
+    pub hash_payloads: bool,
+    pub dedupe_window_ms: Option<u64>,
+    pub max_task_cost: Option<f64>,
+    pub max_attempts: u32,
+    pub max_solve_attempts: Option<u32>,
+}
+
+/// Duration statistics of completed solves
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SolveTimings {
+    pub solves: u64,
+    pub failures: u64,
+    pub min_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+    pub mean_ms: Option<u64>,
+    #[serde(skip)]
+    total_ms: u64,
+}
+
+impl SolveTimings {
+    fn record(&mut self, elapsed: Duration, failed: bool) {
+        let ms = elapsed.as_millis() as u64;
+
+        self.solves += 1;
+        if failed {
+            self.failures += 1;
+        }
+        self.total_ms += ms;
+        self.min_ms = Some(self.min_ms.map_or(ms, |min| min.min(ms)));
+        self.max_ms = Some(self.max_ms.map_or(ms, |max| max.max(ms)));
+        self.mean_ms = Some(self.total_ms / self.solves);
+    }
+}
+
+/// An error returned to the caller, or a warning about a request
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorRecord {
+    /// Unix timestamp (seconds) of the error
+    pub at: u64,
+    /// Operation that failed, e.g. `solve` or `submit`, or `params` for
+    /// conflicting parameter aliases
+    pub operation: &'static str,
+    pub message: String,
+    /// Trace id of the solve attempt that failed, see [`Attempt::trace_id`]
+    ///
+    /// [`Attempt::trace_id`]: crate::types::Attempt::trace_id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+}
+
+/// Errors and timings recorded by a client
+#[derive(Debug, Default)]
+pub(crate) struct Telemetry {
+    timings: SolveTimings,
+    errors: VecDeque<ErrorRecord>,
+}
+
+impl Telemetry {
+    /// Record the duration of a solve
+    pub(crate) fn record_solve(&mut self, elapsed: Duration, failed: bool) {
+        self.timings.record(elapsed, failed);
+    }
+
+    /// Record an error, replacing occurrences of `secret` in its message
+    pub(crate) fn record_error(&mut self, operation: &'static str, message: &str, secret: &str) {
+        self.record_traced_error(operation, message, secret, None);
+    }
+
+    /// Record an error of the solve attempt `trace_id`
+    pub(crate) fn record_traced_error(
+        &mut self,
+        operation: &'static str,
+        message: &str,
+        secret: &str,
+        trace_id: Option<&str>,
+    ) {
+        let message = if secret.is_empty() {
+            message.to_string()
+        } else {
+            message.replace(secret, "[redacted]")
+        };
+
+        if self.errors.len() == MAX_RECENT_ERRORS {
+            self.errors.pop_front();
+        }
+        self.errors.push_back(ErrorRecord {
+            at: Timestamp::now().unix_secs(),
+            operation,
+            message,
+            trace_id: trace_id.map(str::to_string),
+        });
+    }
+}
+
+/// Mask all but the last four characters of a secret
+pub(crate) fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 4 {
+        return "****".to_string();
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("****{tail}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telemetry_redacts_and_bounds_errors() {
+        let mut telemetry = Telemetry::default();
+        for _ in 0..MAX_RECENT_ERRORS + 5 {
+            telemetry.record_error("solve", "bad request ?key=SECRETKEY", "SECRETKEY");
+        }
+
+        assert_eq!(telemetry.errors.len(), MAX_RECENT_ERRORS);
+        assert_eq!(telemetry.errors[0].message, "bad request ?key=[redacted]");
+        assert_eq!(mask_secret("SECRETKEY"), "****TKEY");
+        assert_eq!(mask_secret("KEY"), "****");
+    }
+
+    #[test]
+    fn test_solve_timings() {
+        let mut timings = SolveTimings::default();
+        timings.record(Duration::from_millis(100), false);
+        timings.record(Duration::from_millis(300), true);
+
+        assert_eq!(timings.solves, 2);
+        assert_eq!(timings.failures, 1);
+        assert_eq!(timings.min_ms, Some(100));
+        assert_eq!(timings.max_ms, Some(300));
+        assert_eq!(timings.mean_ms, Some(200));
+    }
+}
@@ -1,19 +1,67 @@
+use crate::types::AttemptLog;
+use std::fmt;
 use thiserror::Error;
 
 /// Error types for the 2captcha library
+///
+/// Errors of solves come wrapped in [`TwoCaptchaError::WithContext`] and,
+/// with `max_attempts`, [`TwoCaptchaError::Attempts`]. Match on
+/// [`TwoCaptchaError::root`] to find the underlying variant:
+///
+/// ```
+/// # use twocaptcha::{ApiErrorCode, TwoCaptchaError};
+/// fn out_of_funds(error: &TwoCaptchaError) -> bool {
+///     matches!(
+///         error.root(),
+///         TwoCaptchaError::Api { code: Some(ApiErrorCode::ZeroBalance), .. }
+///     )
+/// }
+/// ```
 #[derive(Error, Debug)]
 pub enum TwoCaptchaError {
     #[error("Validation error: {0}")]
     Validation(String),
 
-    #[error("Network error: {0}")]
-    Network(String),
+    /// The API could not be reached or answered with a non-200 status
+    #[error("Network error: {}", describe_network(.status, .body_snippet, .headers))]
+    Network {
+        status: Option<reqwest::StatusCode>,
+        /// Start of the response body, if any
+        body_snippet: String,
+        /// Response headers useful to trace the failure, e.g. `server` or
+        /// `cf-ray`, see [`ApiClient`](crate::ApiClient)
+        headers: Vec<(String, String)>,
+        source: Option<reqwest::Error>,
+    },
 
-    #[error("API error: {0}")]
-    Api(String),
+    /// The API refused the request or could not solve the captcha
+    #[error("API error: {message}")]
+    Api {
+        /// Documented error code found in the response, if any
+        code: Option<ApiErrorCode>,
+        message: String,
+    },
 
-    #[error("Timeout error: {0}")]
-    Timeout(String),
+    /// Workers did not agree on an answer often enough under 100%
+    /// recognition (`ERROR_BAD_DUPLICATES`)
+    ///
+    /// The number of matching answers required is set in the account's
+    /// 100% recognition settings. Submitting the captcha again is charged
+    /// again; `max_solve_attempts` retries it automatically.
+    #[error("API error: ERROR_BAD_DUPLICATES: not enough matching answers for 100% recognition")]
+    BadDuplicates,
+
+    /// No answer arrived in time
+    ///
+    /// The captcha may still be solved: pass `captcha_id` to
+    /// [`TwoCaptcha::resume`](crate::TwoCaptcha::resume) to keep polling
+    /// for the answer that was paid for.
+    #[error("Timeout error: {message}")]
+    Timeout {
+        /// Id of the submitted captcha, if one was assigned
+        captcha_id: Option<String>,
+        message: String,
+    },
 
     #[error("Request error: {0}")]
     Request(#[from] reqwest::Error),
@@ -29,6 +77,489 @@ pub enum TwoCaptchaError {
 
     #[error("URL parse error: {0}")]
     UrlParse(#[from] url::ParseError),
+
+    #[error("Solver has been shut down")]
+    Shutdown,
+
+    /// Requests are refused without reaching the API after repeated auth
+    /// or network failures, see
+    /// [`CircuitBreaker`](crate::config::CircuitBreaker)
+    #[error("Circuit open after repeated auth or network failures, retry in {retry_after:?}")]
+    CircuitOpen {
+        /// Time left before requests are let through again
+        retry_after: std::time::Duration,
+    },
+
+    /// An error of a solve, with what is known of the solve, see
+    /// [`TwoCaptchaError::root`] for the error itself
+    #[error("{source} ({context})")]
+    WithContext {
+        context: Box<ErrorContext>,
+        #[source]
+        source: Box<TwoCaptchaError>,
+    },
+
+    /// Every attempt of a solve failed, see [`TwoCaptchaError::root`] for
+    /// the error of the last one
+    #[error("{last} (after {} attempts)", log.attempts.len())]
+    Attempts {
+        log: AttemptLog,
+        #[source]
+        last: Box<TwoCaptchaError>,
+    },
+
+    #[cfg(feature = "middleware")]
+    #[error("Middleware error: {0}")]
+    Middleware(#[from] reqwest_middleware::Error),
+}
+
+fn describe_network(
+    status: &Option<reqwest::StatusCode>,
+    body_snippet: &str,
+    headers: &[(String, String)],
+) -> String {
+    let mut description = match (status, body_snippet.is_empty()) {
+        (Some(status), true) => format!("bad response: {status}"),
+        (Some(status), false) => format!("bad response: {status}: {body_snippet}"),
+        (None, _) => body_snippet.to_string(),
+    };
+    if !headers.is_empty() {
+        let headers: Vec<String> = headers
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect();
+        description.push_str(&format!(" ({})", headers.join(", ")));
+    }
+    description
+}
+
+impl TwoCaptchaError {
+    /// Network error without an HTTP status, e.g. a dropped response
+    #[cfg(feature = "test-util")]
+    pub(crate) fn network(message: impl Into<String>) -> Self {
+        TwoCaptchaError::Network {
+            status: None,
+            body_snippet: message.into(),
+            headers: Vec::new(),
+            source: None,
+        }
+    }
+
+    /// API error from a response body or message, parsing the error code
+    /// it contains
+    pub(crate) fn api(message: impl Into<String>) -> Self {
+        let message = message.into();
+        Self::api_with_code(ApiErrorCode::find(&message), message)
+    }
+
+    /// API error whose code is read from the full response `body`, keeping
+    /// `message` as its text, e.g. a cut and redacted echo of the body
+    pub(crate) fn api_from_body(body: &str, message: impl Into<String>) -> Self {
+        Self::api_with_code(ApiErrorCode::find(body), message.into())
+    }
+
+    fn api_with_code(code: Option<ApiErrorCode>, message: String) -> Self {
+        match code {
+            Some(ApiErrorCode::BadDuplicates) => TwoCaptchaError::BadDuplicates,
+            code => TwoCaptchaError::Api { code, message },
+        }
+    }
+
+    /// The error itself, without the solve context and attempt log
+    /// wrapping it
+    pub fn root(&self) -> &TwoCaptchaError {
+        match self {
+            TwoCaptchaError::WithContext { source, .. } => source.root(),
+            TwoCaptchaError::Attempts { last, .. } => last.root(),
+            error => error,
+        }
+    }
+
+    /// Error code the API answered with, if any
+    pub fn api_code(&self) -> Option<ApiErrorCode> {
+        match self {
+            TwoCaptchaError::Api { code, .. } => code.clone(),
+            TwoCaptchaError::BadDuplicates => Some(ApiErrorCode::BadDuplicates),
+            TwoCaptchaError::WithContext { source, .. } => source.api_code(),
+            TwoCaptchaError::Attempts { last, .. } => last.api_code(),
+            _ => None,
+        }
+    }
+
+    /// Add to the context of the error, attaching one if it has none
+    pub(crate) fn with_context(self, update: impl FnOnce(&mut ErrorContext)) -> Self {
+        let (mut context, source) = match self {
+            TwoCaptchaError::WithContext { context, source } => (context, source),
+            error => (Box::default(), Box::new(error)),
+        };
+        update(&mut context);
+        TwoCaptchaError::WithContext { context, source }
+    }
+
+    /// Copy of the error for other waiters of the same captcha
+    ///
+    /// The variant, code and message are kept. Errors wrapping a source
+    /// that cannot be cloned keep its message: failed requests become
+    /// [`TwoCaptchaError::Network`], decoding and IO errors keep their
+    /// variant.
+    pub(crate) fn duplicate(&self) -> Self {
+        use serde::de::Error as _;
+
+        let network =
+            |status: Option<reqwest::StatusCode>, message: String| TwoCaptchaError::Network {
+                status,
+                body_snippet: message,
+                headers: Vec::new(),
+                source: None,
+            };
+        match self {
+            TwoCaptchaError::Validation(message) => TwoCaptchaError::Validation(message.clone()),
+            TwoCaptchaError::Network {
+                status,
+                body_snippet,
+                headers,
+                ..
+            } => TwoCaptchaError::Network {
+                status: *status,
+                body_snippet: body_snippet.clone(),
+                headers: headers.clone(),
+                source: None,
+            },
+            TwoCaptchaError::Api { code, message } => TwoCaptchaError::Api {
+                code: code.clone(),
+                message: message.clone(),
+            },
+            TwoCaptchaError::BadDuplicates => TwoCaptchaError::BadDuplicates,
+            TwoCaptchaError::Timeout {
+                captcha_id,
+                message,
+            } => TwoCaptchaError::Timeout {
+                captcha_id: captcha_id.clone(),
+                message: message.clone(),
+            },
+            TwoCaptchaError::Request(e) => network(e.status(), e.to_string()),
+            #[cfg(feature = "middleware")]
+            TwoCaptchaError::Middleware(e) => network(e.status(), e.to_string()),
+            TwoCaptchaError::Json(e) => TwoCaptchaError::Json(serde_json::Error::custom(e)),
+            TwoCaptchaError::Base64(e) => TwoCaptchaError::Base64(e.clone()),
+            TwoCaptchaError::Io(e) => {
+                TwoCaptchaError::Io(std::io::Error::new(e.kind(), e.to_string()))
+            }
+            TwoCaptchaError::UrlParse(e) => TwoCaptchaError::UrlParse(*e),
+            TwoCaptchaError::Shutdown => TwoCaptchaError::Shutdown,
+            TwoCaptchaError::CircuitOpen { retry_after } => TwoCaptchaError::CircuitOpen {
+                retry_after: *retry_after,
+            },
+            TwoCaptchaError::WithContext { context, source } => TwoCaptchaError::WithContext {
+                context: context.clone(),
+                source: Box::new(source.duplicate()),
+            },
+            TwoCaptchaError::Attempts { log, last } => TwoCaptchaError::Attempts {
+                log: log.clone(),
+                last: Box::new(last.duplicate()),
+            },
+        }
+    }
+
+    /// Context of the failed solve, when the error comes from one
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            TwoCaptchaError::WithContext { context, .. } => Some(context),
+            TwoCaptchaError::Attempts { last, .. } => last.context(),
+            _ => None,
+        }
+    }
+
+    /// Waiting for captcha `id` exceeded `timeout`
+    pub(crate) fn timeout(id: &str, timeout: std::time::Duration) -> Self {
+        TwoCaptchaError::Timeout {
+            captcha_id: Some(id.to_string()),
+            message: format!("timeout {} exceeded for captcha {id}", timeout.as_secs()),
+        }
+    }
+
+    /// Id of the captcha the error happened with, when the API assigned
+    /// one, e.g. that of a timed out solve
+    pub fn captcha_id(&self) -> Option<&str> {
+        match self {
+            TwoCaptchaError::Timeout { captcha_id, .. } => captcha_id.as_deref(),
+            TwoCaptchaError::WithContext { context, source } => context
+                .captcha_id
+                .as_deref()
+                .or_else(|| source.captcha_id()),
+            TwoCaptchaError::Attempts { last, .. } => last.captcha_id(),
+            _ => None,
+        }
+    }
+
+    /// Trace id of the last failed solve attempt, see
+    /// [`Attempt::trace_id`](crate::types::Attempt::trace_id)
+    pub fn trace_id(&self) -> Option<&str> {
+        match self {
+            TwoCaptchaError::WithContext { context, .. } => context.trace_id.as_deref(),
+            TwoCaptchaError::Attempts { log, .. } => {
+                log.attempts.last().map(|attempt| attempt.trace_id.as_str())
+            }
+            _ => None,
+        }
+    }
+
+    /// Short code describing the error, as recorded in an [`AttemptLog`]
+    pub(crate) fn attempt_code(&self) -> String {
+        match self {
+            TwoCaptchaError::Api { code, .. } => code
+                .as_ref()
+                .map_or("API", ApiErrorCode::as_str)
+                .to_string(),
+            TwoCaptchaError::BadDuplicates => "ERROR_BAD_DUPLICATES".to_string(),
+            TwoCaptchaError::Timeout { .. } => "TIMEOUT".to_string(),
+            TwoCaptchaError::Network { .. } | TwoCaptchaError::Request(_) => "NETWORK".to_string(),
+            TwoCaptchaError::WithContext { source, .. } => source.attempt_code(),
+            TwoCaptchaError::Attempts { last, .. } => last.attempt_code(),
+            _ => "OTHER".to_string(),
+        }
+    }
+
+    /// Whether no connection to the server could be established
+    pub(crate) fn is_connect_failure(&self) -> bool {
+        match self {
+            TwoCaptchaError::Request(e) => e.is_connect(),
+            TwoCaptchaError::WithContext { source, .. } => source.is_connect_failure(),
+            #[cfg(feature = "middleware")]
+            TwoCaptchaError::Middleware(reqwest_middleware::Error::Reqwest(e)) => e.is_connect(),
+            _ => false,
+        }
+    }
+
+    /// Broad category of the error, for retry loops and alerting
+    pub fn kind(&self) -> ErrorKind {
+        use ApiErrorCode::*;
+
+        match self {
+            TwoCaptchaError::Validation(_) => ErrorKind::Validation,
+            TwoCaptchaError::Network { .. } | TwoCaptchaError::Request(_) => ErrorKind::Network,
+            #[cfg(feature = "middleware")]
+            TwoCaptchaError::Middleware(_) => ErrorKind::Network,
+            TwoCaptchaError::Timeout { .. } => ErrorKind::Timeout,
+            TwoCaptchaError::BadDuplicates => ErrorKind::Unsolvable,
+            TwoCaptchaError::Api { code, .. } => match code {
+                Some(WrongUserKey | KeyDoesNotExist | IpNotAllowed | IpBanned | IpBlocked) => {
+                    ErrorKind::Auth
+                }
+                Some(ZeroBalance) => ErrorKind::Balance,
+                Some(NoSlotAvailable | MaxUserTurn) => ErrorKind::Overloaded,
+                Some(CaptchaUnsolvable | BadDuplicates) => ErrorKind::Unsolvable,
+                Some(PageUrl) => ErrorKind::Validation,
+                _ => ErrorKind::Api,
+            },
+            TwoCaptchaError::Json(_)
+            | TwoCaptchaError::Base64(_)
+            | TwoCaptchaError::UrlParse(_) => ErrorKind::Parse,
+            TwoCaptchaError::Io(_) => ErrorKind::Io,
+            TwoCaptchaError::Shutdown => ErrorKind::Shutdown,
+            TwoCaptchaError::CircuitOpen { .. } => ErrorKind::CircuitOpen,
+            TwoCaptchaError::WithContext { source, .. } => source.kind(),
+            TwoCaptchaError::Attempts { last, .. } => last.kind(),
+        }
+    }
+
+    /// Whether a new attempt may succeed where this one failed
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self.kind(),
+            ErrorKind::Network | ErrorKind::Timeout | ErrorKind::Overloaded | ErrorKind::Unsolvable
+        )
+    }
+
+    /// Whether retrying the same request cannot help
+    pub fn is_permanent(&self) -> bool {
+        !self.is_retryable()
+    }
+
+    /// Whether the API key is wrong or not allowed from this IP
+    pub fn is_auth_error(&self) -> bool {
+        self.kind() == ErrorKind::Auth
+    }
+
+    /// Whether the account has run out of funds
+    pub fn is_balance_error(&self) -> bool {
+        self.kind() == ErrorKind::Balance
+    }
+}
+
+/// What is known of the solve an error happened in, see
+/// [`TwoCaptchaError::context`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ErrorContext {
+    /// `in.php` method of the task, e.g. `hcaptcha`
+    pub method: Option<String>,
+    /// Id of the captcha, once the API accepted it
+    pub captcha_id: Option<String>,
+    /// Result requests made for the captcha
+    pub polls: Option<u32>,
+    /// Trace id of the solve attempt, see
+    /// [`Attempt::trace_id`](crate::types::Attempt::trace_id)
+    pub trace_id: Option<String>,
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let polls = self.polls.map(|polls| polls.to_string());
+        let fields = [
+            ("method", self.method.as_deref()),
+            ("captcha", self.captcha_id.as_deref()),
+            ("polls", polls.as_deref()),
+            ("trace", self.trace_id.as_deref()),
+        ];
+        let fields: Vec<String> = fields
+            .iter()
+            .filter_map(|(name, value)| Some(format!("{name} {}", (*value)?)))
+            .collect();
+        f.write_str(&fields.join(", "))
+    }
+}
+
+/// Category of a [`TwoCaptchaError`], see [`TwoCaptchaError::kind`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Invalid parameters or configuration, rejected before sending or
+    /// refused by the API as malformed (such as `ERROR_PAGEURL`)
+    Validation,
+    /// The API could not be reached or answered with a bad status
+    Network,
+    /// No answer within the timeout
+    Timeout,
+    /// The API key is wrong or not allowed from this IP
+    Auth,
+    /// The account has no funds left
+    Balance,
+    /// The API has no free workers or queue slots right now
+    Overloaded,
+    /// Workers could not solve the captcha
+    Unsolvable,
+    /// Any other error answered by the API
+    Api,
+    /// A response or input could not be decoded
+    Parse,
+    Io,
+    Shutdown,
+    /// Refused by the client's circuit breaker without reaching the API
+    CircuitOpen,
+}
+
+macro_rules! api_error_codes {
+    ($($(#[$doc:meta])* $variant:ident => $code:literal,)*) => {
+        /// Error code documented by 2captcha, as answered by `in.php`,
+        /// `res.php` or the JSON API
+        ///
+        /// Codes not listed here are kept in [`ApiErrorCode::Other`].
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum ApiErrorCode {
+            $($(#[$doc])* $variant,)*
+            /// Undocumented code, as sent by the API
+            Other(String),
+        }
+
+        impl ApiErrorCode {
+            /// Code as sent by the API, e.g. `ERROR_ZERO_BALANCE`
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(ApiErrorCode::$variant => $code,)*
+                    ApiErrorCode::Other(code) => code,
+                }
+            }
+
+            fn known(code: &str) -> Option<Self> {
+                match code {
+                    $($code => Some(ApiErrorCode::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+api_error_codes! {
+    /// The API key is malformed
+    WrongUserKey => "ERROR_WRONG_USER_KEY",
+    /// No account has this API key
+    KeyDoesNotExist => "ERROR_KEY_DOES_NOT_EXIST",
+    ZeroBalance => "ERROR_ZERO_BALANCE",
+    /// `pageurl` is missing
+    PageUrl => "ERROR_PAGEURL",
+    /// The queue is full, try again later
+    NoSlotAvailable => "ERROR_NO_SLOT_AVAILABLE",
+    ZeroCaptchaFilesize => "ERROR_ZERO_CAPTCHA_FILESIZE",
+    TooBigCaptchaFilesize => "ERROR_TOO_BIG_CAPTCHA_FILESIZE",
+    WrongFileExtension => "ERROR_WRONG_FILE_EXTENSION",
+    ImageTypeNotSupported => "ERROR_IMAGE_TYPE_NOT_SUPPORTED",
+    Upload => "ERROR_UPLOAD",
+    IpNotAllowed => "ERROR_IP_NOT_ALLOWED",
+    IpBanned => "IP_BANNED",
+    IpBlocked => "ERROR_IP_BLOCKED",
+    BadTokenOrPageUrl => "ERROR_BAD_TOKEN_OR_PAGEURL",
+    GoogleKey => "ERROR_GOOGLEKEY",
+    WrongGoogleKey => "ERROR_WRONG_GOOGLEKEY",
+    CaptchaImageBlocked => "ERROR_CAPTCHAIMAGE_BLOCKED",
+    TooManyBadImages => "TOO_MANY_BAD_IMAGES",
+    MaxUserTurn => "MAX_USER_TURN",
+    BadParameters => "ERROR_BAD_PARAMETERS",
+    BadProxy => "ERROR_BAD_PROXY",
+    ProxyConnectionFailed => "ERROR_PROXY_CONNECTION_FAILED",
+    NoSuchMethod => "ERROR_NO_SUCH_METHOD",
+    /// No worker could solve the captcha
+    CaptchaUnsolvable => "ERROR_CAPTCHA_UNSOLVABLE",
+    /// Not enough matching answers under 100% recognition
+    BadDuplicates => "ERROR_BAD_DUPLICATES",
+    WrongIdFormat => "ERROR_WRONG_ID_FORMAT",
+    /// The id is unknown or its result has expired
+    WrongCaptchaId => "ERROR_WRONG_CAPTCHA_ID",
+    NoSuchCaptchaId => "ERROR_NO_SUCH_CAPCHA_ID",
+    EmptyAction => "ERROR_EMPTY_ACTION",
+    ReportNotRecorded => "ERROR_REPORT_NOT_RECORDED",
+    DuplicateReport => "ERROR_DUPLICATE_REPORT",
+    TokenExpired => "ERROR_TOKEN_EXPIRED",
+    TaskAbsent => "ERROR_TASK_ABSENT",
+    TaskNotSupported => "ERROR_TASK_NOT_SUPPORTED",
+    RecaptchaInvalidSitekey => "ERROR_RECAPTCHA_INVALID_SITEKEY",
+    RecaptchaInvalidDomain => "ERROR_RECAPTCHA_INVALID_DOMAIN",
+    RecaptchaTimeout => "ERROR_RECAPTCHA_TIMEOUT",
+}
+
+impl ApiErrorCode {
+    /// Code of a response body, plain text like `ERROR_ZERO_BALANCE` or
+    /// `ERROR_BAD_PARAMETERS: details`, or JSON with the code in `request`
+    /// (`in.php`/`res.php`) or `errorCode` (JSON API)
+    pub fn parse(body: &str) -> Option<Self> {
+        if let Ok(serde_json::Value::Object(response)) = serde_json::from_str(body.trim()) {
+            return ["errorCode", "request"]
+                .iter()
+                .find_map(|key| response.get(*key)?.as_str())
+                .and_then(Self::find);
+        }
+        Self::find(body)
+    }
+
+    /// First word of `message` that is an error code
+    fn find(message: &str) -> Option<Self> {
+        message
+            .split(|c: char| !(c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_'))
+            .find_map(|word| {
+                Self::known(word).or_else(|| {
+                    (word.starts_with("ERROR_") && word.len() > "ERROR_".len())
+                        .then(|| ApiErrorCode::Other(word.to_string()))
+                })
+            })
+    }
+}
+
+impl fmt::Display for ApiErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 /// Alias for Result with TwoCaptchaError
@@ -38,3 +569,99 @@ pub type Result<T> = std::result::Result<T, TwoCaptchaError>;
 pub trait SolverExceptions: std::error::Error + Send + Sync {}
 
 impl SolverExceptions for TwoCaptchaError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attempt_code() {
+        let error = TwoCaptchaError::api("ERROR_CAPTCHA_UNSOLVABLE");
+        assert_eq!(error.attempt_code(), "ERROR_CAPTCHA_UNSOLVABLE");
+        assert!(error.is_retryable());
+
+        let error = TwoCaptchaError::api("cannot recognize response NOPE");
+        assert_eq!(error.attempt_code(), "API");
+        assert!(!error.is_retryable());
+
+        let error = TwoCaptchaError::timeout("123", std::time::Duration::from_secs(120));
+        assert!(error.is_retryable());
+        assert_eq!(error.captcha_id(), Some("123"));
+        assert_eq!(
+            error.to_string(),
+            "Timeout error: timeout 120 exceeded for captcha 123"
+        );
+        let error = TwoCaptchaError::api("ERROR_WRONG_USER_KEY");
+        assert!(error.is_permanent() && error.is_auth_error());
+        assert_eq!(error.kind(), ErrorKind::Auth);
+        assert!(TwoCaptchaError::api("ERROR_ZERO_BALANCE").is_balance_error());
+        assert_eq!(
+            TwoCaptchaError::api("ERROR_NO_SLOT_AVAILABLE").kind(),
+            ErrorKind::Overloaded
+        );
+
+        let error = TwoCaptchaError::api("ERROR_BAD_DUPLICATES");
+        assert!(matches!(error, TwoCaptchaError::BadDuplicates));
+        assert_eq!(error.attempt_code(), "ERROR_BAD_DUPLICATES");
+        assert!(error.is_retryable());
+    }
+
+    #[test]
+    fn test_error_context() {
+        let error = TwoCaptchaError::api("ERROR_CAPTCHA_UNSOLVABLE")
+            .with_context(|context| {
+                context.captcha_id = Some("123".to_string());
+                context.polls = Some(4);
+            })
+            .with_context(|context| context.method = Some("hcaptcha".to_string()));
+
+        let context = error.context().unwrap();
+        assert_eq!(context.method.as_deref(), Some("hcaptcha"));
+        assert_eq!(error.captcha_id(), Some("123"));
+        assert_eq!(error.kind(), ErrorKind::Unsolvable);
+        assert_eq!(
+            error.to_string(),
+            "API error: ERROR_CAPTCHA_UNSOLVABLE (method hcaptcha, captcha 123, polls 4)"
+        );
+        assert!(matches!(
+            error.root(),
+            TwoCaptchaError::Api {
+                code: Some(ApiErrorCode::CaptchaUnsolvable),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_api_error_code() {
+        assert_eq!(
+            ApiErrorCode::parse("ERROR_ZERO_BALANCE"),
+            Some(ApiErrorCode::ZeroBalance)
+        );
+        assert_eq!(
+            ApiErrorCode::parse(
+                r#"{"status":0,"request":"ERROR_WRONG_USER_KEY","error_text":"..."}"#
+            ),
+            Some(ApiErrorCode::WrongUserKey)
+        );
+        assert_eq!(
+            ApiErrorCode::parse(r#"{"errorId":1,"errorCode":"ERROR_PROXY_CONNECTION_FAILED"}"#),
+            Some(ApiErrorCode::ProxyConnectionFailed)
+        );
+        assert_eq!(
+            ApiErrorCode::parse("ERROR_SOMETHING_NEW: details"),
+            Some(ApiErrorCode::Other("ERROR_SOMETHING_NEW".to_string()))
+        );
+        assert_eq!(ApiErrorCode::parse("OK|123"), None);
+
+        let error = TwoCaptchaError::api("MAX_USER_TURN");
+        assert!(matches!(
+            error,
+            TwoCaptchaError::Api {
+                code: Some(ApiErrorCode::MaxUserTurn),
+                ..
+            }
+        ));
+        assert_eq!(error.to_string(), "API error: MAX_USER_TURN");
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use crate::error::{Result, TwoCaptchaError};
+use crate::types::Proxy;
+
+/// Parameters the client sets itself, which extras must not override
+const RESERVED_KEYS: &[&str] = &["key", "method", "json", "softId", "soft_id", "body", "file"];
+
+/// Optional parameters passed alongside a captcha task
+///
+/// Common parameters have their own methods. Anything else can be set with
+/// [`Extras::set_raw`], which refuses the parameters the client manages
+/// itself (API key, method, ...).
+///
+/// ```
+/// use twocaptcha::Extras;
+///
+/// let extras = Extras::new()
+///     .min_score(0.3)
+///     .action("verify")
+///     .set_raw("pagedata", "3gAFo2l0aM4AA")
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Extras {
+    params: HashMap<String, String>,
+}
+
+impl Extras {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set any parameter, failing if the client manages it itself
+    pub fn set_raw(self, key: impl Into<String>, value: impl Into<String>) -> Result<Self> {
+        let key = key.into();
+        if RESERVED_KEYS.contains(&key.as_str()) {
+            return Err(TwoCaptchaError::Validation(format!(
+                "`{key}` is set by the client and cannot be passed as an extra"
+            )));
+        }
+        Ok(self.set(key, value))
+    }
+
+    /// Whether the answer is case sensitive
+    pub fn case_sensitive(self, enabled: bool) -> Self {
+        self.set("caseSensitive", flag(enabled))
+    }
+
+    /// Minimum number of characters in the answer
+    pub fn min_len(self, len: u32) -> Self {
+        self.set("minLen", len.to_string())
+    }
+
+    /// Maximum number of characters in the answer
+    pub fn max_len(self, len: u32) -> Self {
+        self.set("maxLen", len.to_string())
+    }
+
+    /// Allowed characters: 1 digits only, 2 letters only, 3 either, 4 both
+    pub fn numeric(self, kind: u8) -> Self {
+        self.set("numeric", kind.to_string())
+    }
+
+    /// Whether the answer contains several words
+    pub fn phrase(self, enabled: bool) -> Self {
+        self.set("phrase", flag(enabled))
+    }
+
+    /// Whether the captcha is a math problem to calculate
+    pub fn calc(self, enabled: bool) -> Self {
+        self.set("calc", flag(enabled))
+    }
+
+    /// Language of the captcha, e.g. `en`
+    pub fn lang(self, lang: impl Into<String>) -> Self {
+        self.set("lang", lang)
+    }
+
+    /// Whether a reCAPTCHA v2 is invisible
+    pub fn invisible(self, enabled: bool) -> Self {
+        self.set("invisible", flag(enabled))
+    }
+
+    /// reCAPTCHA v3 or Turnstile action
+    pub fn action(self, action: impl Into<String>) -> Self {
+        self.set("action", action)
+    }
+
+    /// Minimum reCAPTCHA v3 score required
+    pub fn min_score(self, score: f64) -> Self {
+        self.set("min_score", score.to_string())
+    }
+
+    /// reCAPTCHA `data-s` value
+    pub fn data_s(self, data_s: impl Into<String>) -> Self {
+        self.set("datas", data_s)
+    }
+
+    /// Enterprise payload of a reCAPTCHA Enterprise or hCaptcha Enterprise
+    ///
+    /// Takes the JSON object the official JSON API calls `enterprisePayload`
+    /// and spreads it over the `in.php` parameters: `s` is sent as `data-s`,
+    /// `rqdata` as `data` and any other field under its own name. Nested
+    /// values are serialized to JSON strings.
+    pub fn enterprise_payload(self, payload: serde_json::Value) -> Result<Self> {
+        let serde_json::Value::Object(payload) = payload else {
+            return Err(TwoCaptchaError::Validation(
+                "enterprise payload must be a JSON object".to_string(),
+            ));
+        };
+
+        payload.into_iter().try_fold(self, |extras, (key, value)| {
+            let key = match key.as_str() {
+                "s" => "data-s".to_string(),
+                "rqdata" => "data".to_string(),
+                _ => key,
+            };
+            let value = match value {
+                serde_json::Value::String(value) => value,
+                value => value.to_string(),
+            };
+            extras.set_raw(key, value)
+        })
+    }
+
+    /// API server (domain) of the captcha provider
+    pub fn api_server(self, api_server: impl Into<String>) -> Self {
+        self.set("apiServer", api_server)
+    }
+
+    /// Proxy the worker uses to load the captcha
+    pub fn proxy(self, proxy: Proxy) -> Self {
+        let proxy = serde_json::json!({ "type": proxy.proxy_type, "uri": proxy.uri });
+        self.set("proxy", proxy.to_string())
+    }
+
+    /// Cookies to set in the worker's browser, as `key1:value1;key2:value2`
+    /// or [`Cookies`](crate::Cookies)
+    ///
+    /// Used by reCAPTCHA, where a signed-in user's Google cookies can raise
+    /// the score; cookies from a previous answer
+    /// ([`CaptchaResult::cookies`](crate::CaptchaResult::cookies)) can be
+    /// passed back as they are.
+    pub fn cookies(self, cookies: impl Into<String>) -> Self {
+        self.set("cookies", cookies)
+    }
+
+    /// URL the API sends the answer of this task to, instead of the
+    /// client-wide callback; see [`TwoCaptcha::add_pingback`]
+    ///
+    /// [`TwoCaptcha::add_pingback`]: crate::TwoCaptcha::add_pingback
+    pub fn callback(self, url: impl Into<String>) -> Self {
+        self.set("callback", url)
+    }
+
+    /// Id of a previous captcha, to solve it with the same worker
+    pub fn previous_id(self, id: impl Into<String>) -> Self {
+        self.set("previousId", id)
+    }
+
+    /// Let workers skip the task when there is nothing to select
+    pub fn can_skip(self, enabled: bool) -> Self {
+        self.set("canSkip", flag(enabled))
+    }
+
+    /// Whether `key` was set
+    pub fn contains(&self, key: &str) -> bool {
+        self.params.contains_key(key)
+    }
+
+    pub(crate) fn params_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.params
+    }
+
+    fn set(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.params.insert(key.into(), value.into());
+        self
+    }
+}
+
+fn flag(enabled: bool) -> &'static str {
+    if enabled { "1" } else { "0" }
+}
+
+impl TryFrom<HashMap<String, String>> for Extras {
+    type Error = TwoCaptchaError;
+
+    fn try_from(params: HashMap<String, String>) -> Result<Self> {
+        params
+            .into_iter()
+            .try_fold(Extras::new(), |extras, (key, value)| {
+                extras.set_raw(key, value)
+            })
+    }
+}
+
+impl IntoIterator for Extras {
+    type Item = (String, String);
+    type IntoIter = std::collections::hash_map::IntoIter<String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.params.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extras_builder() {
+        let extras = Extras::new()
+            .case_sensitive(true)
+            .min_len(4)
+            .set_raw("textinstructions", "digits only")
+            .unwrap();
+        let params: HashMap<_, _> = extras.into_iter().collect();
+
+        assert_eq!(params["caseSensitive"], "1");
+        assert_eq!(params["minLen"], "4");
+        assert_eq!(params["textinstructions"], "digits only");
+    }
+
+    #[test]
+    fn test_enterprise_payload() {
+        let payload = serde_json::json!({
+            "s": "S_VALUE",
+            "rqdata": { "key": "value" },
+        });
+        let params: HashMap<_, _> = Extras::new()
+            .enterprise_payload(payload)
+            .unwrap()
+            .into_iter()
+            .collect();
+
+        assert_eq!(params["data-s"], "S_VALUE");
+        assert_eq!(params["data"], r#"{"key":"value"}"#);
+        assert!(Extras::new().enterprise_payload("s".into()).is_err());
+    }
+
+    #[test]
+    fn test_extras_reject_reserved_keys() {
+        assert!(Extras::new().set_raw("key", "other").is_err());
+
+        let mut params = HashMap::new();
+        params.insert("method".to_string(), "post".to_string());
+        assert!(Extras::try_from(params).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! Solving a page that serves one of two captcha types
+//!
+//! Some sites show hCaptcha or Turnstile interchangeably. A
+//! [`FallbackTask`] tries the first task and, when it is unsolvable or
+//! times out, the second one:
+//!
+//! ```no_run
+//! # async fn run(solver: twocaptcha::TwoCaptcha) -> twocaptcha::Result<()> {
+//! use std::collections::HashMap;
+//! use twocaptcha::{FallbackTask, SolvedBy, Task};
+//!
+//! let task = |pairs: &[(&str, &str)]| {
+//!     Task::from_params(pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect())
+//! };
+//! let fallback = FallbackTask::new(
+//!     task(&[
+//!         ("method", "hcaptcha"),
+//!         ("sitekey", "f7de0da3-3303-44e8-ab48-fa32ff8ccc7b"),
+//!         ("pageurl", "https://example.com/login"),
+//!     ]),
+//!     // Page URL, proxy, user agent and cookies come from the first task
+//!     task(&[("method", "turnstile"), ("sitekey", "0x4AAAAAAAChNiVJM_WtShFf")]),
+//! );
+//!
+//! let solved = solver.solve_fallback(&fallback).await?;
+//! if solved.solved_by == SolvedBy::Fallback {
+//!     println!("served Turnstile: {}", solved.result.code.unwrap_or_default());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+
+use crate::error::{ErrorKind, Result, TwoCaptchaError};
+use crate::solver::TwoCaptcha;
+use crate::task::{CaptchaTask, Task};
+use crate::types::CaptchaResult;
+
+/// Two tasks for the same page, the second solved when the first fails
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackTask {
+    primary: Task,
+    fallback: Task,
+}
+
+/// Which task of a [`FallbackTask`] produced the answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolvedBy {
+    Primary,
+    Fallback,
+}
+
+/// Answer of a [`FallbackTask`]
+#[derive(Debug)]
+pub struct FallbackResult {
+    pub result: CaptchaResult,
+    pub solved_by: SolvedBy,
+    /// Why the primary task was given up, when the fallback was solved
+    pub primary_error: Option<TwoCaptchaError>,
+}
+
+impl FallbackTask {
+    /// Try `primary`, then `fallback` with the page URL, proxy, user agent
+    /// and cookies of `primary` filled in where it does not set them
+    pub fn new(primary: impl CaptchaTask, fallback: impl CaptchaTask) -> Self {
+        let primary = primary.to_task();
+        let mut params = fallback.to_task().into_params();
+        fill_page_context(primary.params(), &mut params);
+
+        Self {
+            primary,
+            fallback: Task::from_params(params),
+        }
+    }
+
+    pub fn primary(&self) -> &Task {
+        &self.primary
+    }
+
+    pub fn fallback(&self) -> &Task {
+        &self.fallback
+    }
+}
+
+/// Copy the page context of `from` into `to`
+fn fill_page_context(from: &HashMap<String, String>, to: &mut HashMap<String, String>) {
+    let get = |keys: &[&str]| keys.iter().find_map(|key| from.get(*key));
+    let missing =
+        |to: &HashMap<String, String>, keys: &[&str]| !keys.iter().any(|key| to.contains_key(*key));
+
+    if let Some(url) = get(&["pageurl", "url"])
+        && missing(to, &["pageurl", "url"])
+    {
+        to.insert("pageurl".to_string(), url.clone());
+    }
+    if let Some(user_agent) = get(&["userAgent", "useragent"])
+        && missing(to, &["userAgent", "useragent"])
+    {
+        let key = if to.get("method").is_some_and(|m| m == "turnstile") {
+            "useragent"
+        } else {
+            "userAgent"
+        };
+        to.insert(key.to_string(), user_agent.clone());
+    }
+    // The proxy type only applies to the proxy it came with
+    if let Some(proxy) = from.get("proxy")
+        && !to.contains_key("proxy")
+    {
+        to.insert("proxy".to_string(), proxy.clone());
+        if let Some(proxy_type) = from.get("proxytype") {
+            to.insert("proxytype".to_string(), proxy_type.clone());
+        }
+    }
+    if let Some(cookies) = from.get("cookies")
+        && !to.contains_key("cookies")
+    {
+        to.insert("cookies".to_string(), cookies.clone());
+    }
+}
+
+/// Whether the other captcha type may succeed after `error`
+fn should_fall_back(error: &TwoCaptchaError) -> bool {
+    matches!(error.kind(), ErrorKind::Unsolvable | ErrorKind::Timeout)
+}
+
+impl TwoCaptcha {
+    /// Solve the primary task of `task`, then its fallback if the primary
+    /// one is unsolvable or times out; other errors are returned as is
+    pub async fn solve_fallback(&self, task: &FallbackTask) -> Result<FallbackResult> {
+        let primary_error = match self.solve(None, None, task.primary.params().clone()).await {
+            Ok(result) => {
+                return Ok(FallbackResult {
+                    result,
+                    solved_by: SolvedBy::Primary,
+                    primary_error: None,
+                });
+            }
+            Err(e) if should_fall_back(&e) => e,
+            Err(e) => return Err(e),
+        };
+
+        let result = self
+            .solve(None, None, task.fallback.params().clone())
+            .await?;
+        Ok(FallbackResult {
+            result,
+            solved_by: SolvedBy::Fallback,
+            primary_error: Some(primary_error),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(pairs: &[(&str, &str)]) -> Task {
+        Task::from_params(
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_fallback_shares_page_context() {
+        let fallback = FallbackTask::new(
+            task(&[
+                ("method", "hcaptcha"),
+                ("sitekey", "a"),
+                ("url", "https://example.com"),
+                ("userAgent", "Mozilla/5.0"),
+                ("proxy", "user:pass@1.2.3.4:8080"),
+                ("proxytype", "HTTP"),
+            ]),
+            task(&[("method", "turnstile"), ("sitekey", "b"), ("proxy", "own")]),
+        );
+
+        let params = fallback.fallback().params();
+        assert_eq!(params["pageurl"], "https://example.com");
+        assert_eq!(params["useragent"], "Mozilla/5.0");
+        assert_eq!(params["proxy"], "own");
+        assert!(!params.contains_key("proxytype"));
+        assert_eq!(params["sitekey"], "b");
+
+        assert!(should_fall_back(&TwoCaptchaError::api(
+            "ERROR_CAPTCHA_UNSOLVABLE"
+        )));
+        assert!(should_fall_back(&TwoCaptchaError::timeout(
+            "1",
+            std::time::Duration::from_secs(120)
+        )));
+        assert!(!should_fall_back(&TwoCaptchaError::api(
+            "ERROR_ZERO_BALANCE"
+        )));
+    }
+}
@@ -0,0 +1,198 @@
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::error::Result;
+
+/// Files accessed at the same time by default
+const DEFAULT_MAX_CONCURRENT: usize = 4;
+
+/// Every filesystem access of a client goes through here
+///
+/// Accesses are async and limited in number, so pointing the client at a
+/// slow network filesystem neither stalls the runtime nor floods the
+/// filesystem. [`FileAccess::stats`] shows how much time they take.
+#[derive(Debug)]
+pub struct FileAccess {
+    permits: Semaphore,
+    reads: AtomicU64,
+    bytes_read: AtomicU64,
+    existence_checks: AtomicU64,
+    wait_nanos: AtomicU64,
+    io_nanos: AtomicU64,
+}
+
+/// Counters of a [`FileAccess`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileStats {
+    pub reads: u64,
+    pub bytes_read: u64,
+    pub existence_checks: u64,
+    /// Time spent waiting for a free slot
+    pub wait: Duration,
+    /// Time spent in filesystem calls
+    pub io: Duration,
+}
+
+impl Default for FileAccess {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT)
+    }
+}
+
+impl FileAccess {
+    /// Allow at most `max_concurrent` accesses at the same time
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            permits: Semaphore::new(max_concurrent.max(1)),
+            reads: AtomicU64::new(0),
+            bytes_read: AtomicU64::new(0),
+            existence_checks: AtomicU64::new(0),
+            wait_nanos: AtomicU64::new(0),
+            io_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Read a whole file
+    pub async fn read(&self, path: impl AsRef<Path>) -> Result<Vec<u8>> {
+        let content = self.access(tokio::fs::read(path.as_ref())).await?;
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.bytes_read
+            .fetch_add(content.len() as u64, Ordering::Relaxed);
+        Ok(content)
+    }
+
+    /// Read a whole file into a buffer that can be shared without copies
+    ///
+    /// With the `mmap` feature the file is memory-mapped instead of read,
+    /// so large files are paged in while they are uploaded rather than
+    /// held in memory up front.
+    pub async fn read_shared(&self, path: impl AsRef<Path>) -> Result<Bytes> {
+        #[cfg(feature = "mmap")]
+        let content = {
+            let path = path.as_ref().to_path_buf();
+            self.access(tokio::task::spawn_blocking(move || map_file(&path)))
+                .await
+                .map_err(std::io::Error::other)??
+        };
+        #[cfg(not(feature = "mmap"))]
+        let content = Bytes::from(self.access(tokio::fs::read(path.as_ref())).await?);
+
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.bytes_read
+            .fetch_add(content.len() as u64, Ordering::Relaxed);
+        Ok(content)
+    }
+
+    /// Whether `path` exists, treating unreadable metadata as missing
+    pub async fn exists(&self, path: impl AsRef<Path>) -> bool {
+        let exists = self.access(tokio::fs::try_exists(path.as_ref())).await;
+        self.existence_checks.fetch_add(1, Ordering::Relaxed);
+        exists.unwrap_or(false)
+    }
+
+    /// Read several files concurrently, keyed like `files`
+    pub async fn read_all(
+        self: &Arc<Self>,
+        files: HashMap<String, String>,
+    ) -> Result<HashMap<String, Bytes>> {
+        let mut reads = JoinSet::new();
+        for (key, path) in files {
+            let access = Arc::clone(self);
+            reads.spawn(async move {
+                access
+                    .read_shared(&path)
+                    .await
+                    .map(|content| (key, content))
+            });
+        }
+
+        let mut contents = HashMap::new();
+        while let Some(read) = reads.join_next().await {
+            let (key, content) = read.map_err(std::io::Error::other)??;
+            contents.insert(key, content);
+        }
+        Ok(contents)
+    }
+
+    pub fn stats(&self) -> FileStats {
+        FileStats {
+            reads: self.reads.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            existence_checks: self.existence_checks.load(Ordering::Relaxed),
+            wait: Duration::from_nanos(self.wait_nanos.load(Ordering::Relaxed)),
+            io: Duration::from_nanos(self.io_nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Run a filesystem call once a slot is free, timing both
+    async fn access<T>(&self, call: impl Future<Output = T>) -> T {
+        let start = Instant::now();
+        let _permit = self.permits.acquire().await;
+        let acquired = Instant::now();
+        let output = call.await;
+
+        self.wait_nanos
+            .fetch_add(nanos(acquired - start), Ordering::Relaxed);
+        self.io_nanos
+            .fetch_add(nanos(acquired.elapsed()), Ordering::Relaxed);
+        output
+    }
+}
+
+/// Map a file read-only, keeping the mapping alive as long as the buffer
+#[cfg(feature = "mmap")]
+fn map_file(path: &Path) -> std::io::Result<Bytes> {
+    let file = std::fs::File::open(path)?;
+    // Empty files cannot be mapped on every platform
+    if file.metadata()?.len() == 0 {
+        return Ok(Bytes::new());
+    }
+    // SAFETY: the mapping is only read. A file truncated or rewritten by
+    // another process while it is uploaded may be sent torn, as it would
+    // be by a concurrent `read`.
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    Ok(Bytes::from_owner(map))
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_all() {
+        let access = Arc::new(FileAccess::new(2));
+        let dir =
+            std::env::temp_dir().join(format!("twocaptcha_read_files_{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let mut files = HashMap::new();
+        for i in 1..=6 {
+            let path = dir.join(format!("{i}.png"));
+            tokio::fs::write(&path, [i as u8; 3]).await.unwrap();
+            files.insert(format!("file_{i}"), path.to_string_lossy().into_owned());
+        }
+
+        let contents = access.read_all(files).await.unwrap();
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+        assert_eq!(contents.len(), 6);
+        assert_eq!(contents["file_4"][..], [4, 4, 4]);
+        assert!(!access.exists("missing.png").await);
+
+        let stats = access.stats();
+        assert_eq!(stats.reads, 6);
+        assert_eq!(stats.bytes_read, 18);
+        assert_eq!(stats.existence_checks, 1);
+
+        let missing = HashMap::from([("file_1".to_string(), "missing.png".to_string())]);
+        assert!(access.read_all(missing).await.is_err());
+    }
+}
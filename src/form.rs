@@ -0,0 +1,167 @@
+use reqwest::header::{CONTENT_TYPE, COOKIE, USER_AGENT};
+use std::collections::HashMap;
+
+use crate::error::{Result, TwoCaptchaError};
+use crate::solver::TwoCaptcha;
+
+/// Placeholder replaced by the solved token in a [`FormTemplate`]
+pub const TOKEN_PLACEHOLDER: &str = "{{token}}";
+
+/// Template of the request to send to a target site once a captcha is solved
+///
+/// Field values and the raw body may contain [`TOKEN_PLACEHOLDER`], which is
+/// substituted with the token before the request is sent.
+#[derive(Debug, Clone)]
+pub struct FormTemplate {
+    url: String,
+    fields: Vec<(String, String)>,
+    body: Option<(String, String)>,
+    user_agent: Option<String>,
+    cookies: Vec<(String, String)>,
+}
+
+impl FormTemplate {
+    /// Create a template posting to `url`
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            fields: Vec::new(),
+            body: None,
+            user_agent: None,
+            cookies: Vec::new(),
+        }
+    }
+
+    /// Add a urlencoded form field
+    pub fn field(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields.push((name.into(), value.into()));
+        self
+    }
+
+    /// Send a raw body instead of form fields
+    pub fn body(mut self, body: impl Into<String>, content_type: impl Into<String>) -> Self {
+        self.body = Some((body.into(), content_type.into()));
+        self
+    }
+
+    /// User agent to send (defaults to the `userAgent` of the solved task)
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Add a cookie to send with the request
+    pub fn cookie(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.cookies.push((name.into(), value.into()));
+        self
+    }
+
+    /// Build the request with the token substituted
+    fn build(
+        &self,
+        client: &reqwest::Client,
+        token: &str,
+        user_agent: Option<&str>,
+        cookies: &HashMap<String, String>,
+    ) -> reqwest::RequestBuilder {
+        let mut request = client.post(&self.url);
+
+        request = match &self.body {
+            Some((body, content_type)) => request
+                .header(CONTENT_TYPE, content_type)
+                .body(body.replace(TOKEN_PLACEHOLDER, token)),
+            None => {
+                let fields: Vec<(&str, String)> = self
+                    .fields
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.replace(TOKEN_PLACEHOLDER, token)))
+                    .collect();
+                request.form(&fields)
+            }
+        };
+
+        if let Some(user_agent) = self.user_agent.as_deref().or(user_agent) {
+            request = request.header(USER_AGENT, user_agent);
+        }
+
+        let cookie_header = cookies
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .chain(self.cookies.iter().map(|(n, v)| (n.as_str(), v.as_str())))
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if !cookie_header.is_empty() {
+            request = request.header(COOKIE, cookie_header);
+        }
+
+        request
+    }
+}
+
+impl TwoCaptcha {
+    /// Solve a captcha and submit the token to the target site in one call
+    ///
+    /// `params` are the raw task parameters, as accepted by
+    /// [`TwoCaptcha::solve`]. Cookies returned with an extended response are
+    /// sent along with the template's own cookies, and the task's
+    /// `userAgent` is reused unless the template sets one.
+    pub async fn solve_and_post(
+        &self,
+        params: HashMap<String, String>,
+        form: &FormTemplate,
+    ) -> Result<reqwest::Response> {
+        let user_agent = params.get("userAgent").cloned();
+        let result = self.solve(None, None, params).await?;
+
+        let extended = result.extended.as_ref();
+        let token = result
+            .code
+            .clone()
+            .or_else(|| {
+                extended
+                    .and_then(|e| e.get("code"))
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+            })
+            .ok_or_else(|| {
+                TwoCaptchaError::api(format!("no token in result {}", result.captcha_id))
+            })?;
+        let cookies: HashMap<String, String> = extended
+            .and_then(|e| e.get("cookies"))
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default();
+
+        let client = reqwest::Client::new();
+        let response = form
+            .build(&client, &token, user_agent.as_deref(), &cookies)
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_form_template_substitution() {
+        let form = FormTemplate::new("https://example.com/login")
+            .field("g-recaptcha-response", TOKEN_PLACEHOLDER)
+            .field("user", "alice")
+            .cookie("session", "abc");
+
+        let client = reqwest::Client::new();
+        let request = form
+            .build(&client, "TOKEN123", Some("agent/1.0"), &HashMap::new())
+            .build()
+            .unwrap();
+
+        let body = request.body().unwrap().as_bytes().unwrap();
+        assert_eq!(body, b"g-recaptcha-response=TOKEN123&user=alice");
+        assert_eq!(request.headers()[USER_AGENT], "agent/1.0");
+        assert_eq!(request.headers()[COOKIE], "session=abc");
+    }
+}
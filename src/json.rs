@@ -0,0 +1,89 @@
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::Result;
+
+/// Repairs a response body that failed to parse as JSON
+///
+/// Set one through `TwoCaptchaConfig::json_repair` to talk to
+/// 2captcha-compatible servers returning slightly broken JSON.
+pub trait JsonRepair: fmt::Debug + Send + Sync {
+    /// Return a fixed body, or `None` when it cannot be repaired
+    fn repair(&self, body: &str) -> Option<String>;
+}
+
+/// Repair for the usual defects: byte order mark, text around the JSON
+/// value and single-quoted strings
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LenientJson;
+
+impl JsonRepair for LenientJson {
+    fn repair(&self, body: &str) -> Option<String> {
+        let body = body.trim_start_matches('\u{feff}');
+        let start = body.find(['{', '['])?;
+        let end = body.rfind(['}', ']'])?;
+        if end < start {
+            return None;
+        }
+
+        let body = &body[start..=end];
+        if body.contains('"') {
+            Some(body.to_string())
+        } else {
+            Some(body.replace('\'', "\""))
+        }
+    }
+}
+
+/// JSON parsing falling back to a [`JsonRepair`], counting repairs
+#[derive(Debug, Default)]
+pub(crate) struct JsonParser {
+    repairs: AtomicU64,
+}
+
+impl JsonParser {
+    pub(crate) fn parse<T: DeserializeOwned>(
+        &self,
+        body: &str,
+        repair: Option<&dyn JsonRepair>,
+    ) -> Result<T> {
+        let error = match serde_json::from_str(body) {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+
+        let value = repair
+            .and_then(|repair| repair.repair(body))
+            .and_then(|fixed| serde_json::from_str(&fixed).ok())
+            .ok_or(error)?;
+        self.repairs.fetch_add(1, Ordering::Relaxed);
+        Ok(value)
+    }
+
+    /// Number of bodies that only parsed after a repair
+    pub(crate) fn repairs(&self) -> u64 {
+        self.repairs.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_lenient_json() {
+        let parser = JsonParser::default();
+        let body = "\u{feff}{'status': 1, 'request': '123'}\n<!-- cached -->";
+
+        assert!(parser.parse::<Value>(body, None).is_err());
+        let value: Value = parser.parse(body, Some(&LenientJson)).unwrap();
+        assert_eq!(value["request"], "123");
+        assert_eq!(parser.repairs(), 1);
+
+        let value: Value = parser.parse(r#"{"status":1}"#, Some(&LenientJson)).unwrap();
+        assert_eq!(value["status"], 1);
+        assert_eq!(parser.repairs(), 1);
+    }
+}
@@ -0,0 +1,50 @@
+//! Guessing the `lang` of a captcha from the script of its hint text
+
+/// Language code of the first script in `text` that identifies one
+///
+/// Only scripts used by a single language 2captcha assigns workers for are
+/// recognized. Latin text is ambiguous and yields `None`, as do digits and
+/// punctuation. Japanese kana win over Han characters, which are shared
+/// with Chinese.
+pub fn detect_lang(text: &str) -> Option<&'static str> {
+    if text.chars().any(is_kana) {
+        return Some("ja");
+    }
+    text.chars().find_map(script_lang)
+}
+
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30ff}')
+}
+
+fn script_lang(c: char) -> Option<&'static str> {
+    let lang = match c {
+        '\u{0370}'..='\u{03ff}' => "el",
+        '\u{0400}'..='\u{04ff}' => "ru",
+        '\u{0530}'..='\u{058f}' => "hy",
+        '\u{0590}'..='\u{05ff}' => "he",
+        '\u{0600}'..='\u{06ff}' => "ar",
+        '\u{0900}'..='\u{097f}' => "hi",
+        '\u{0980}'..='\u{09ff}' => "bn",
+        '\u{0e00}'..='\u{0e7f}' => "th",
+        '\u{10a0}'..='\u{10ff}' => "ka",
+        '\u{1100}'..='\u{11ff}' | '\u{ac00}'..='\u{d7af}' => "ko",
+        '\u{4e00}'..='\u{9fff}' => "zh",
+        _ => return None,
+    };
+    Some(lang)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_lang() {
+        assert_eq!(detect_lang("Введите текст с картинки"), Some("ru"));
+        assert_eq!(detect_lang("1 + 2 = ?"), None);
+        assert_eq!(detect_lang("Type the text"), None);
+        assert_eq!(detect_lang("画像の文字を入力してください"), Some("ja"));
+        assert_eq!(detect_lang("输入图片中的文字"), Some("zh"));
+    }
+}
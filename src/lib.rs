@@ -8,7 +8,6 @@
 //!
 //! ```no_run
 //! use twocaptcha::{TwoCaptcha, TwoCaptchaConfig, RecaptchaVersion};
-//! use std::collections::HashMap;
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -28,17 +27,92 @@
 //! }
 //! ```
 
+// Lets `#[derive(CaptchaTask)]` output resolve `::twocaptcha` inside this crate
+extern crate self as twocaptcha;
+
+pub mod actor;
+pub mod answer;
 pub mod api;
+pub mod api_v2;
+pub mod backoff;
+mod breaker;
+pub mod builder;
+#[cfg(feature = "test-util")]
+pub mod chaos;
+mod clock;
+pub mod config;
+mod cooldown;
+mod dedupe;
+pub mod diagnostics;
 pub mod error;
+pub mod extras;
+pub mod fallback;
+pub mod files;
+pub mod form;
+pub mod json;
+#[cfg(feature = "lang-detect")]
+pub mod lang;
+pub mod methods;
+pub mod metrics;
+#[cfg(feature = "pinning")]
+pub mod pinning;
+mod polls;
+#[cfg(feature = "image")]
+pub mod precheck;
+pub mod pricing;
+mod recent;
+pub mod rotate;
+#[cfg(test)]
+mod scripted;
+mod secret;
+pub mod session;
 pub mod solver;
+pub mod task;
+mod trace;
+pub mod traits;
 pub mod types;
 pub mod utils;
+pub mod webhook;
 
 // Re-export main types
-pub use api::ApiClient;
-pub use error::{Result, TwoCaptchaError};
+pub use actor::SolverHandle;
+pub use answer::CaseMode;
+pub use api::{ApiClient, ApiPaths, DnsConfig, Endpoint, HttpTransport, Params, TransportResponse};
+pub use api_v2::ApiVersion;
+pub use builder::TwoCaptchaBuilder;
+#[cfg(feature = "test-util")]
+pub use chaos::ChaosLayer;
+pub use config::{CircuitBreaker, ConfigProfile, CoolDown, Scope, SubmitRetry, TimeoutProfile};
+pub use cooldown::CoolDownStatus;
+pub use diagnostics::DiagnosticsBundle;
+pub use error::{ApiErrorCode, ErrorContext, ErrorKind, Result, TwoCaptchaError};
+pub use extras::Extras;
+pub use fallback::{FallbackResult, FallbackTask, SolvedBy};
+pub use files::{FileAccess, FileStats};
+pub use form::FormTemplate;
+pub use json::{JsonRepair, LenientJson};
+pub use methods::ParamPolicy;
+pub use metrics::{BandwidthLedger, BandwidthUsage};
+#[cfg(feature = "pinning")]
+pub use pinning::Pin;
+pub use polls::PollStatus;
+pub use pricing::PriceTable;
+pub use rotate::RotateWidget;
+pub use secret::ApiKey;
+#[cfg(feature = "secrets")]
+pub use secret::SecretProvider;
+pub use session::CaptchaSession;
 pub use solver::{TwoCaptcha, TwoCaptchaConfig};
-pub use types::{AudioLanguage, Balance, CaptchaResult, ExtendedResponse, Proxy, RecaptchaVersion};
+pub use task::{CaptchaTask, Task};
+#[cfg(feature = "derive")]
+pub use twocaptcha_derive::CaptchaTask;
+pub use types::{
+    Attempt, AttemptLog, AudioLanguage, Balance, BalanceDetails, CaptchaImage, CaptchaResult,
+    Cookies, ExtendedResponse, Fingerprint, Hint, LeminSolution, Proxy, RecaptchaVersion,
+    ReportOutcome, SubmitReceipt, V2Task,
+};
+pub use webhook::Webhook;
 
 // Re-export commonly used traits
 pub use error::SolverExceptions;
+pub use traits::CaptchaSolver;
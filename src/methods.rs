@@ -0,0 +1,218 @@
+//! Machine-readable list of the supported captcha methods
+//!
+//! Generated from the schemas used to convert and check tasks, so it always
+//! matches what the solver accepts. Parameter names are the `in.php` ones;
+//! image methods take `body` as base64 or, instead, an uploaded `file`.
+//!
+//! ```
+//! let catalog = twocaptcha::methods::catalog();
+//! let hcaptcha = catalog.iter().find(|method| method.name == "hcaptcha").unwrap();
+//! assert_eq!(hcaptcha.required, ["pageurl", "sitekey"]);
+//! println!("{}", serde_json::to_string_pretty(&catalog).unwrap());
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::task::SCHEMAS;
+
+/// Shape of the answer a method returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultKind {
+    /// Recognized text, a number or selected cells
+    Text,
+    /// Token to submit with the page's form
+    Token,
+    /// Points clicked on the image
+    Coordinates,
+    /// JSON object of several values, e.g. GeeTest's
+    Json,
+}
+
+/// One method variant and the parameters it takes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MethodInfo {
+    /// Value of the `method` parameter
+    pub name: &'static str,
+    /// Other parameters selecting this variant, e.g. `version=v3`
+    pub variant: BTreeMap<&'static str, &'static str>,
+    /// Task type of the JSON API, without the `Proxyless` suffix
+    pub task_type: &'static str,
+    pub required: Vec<&'static str>,
+    pub optional: Vec<&'static str>,
+    pub result: ResultKind,
+}
+
+/// Handling of parameters the catalog does not list for a task's method,
+/// see [`TwoCaptchaConfig::param_policy`](crate::TwoCaptchaConfig::param_policy)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamPolicy {
+    /// Send them as given
+    #[default]
+    Allow,
+    /// Send them, recording each in the telemetry errors
+    Warn,
+    /// Reject the task before it is submitted
+    Deny,
+}
+
+/// Parameters every method accepts besides its own
+const COMMON_PARAMS: &[&str] = &[
+    "method",
+    "key",
+    "soft_id",
+    "json",
+    "pingback",
+    "header_acao",
+    "proxy",
+    "proxytype",
+    "file",
+    "lang",
+    "textinstructions",
+    "imginstructions",
+    "previousID",
+    "can_no_answer",
+    "api_server",
+];
+
+/// Parameters, by their `in.php` names, that no variant of the task's
+/// method takes, each with the closest known name when one is near
+///
+/// Methods missing from the catalog are not checked. `method=post` also
+/// takes the parameters of `base64`, being its upload form.
+pub(crate) fn unknown_params(
+    params: &HashMap<String, String>,
+) -> Vec<(String, Option<&'static str>)> {
+    let Some(method) = params.get("method") else {
+        return Vec::new();
+    };
+    let variants = catalog()
+        .into_iter()
+        .filter(|info| info.name == method || (method == "post" && info.name == "base64"));
+
+    let mut known: Vec<&'static str> = COMMON_PARAMS.to_vec();
+    for info in variants {
+        known.extend(info.variant.keys());
+        known.extend(info.required);
+        known.extend(info.optional);
+    }
+    if known.len() == COMMON_PARAMS.len() {
+        return Vec::new();
+    }
+
+    let mut unknown: Vec<_> = params
+        .keys()
+        .filter(|key| !known.contains(&key.as_str()) && !key.starts_with("file_"))
+        .map(|key| {
+            let closest = known
+                .iter()
+                .map(|name| (edit_distance(key, name), *name))
+                .filter(|(distance, _)| *distance <= 2)
+                .min()
+                .map(|(_, name)| name);
+            (key.clone(), closest)
+        })
+        .collect();
+    unknown.sort();
+    unknown
+}
+
+/// Levenshtein distance between two parameter names, ignoring case and
+/// separators so `site_key` matches `sitekey`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let normalize = |name: &str| -> Vec<char> {
+        name.chars()
+            .filter(|c| !matches!(c, '_' | '-'))
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    };
+    let (a, b) = (normalize(a), normalize(b));
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Every supported method variant
+pub fn catalog() -> Vec<MethodInfo> {
+    SCHEMAS
+        .iter()
+        .map(|schema| {
+            let mut variant: BTreeMap<_, _> = schema.markers().iter().copied().collect();
+            MethodInfo {
+                name: variant.remove("method").unwrap_or_default(),
+                variant,
+                task_type: schema.task_type(),
+                required: schema.required().to_vec(),
+                optional: schema.optional().collect(),
+                result: schema.result(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog() {
+        let catalog = catalog();
+        assert_eq!(catalog.len(), SCHEMAS.len());
+
+        let v3 = catalog
+            .iter()
+            .find(|method| method.variant.get("version") == Some(&"v3"))
+            .unwrap();
+        assert_eq!(v3.name, "userrecaptcha");
+        assert_eq!(v3.result, ResultKind::Token);
+        assert!(v3.optional.contains(&"min_score"));
+
+        for method in &catalog {
+            assert!(!method.name.is_empty());
+            assert!(!method.required.is_empty());
+            assert!(method.required.iter().all(|r| !method.optional.contains(r)));
+        }
+
+        let json = serde_json::to_value(&catalog).unwrap();
+        assert_eq!(json[0]["result"], "text");
+    }
+
+    #[test]
+    fn test_unknown_params() {
+        let params = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+
+        let turnstile = params(&[
+            ("method", "turnstile"),
+            ("sitekey", "0x4AAA"),
+            ("stie_key", "0x4AAA"),
+            ("pageurl", "https://example.com"),
+            ("soft_id", "4580"),
+        ]);
+        assert_eq!(
+            unknown_params(&turnstile),
+            [("stie_key".to_string(), Some("sitekey"))]
+        );
+
+        // Parameters of the v3 variant are known to a v2 task of the method
+        let recaptcha = params(&[("method", "userrecaptcha"), ("min_score", "0.3")]);
+        assert!(unknown_params(&recaptcha).is_empty());
+        let upload = params(&[("method", "post"), ("regsense", "1"), ("file_1", "a")]);
+        assert!(unknown_params(&upload).is_empty());
+        assert!(unknown_params(&params(&[("method", "custom"), ("x", "1")])).is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Traffic totals for a single API endpoint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BandwidthUsage {
+    pub requests: u64,
+    /// Approximate size of the request payloads
+    pub bytes_sent: u64,
+    /// Size of the (decompressed) response bodies
+    pub bytes_received: u64,
+}
+
+impl std::ops::Add for BandwidthUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            requests: self.requests + other.requests,
+            bytes_sent: self.bytes_sent + other.bytes_sent,
+            bytes_received: self.bytes_received + other.bytes_received,
+        }
+    }
+}
+
+/// Lock-free counters backing a [`BandwidthUsage`]
+#[derive(Debug, Default)]
+pub(crate) struct UsageCounters {
+    requests: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+}
+
+impl UsageCounters {
+    pub(crate) fn record(&self, bytes_sent: u64, bytes_received: u64) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes_sent, Ordering::Relaxed);
+        self.bytes_received
+            .fetch_add(bytes_received, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> BandwidthUsage {
+        BandwidthUsage {
+            requests: self.requests.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Running record of the traffic exchanged with the API
+#[derive(Debug, Default)]
+pub struct BandwidthLedger {
+    pub(crate) submit: UsageCounters,
+    pub(crate) result: UsageCounters,
+}
+
+impl BandwidthLedger {
+    /// Traffic of captcha submissions (`in.php`)
+    pub fn submit(&self) -> BandwidthUsage {
+        self.submit.snapshot()
+    }
+
+    /// Traffic of result polls and other queries (`res.php`)
+    pub fn result(&self) -> BandwidthUsage {
+        self.result.snapshot()
+    }
+
+    /// Traffic of all endpoints combined
+    pub fn total(&self) -> BandwidthUsage {
+        self.submit() + self.result()
+    }
+}
+
+/// Number of recent solves per method used for time estimates
+const SOLVE_TIME_WINDOW: usize = 50;
+
+/// Average solve times published by 2captcha, in seconds, per `in.php` method
+const DOCUMENTED_SOLVE_TIMES: &[(&str, u64)] = &[
+    ("base64", 10),
+    ("post", 10),
+    ("audio", 15),
+    ("rotatecaptcha", 15),
+    ("userrecaptcha", 40),
+    ("hcaptcha", 30),
+    ("funcaptcha", 30),
+    ("geetest", 20),
+    ("geetest_v4", 20),
+    ("keycaptcha", 30),
+    ("capy", 20),
+    ("lemin", 20),
+    ("atb_captcha", 20),
+    ("turnstile", 15),
+    ("amazon_waf", 25),
+    ("mt_captcha", 20),
+    ("friendly_captcha", 20),
+    ("tencent", 20),
+    ("cutcaptcha", 20),
+    ("datadome", 25),
+    ("cybersiara", 20),
+    ("yandex", 20),
+];
+
+/// Rolling window of recent solve durations per method
+#[derive(Debug, Default)]
+pub(crate) struct SolveTimes {
+    recent: Mutex<HashMap<String, VecDeque<Duration>>>,
+}
+
+impl SolveTimes {
+    pub(crate) fn record(&self, method: &str, elapsed: Duration) {
+        let mut recent = self.recent.lock().unwrap();
+        let window = recent.entry(method.to_string()).or_default();
+        if window.len() == SOLVE_TIME_WINDOW {
+            window.pop_front();
+        }
+        window.push_back(elapsed);
+    }
+
+    /// Mean of the recent solves, or the documented average before any
+    pub(crate) fn estimate(&self, method: &str) -> Option<Duration> {
+        let recent = self.recent.lock().unwrap();
+        match recent.get(method) {
+            Some(window) if !window.is_empty() => {
+                Some(window.iter().sum::<Duration>() / window.len() as u32)
+            }
+            _ => DOCUMENTED_SOLVE_TIMES
+                .iter()
+                .find(|(name, _)| *name == method)
+                .map(|(_, secs)| Duration::from_secs(*secs)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_totals() {
+        let ledger = BandwidthLedger::default();
+        ledger.submit.record(100, 12);
+        ledger.result.record(40, 20);
+        ledger.result.record(40, 20);
+
+        assert_eq!(ledger.submit().requests, 1);
+        assert_eq!(
+            ledger.total(),
+            BandwidthUsage {
+                requests: 3,
+                bytes_sent: 180,
+                bytes_received: 52,
+            }
+        );
+    }
+
+    #[test]
+    fn test_solve_time_estimate() {
+        let times = SolveTimes::default();
+        assert_eq!(times.estimate("hcaptcha"), Some(Duration::from_secs(30)));
+        assert_eq!(times.estimate("unknown"), None);
+
+        times.record("hcaptcha", Duration::from_secs(10));
+        times.record("hcaptcha", Duration::from_secs(20));
+        assert_eq!(times.estimate("hcaptcha"), Some(Duration::from_secs(15)));
+    }
+}
@@ -0,0 +1,149 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{WebPkiSupportedAlgorithms, verify_tls12_signature, verify_tls13_signature};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::server::ParsedCertificate;
+use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::error::{Result, TwoCaptchaError};
+
+/// Expected SHA-256 digest of the API server's certificate or public key
+///
+/// Pins are written as base64, optionally prefixed with `sha256//` as in
+/// curl's `--pinnedpubkey`. A public key pin can be computed with:
+///
+/// ```text
+/// openssl s_client -connect 2captcha.com:443 </dev/null 2>/dev/null \
+///   | openssl x509 -pubkey -noout | openssl pkey -pubin -outform der \
+///   | openssl dgst -sha256 -binary | base64
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pin {
+    /// Digest of the DER encoded leaf certificate
+    Certificate([u8; 32]),
+    /// Digest of the DER encoded SubjectPublicKeyInfo of the leaf certificate
+    PublicKey([u8; 32]),
+}
+
+impl Pin {
+    /// Pin the leaf certificate
+    pub fn certificate(digest: &str) -> Result<Self> {
+        Self::decode(digest).map(Pin::Certificate)
+    }
+
+    /// Pin the public key of the leaf certificate, surviving certificate renewals
+    pub fn public_key(digest: &str) -> Result<Self> {
+        Self::decode(digest).map(Pin::PublicKey)
+    }
+
+    fn decode(digest: &str) -> Result<[u8; 32]> {
+        let digest = digest.strip_prefix("sha256//").unwrap_or(digest);
+        STANDARD.decode(digest)?.try_into().map_err(|_| {
+            TwoCaptchaError::Validation("pin must be a base64 SHA-256 digest".to_string())
+        })
+    }
+}
+
+/// Accepts only servers presenting a pinned certificate or public key
+///
+/// Replaces chain validation: a connection succeeds only when the leaf
+/// certificate matches one of the pins, whoever signed it.
+#[derive(Debug)]
+struct PinnedVerifier {
+    pins: Vec<Pin>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl PinnedVerifier {
+    fn matches(&self, certificate: &[u8], public_key: &[u8]) -> bool {
+        let certificate: [u8; 32] = Sha256::digest(certificate).into();
+        let public_key: [u8; 32] = Sha256::digest(public_key).into();
+
+        self.pins.iter().any(|pin| match pin {
+            Pin::Certificate(digest) => *digest == certificate,
+            Pin::PublicKey(digest) => *digest == public_key,
+        })
+    }
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let parsed = ParsedCertificate::try_from(end_entity)?;
+
+        if self.matches(end_entity, &parsed.subject_public_key_info()) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "server certificate does not match any pin".to_string(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+/// TLS configuration trusting only servers that match `pins`
+pub(crate) fn tls_config(pins: Vec<Pin>) -> ClientConfig {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = PinnedVerifier {
+        pins,
+        algorithms: provider.signature_verification_algorithms,
+    };
+
+    ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .expect("ring supports the default protocol versions")
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pin_matching() {
+        let digest = STANDARD.encode(Sha256::digest(b"public key"));
+        let pin = Pin::public_key(&format!("sha256//{digest}")).unwrap();
+        assert!(Pin::certificate("not a digest").is_err());
+        assert!(Pin::certificate(&STANDARD.encode(b"short")).is_err());
+
+        let verifier = PinnedVerifier {
+            pins: vec![pin],
+            algorithms: rustls::crypto::ring::default_provider().signature_verification_algorithms,
+        };
+        assert!(verifier.matches(b"certificate", b"public key"));
+        assert!(!verifier.matches(b"public key", b"other key"));
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+use crate::error::{Result, TwoCaptchaError};
+
+/// Final outcome of polling a captcha id, shared with every waiter
+#[derive(Debug, Clone)]
+pub(crate) enum PollOutcome {
+    Ready(String),
+    Failed(Arc<TwoCaptchaError>),
+}
+
+impl PollOutcome {
+    fn from_result(result: &Result<String>) -> Option<Self> {
+        match result {
+            Ok(code) => Some(PollOutcome::Ready(code.clone())),
+            // Timeouts are specific to each waiter, so they are not shared
+            Err(TwoCaptchaError::Timeout { .. }) => None,
+            Err(e) => Some(PollOutcome::Failed(Arc::new(e.duplicate()))),
+        }
+    }
+
+    pub(crate) fn into_result(self) -> Result<String> {
+        match self {
+            PollOutcome::Ready(code) => Ok(code),
+            PollOutcome::Failed(error) => Err(error.duplicate()),
+        }
+    }
+}
+
+type OutcomeReceiver = watch::Receiver<Option<PollOutcome>>;
+
+/// Finished polls remembered for [`InFlightPolls::status`]
+const MAX_FINISHED: usize = 256;
+
+/// Where the answer of a captcha stands, see [`TwoCaptcha::status`]
+///
+/// [`TwoCaptcha::status`]: crate::TwoCaptcha::status
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PollStatus {
+    /// Still being solved
+    NotReady {
+        /// Time since polling started
+        elapsed: Duration,
+        /// Result requests made so far
+        polls: u32,
+    },
+    Ready,
+    /// Failed with this error code, e.g. `ERROR_CAPTCHA_UNSOLVABLE`
+    Failed {
+        code: String,
+    },
+}
+
+/// Progress of an id being polled
+#[derive(Debug)]
+struct PollProgress {
+    started: Instant,
+    polls: AtomicU32,
+}
+
+#[derive(Debug)]
+struct PollEntry {
+    receiver: OutcomeReceiver,
+    progress: Arc<PollProgress>,
+}
+
+/// Result polls currently running, keyed by captcha id
+///
+/// The first waiter of an id becomes the leader and polls `res.php`; later
+/// waiters follow and receive the leader's outcome. If the leader goes away
+/// before an outcome is known, the next waiter to rejoin takes over.
+#[derive(Debug, Default)]
+pub(crate) struct InFlightPolls {
+    polls: Mutex<HashMap<String, PollEntry>>,
+    finished: Mutex<VecDeque<(String, PollStatus)>>,
+}
+
+/// Role of a waiter joining the polls of an id
+pub(crate) enum PollRole {
+    Leader(PollLeader),
+    Follower(OutcomeReceiver),
+}
+
+/// Handle of the waiter polling an id on behalf of the others
+pub(crate) struct PollLeader {
+    polls: Arc<InFlightPolls>,
+    id: String,
+    sender: watch::Sender<Option<PollOutcome>>,
+    progress: Arc<PollProgress>,
+}
+
+impl PollLeader {
+    /// Count a result request
+    pub(crate) fn record_poll(&self) {
+        self.progress.polls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Result requests made so far
+    pub(crate) fn polls(&self) -> u32 {
+        self.progress.polls.load(Ordering::Relaxed)
+    }
+
+    /// Share the outcome of polling with the followers
+    pub(crate) fn publish(self, result: &Result<String>) {
+        if let Some(outcome) = PollOutcome::from_result(result) {
+            let status = match result {
+                Ok(_) => PollStatus::Ready,
+                Err(e) => PollStatus::Failed {
+                    code: e.attempt_code(),
+                },
+            };
+            let mut finished = self.polls.finished.lock().unwrap();
+            finished.retain(|(id, _)| *id != self.id);
+            if finished.len() == MAX_FINISHED {
+                finished.pop_front();
+            }
+            finished.push_back((self.id.clone(), status));
+            drop(finished);
+
+            let _ = self.sender.send(Some(outcome));
+        }
+    }
+}
+
+impl Drop for PollLeader {
+    fn drop(&mut self) {
+        let mut polls = self.polls.polls.lock().unwrap();
+        if polls
+            .get(&self.id)
+            .is_some_and(|entry| entry.receiver.same_channel(&self.sender.subscribe()))
+        {
+            polls.remove(&self.id);
+        }
+    }
+}
+
+impl InFlightPolls {
+    /// Join the polls of `id`, as leader if nobody is polling it yet
+    pub(crate) fn join(self: &Arc<Self>, id: &str) -> PollRole {
+        let mut polls = self.polls.lock().unwrap();
+
+        if let Some(entry) = polls.get(id)
+            && entry.receiver.has_changed().is_ok()
+        {
+            return PollRole::Follower(entry.receiver.clone());
+        }
+
+        let (sender, receiver) = watch::channel(None);
+        let progress = Arc::new(PollProgress {
+            started: Instant::now(),
+            polls: AtomicU32::new(0),
+        });
+        polls.insert(
+            id.to_string(),
+            PollEntry {
+                receiver,
+                progress: Arc::clone(&progress),
+            },
+        );
+
+        PollRole::Leader(PollLeader {
+            polls: Arc::clone(self),
+            id: id.to_string(),
+            sender,
+            progress,
+        })
+    }
+
+    /// Status of `id`, if it is being polled or finished recently
+    pub(crate) fn status(&self, id: &str) -> Option<PollStatus> {
+        if let Some(entry) = self.polls.lock().unwrap().get(id) {
+            return Some(PollStatus::NotReady {
+                elapsed: entry.progress.started.elapsed(),
+                polls: entry.progress.polls.load(Ordering::Relaxed),
+            });
+        }
+        self.finished
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(finished, _)| finished == id)
+            .map(|(_, status)| status.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_followers_receive_leader_outcome() {
+        let polls = Arc::new(InFlightPolls::default());
+
+        let PollRole::Leader(leader) = polls.join("1") else {
+            panic!("first waiter should lead");
+        };
+        let PollRole::Follower(mut follower) = polls.join("1") else {
+            panic!("second waiter should follow");
+        };
+
+        leader.record_poll();
+        assert!(matches!(
+            polls.status("1"),
+            Some(PollStatus::NotReady { polls: 1, .. })
+        ));
+
+        leader.publish(&Ok("answer".to_string()));
+        let outcome = follower.wait_for(Option::is_some).await.unwrap().clone();
+        assert_eq!(outcome.unwrap().into_result().unwrap(), "answer");
+        assert_eq!(polls.status("1"), Some(PollStatus::Ready));
+        assert_eq!(polls.status("2"), None);
+
+        assert!(matches!(polls.join("1"), PollRole::Leader(_)));
+
+        let PollRole::Leader(leader) = polls.join("2") else {
+            panic!("first waiter should lead");
+        };
+        let PollRole::Follower(mut follower) = polls.join("2") else {
+            panic!("second waiter should follow");
+        };
+        leader.publish(&Err(TwoCaptchaError::api("ERROR_CAPTCHA_UNSOLVABLE")));
+        let outcome = follower.wait_for(Option::is_some).await.unwrap().clone();
+        let error = outcome.unwrap().into_result().unwrap_err();
+        assert_eq!(error.kind(), crate::ErrorKind::Unsolvable);
+        assert_eq!(error.to_string(), "API error: ERROR_CAPTCHA_UNSOLVABLE");
+    }
+
+    #[tokio::test]
+    async fn test_follower_takes_over_after_timeout() {
+        let polls = Arc::new(InFlightPolls::default());
+
+        let PollRole::Leader(leader) = polls.join("1") else {
+            panic!("first waiter should lead");
+        };
+        let PollRole::Follower(mut follower) = polls.join("1") else {
+            panic!("second waiter should follow");
+        };
+
+        leader.publish(&Err(TwoCaptchaError::timeout(
+            "1",
+            std::time::Duration::from_secs(1),
+        )));
+        assert!(follower.wait_for(Option::is_some).await.is_err());
+        assert!(matches!(polls.join("1"), PollRole::Leader(_)));
+    }
+}
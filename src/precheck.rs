@@ -0,0 +1,103 @@
+//! Local checks rejecting captcha images that cannot be solved
+//!
+//! Enabled with the `image` feature. Images failing a check are refused with
+//! a validation error before they are submitted (and paid for).
+
+use base64::Engine;
+use bytes::Bytes;
+use image::GenericImageView;
+use std::collections::HashMap;
+
+use crate::error::{Result, TwoCaptchaError};
+
+/// Smallest width or height of a solvable image, in pixels
+pub const MIN_SIDE: u32 = 5;
+/// Largest width or height of a solvable image, in pixels
+pub const MAX_SIDE: u32 = 10_000;
+
+/// Reject empty, tiny, huge and single-color images
+///
+/// Data in a format this crate cannot decode is let through, since the API
+/// accepts more formats than are compiled in.
+pub fn check_image(bytes: &[u8]) -> Result<()> {
+    if bytes.is_empty() {
+        return Err(invalid("the image is empty"));
+    }
+    if image::guess_format(bytes).is_err() {
+        return Ok(());
+    }
+    let Ok(decoded) = image::load_from_memory(bytes) else {
+        return Err(invalid("the image cannot be decoded"));
+    };
+
+    let (width, height) = decoded.dimensions();
+    if width.min(height) < MIN_SIDE || width.max(height) > MAX_SIDE {
+        return Err(invalid(&format!(
+            "the image is {width}x{height}, sides must be between {MIN_SIDE} and {MAX_SIDE} pixels"
+        )));
+    }
+
+    let pixels = decoded.to_rgba8();
+    let mut colors = pixels.pixels();
+    if let Some(first) = colors.next()
+        && colors.all(|pixel| pixel == first)
+    {
+        return Err(invalid("the image is a single solid color"));
+    }
+    Ok(())
+}
+
+/// Check the image of a `method=base64` task, if it is valid base64
+pub(crate) fn check_params(params: &HashMap<String, String>) -> Result<()> {
+    if params.get("method").map(String::as_str) != Some("base64") {
+        return Ok(());
+    }
+    let Some(body) = params.get("body") else {
+        return Ok(());
+    };
+    match base64::engine::general_purpose::STANDARD.decode(body) {
+        Ok(bytes) => check_image(&bytes),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Check uploaded captcha images (`file`, `file_1`, ...), leaving hints aside
+pub(crate) fn check_uploads(files: &HashMap<String, Bytes>) -> Result<()> {
+    files
+        .iter()
+        .filter(|(key, _)| key.starts_with("file"))
+        .try_for_each(|(_, bytes)| check_image(bytes))
+}
+
+fn invalid(reason: &str) -> TwoCaptchaError {
+    TwoCaptchaError::Validation(format!("Captcha image rejected: {reason}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    fn png(image: RgbImage) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_check_image() {
+        assert!(check_image(&[]).is_err());
+        assert!(check_image(b"not an image").is_ok());
+
+        let solid = RgbImage::from_pixel(40, 20, image::Rgb([255, 255, 255]));
+        assert!(check_image(&png(solid.clone())).is_err());
+        assert!(check_image(&png(RgbImage::new(2, 20))).is_err());
+
+        let mut text = solid;
+        text.put_pixel(10, 10, image::Rgb([0, 0, 0]));
+        assert!(check_image(&png(text)).is_ok());
+    }
+}
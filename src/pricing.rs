@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Per-method price table used to estimate the cost of a task
+///
+/// Keys are the `method` values sent to `in.php` (e.g. `userrecaptcha`,
+/// `hcaptcha`, `post`). reCAPTCHA Enterprise solves can be priced separately
+/// with a `:enterprise` suffix (e.g. `userrecaptcha:enterprise`). Prices are
+/// per single solve, in the account currency.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PriceTable {
+    prices: HashMap<String, f64>,
+}
+
+impl PriceTable {
+    /// Create an empty price table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the price of a single solve for a method
+    pub fn set(&mut self, method: &str, price: f64) -> &mut Self {
+        self.prices.insert(method.to_string(), price);
+        self
+    }
+
+    /// Get the price of a single solve for a method
+    pub fn get(&self, method: &str) -> Option<f64> {
+        self.prices.get(method).copied()
+    }
+
+    /// Estimate the cost of a task from its request parameters
+    ///
+    /// Returns `None` when the method is not present in the table.
+    pub fn estimate(&self, params: &HashMap<String, String>) -> Option<f64> {
+        let method = params.get("method")?;
+
+        if params.get("enterprise").map(String::as_str) == Some("1")
+            && let Some(price) = self.get(&format!("{method}:enterprise"))
+        {
+            return Some(price);
+        }
+
+        self.get(method)
+    }
+}
+
+impl From<HashMap<String, f64>> for PriceTable {
+    fn from(prices: HashMap<String, f64>) -> Self {
+        Self { prices }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate() {
+        let mut table = PriceTable::new();
+        table
+            .set("userrecaptcha", 0.00299)
+            .set("userrecaptcha:enterprise", 0.005);
+
+        let mut params = HashMap::new();
+        params.insert("method".to_string(), "userrecaptcha".to_string());
+        params.insert("enterprise".to_string(), "0".to_string());
+        assert_eq!(table.estimate(&params), Some(0.00299));
+
+        params.insert("enterprise".to_string(), "1".to_string());
+        assert_eq!(table.estimate(&params), Some(0.005));
+
+        params.insert("method".to_string(), "hcaptcha".to_string());
+        assert_eq!(table.estimate(&params), None);
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::types::CaptchaResult;
+
+/// Results kept for late readers of an id
+pub(crate) const RECENT_RESULTS: usize = 64;
+
+/// Least recently used cache of completed results, keyed by captcha id
+///
+/// Lets a second consumer waiting on an id shortly after it completed get
+/// the answer from memory, where the API may already have forgotten it
+/// (`ERROR_WRONG_CAPTCHA_ID`).
+#[derive(Debug)]
+pub(crate) struct RecentResults {
+    capacity: usize,
+    entries: Mutex<VecDeque<CaptchaResult>>,
+}
+
+impl Default for RecentResults {
+    fn default() -> Self {
+        Self::new(RECENT_RESULTS)
+    }
+}
+
+impl RecentResults {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Result of `id`, marking it as recently used
+    pub(crate) fn get(&self, id: &str) -> Option<CaptchaResult> {
+        let mut entries = self.entries.lock().unwrap();
+        let index = entries.iter().position(|result| result.captcha_id == id)?;
+        let result = entries.remove(index)?;
+        entries.push_back(result.clone());
+        Some(result)
+    }
+
+    /// Remember a result, evicting the least recently used one when full
+    pub(crate) fn insert(&self, result: CaptchaResult) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|cached| cached.captcha_id != result.captcha_id);
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(id: &str) -> CaptchaResult {
+        CaptchaResult {
+            captcha_id: id.to_string(),
+            code: Some(format!("answer {id}")),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let recent = RecentResults::new(2);
+        recent.insert(result("1"));
+        recent.insert(result("2"));
+        assert!(recent.get("1").is_some());
+
+        recent.insert(result("3"));
+        assert!(recent.get("2").is_none());
+        assert_eq!(recent.get("1").unwrap().code.unwrap(), "answer 1");
+        assert!(recent.get("3").is_some());
+    }
+}
@@ -0,0 +1,93 @@
+use crate::error::{Result, TwoCaptchaError};
+use crate::types::CaptchaResult;
+
+/// Geometry of a rotate captcha widget, to turn answers into UI actions
+///
+/// 2captcha answers rotate captchas with the clockwise angle to apply to
+/// the image. Slider widgets rotate the image by a fixed number of degrees
+/// per pixel dragged, which this converts to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RotateWidget {
+    /// Degrees the image turns per pixel the slider moves
+    pub degrees_per_pixel: f64,
+    /// Whether dragging the slider right turns the image counter-clockwise
+    pub counter_clockwise: bool,
+}
+
+impl RotateWidget {
+    pub fn new(degrees_per_pixel: f64) -> Self {
+        Self {
+            degrees_per_pixel,
+            counter_clockwise: false,
+        }
+    }
+
+    /// Widget whose slider spans `track_width` pixels for a full turn
+    pub fn with_track(track_width: f64) -> Self {
+        Self::new(360.0 / track_width)
+    }
+
+    /// Pixels to drag the slider to apply `angle`
+    pub fn drag_distance(&self, angle: f64) -> f64 {
+        let angle = if self.counter_clockwise {
+            normalize(-angle)
+        } else {
+            normalize(angle)
+        };
+        angle / self.degrees_per_pixel
+    }
+
+    /// CSS `transform` turning the image by `angle`
+    pub fn css_transform(&self, angle: f64) -> String {
+        format!("rotate({}deg)", normalize(angle))
+    }
+}
+
+/// Angle in degrees, brought into `[0, 360)`
+fn normalize(angle: f64) -> f64 {
+    angle.rem_euclid(360.0)
+}
+
+impl CaptchaResult {
+    /// Parse the angles answering a rotate captcha, one per image
+    pub fn rotate_angles(&self) -> Result<Vec<f64>> {
+        let code = self
+            .code
+            .as_deref()
+            .ok_or_else(|| TwoCaptchaError::Validation("captcha has no answer".to_string()))?;
+
+        code.split(['|', ','])
+            .map(|angle| {
+                angle.trim().parse().map_err(|_| {
+                    TwoCaptchaError::api(format!("cannot parse rotate answer `{code}`"))
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_widget() {
+        let result = CaptchaResult {
+            code: Some("60|-90".to_string()),
+            ..Default::default()
+        };
+        let angles = result.rotate_angles().unwrap();
+        assert_eq!(angles, vec![60.0, -90.0]);
+
+        let widget = RotateWidget::with_track(180.0);
+        assert_eq!(widget.drag_distance(60.0), 30.0);
+        assert_eq!(widget.drag_distance(-90.0), 135.0);
+        assert_eq!(widget.css_transform(-90.0), "rotate(270deg)");
+
+        let widget = RotateWidget {
+            counter_clockwise: true,
+            ..widget
+        };
+        assert_eq!(widget.drag_distance(60.0), 150.0);
+    }
+}
@@ -0,0 +1,105 @@
+//! Transport answering requests from a script, for unit tests
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use indexmap::IndexMap;
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::api::{HttpTransport, Params, TransportResponse};
+use crate::error::{Result, TwoCaptchaError};
+
+/// A request made to a [`Scripted`] transport
+pub(crate) struct Request<'a> {
+    /// Query or form parameters, empty for JSON bodies
+    pub(crate) params: &'a Params,
+    /// Whether it is a result request (`res.php`) rather than a submission
+    pub(crate) poll: bool,
+    /// Requests of the same kind made before this one
+    pub(crate) count: u32,
+}
+
+type Script = dyn Fn(Request<'_>) -> Result<String> + Send + Sync;
+
+/// Transport answering every request with the body the script returns
+///
+/// Submissions and result requests are counted separately, so a script
+/// can refuse the first submissions or fail the first polls.
+pub(crate) struct Scripted {
+    script: Box<Script>,
+    submissions: AtomicU32,
+    polls: AtomicU32,
+}
+
+impl std::fmt::Debug for Scripted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scripted").finish_non_exhaustive()
+    }
+}
+
+impl Scripted {
+    pub(crate) fn new(
+        script: impl Fn(Request<'_>) -> Result<String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            script: Box::new(script),
+            submissions: AtomicU32::new(0),
+            polls: AtomicU32::new(0),
+        }
+    }
+
+    /// Answer every request with `body`
+    pub(crate) fn canned(body: &'static str) -> Self {
+        Self::new(move |_| Ok(body.to_string()))
+    }
+
+    /// Error of a request answered with `502 Bad Gateway`
+    pub(crate) fn bad_gateway() -> TwoCaptchaError {
+        TwoCaptchaError::Network {
+            status: Some(StatusCode::BAD_GATEWAY),
+            body_snippet: String::new(),
+            headers: Vec::new(),
+            source: None,
+        }
+    }
+
+    fn respond(&self, params: &Params, poll: bool) -> Result<TransportResponse> {
+        let counter = if poll { &self.polls } else { &self.submissions };
+        let count = counter.fetch_add(1, Ordering::Relaxed);
+        let body = (self.script)(Request {
+            params,
+            poll,
+            count,
+        })?;
+        Ok(TransportResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body,
+        })
+    }
+}
+
+#[async_trait]
+impl HttpTransport for Scripted {
+    async fn post_form(&self, _url: &str, params: &Params) -> Result<TransportResponse> {
+        self.respond(params, false)
+    }
+
+    async fn post_multipart(
+        &self,
+        _url: &str,
+        params: &Params,
+        _files: IndexMap<String, Bytes>,
+    ) -> Result<TransportResponse> {
+        self.respond(params, false)
+    }
+
+    async fn post_json(&self, url: &str, _body: String) -> Result<TransportResponse> {
+        self.respond(&Params::new(), url.ends_with("getTaskResult"))
+    }
+
+    async fn get(&self, _url: &str, query: &Params) -> Result<TransportResponse> {
+        self.respond(query, true)
+    }
+}
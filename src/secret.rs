@@ -0,0 +1,162 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+use zeroize::Zeroizing;
+
+#[cfg(feature = "secrets")]
+use crate::error::{Result, TwoCaptchaError};
+
+/// API key wiped from memory on drop and printed as `sk_****`
+///
+/// Both `Debug` and `Display` hide the key, so debug-printing a
+/// [`TwoCaptcha`](crate::TwoCaptcha) or its builder never leaks it.
+#[derive(Clone)]
+pub struct ApiKey(Zeroizing<String>);
+
+impl ApiKey {
+    pub fn new(key: String) -> Self {
+        Self(Zeroizing::new(key))
+    }
+
+    /// Raw key, to be copied only into the request that needs it
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Source of the API key, such as a cloud secret manager or a vault
+///
+/// ```ignore
+/// struct Vault(VaultClient);
+///
+/// #[async_trait::async_trait]
+/// impl SecretProvider for Vault {
+///     async fn api_key(&self) -> twocaptcha::Result<String> {
+///         Ok(self.0.read("secret/2captcha").await?.into())
+///     }
+/// }
+///
+/// let key = ApiKey::from_secret_provider(&Vault(client)).await?;
+/// ```
+#[cfg(feature = "secrets")]
+#[async_trait::async_trait]
+pub trait SecretProvider: Send + Sync {
+    async fn api_key(&self) -> Result<String>;
+}
+
+#[cfg(feature = "secrets")]
+impl ApiKey {
+    /// Read the key stored for `service` and `user` in the OS keyring
+    /// (macOS Keychain, Windows Credential Manager, Linux kernel keyring)
+    pub fn from_keyring(service: &str, user: &str) -> Result<Self> {
+        let unavailable = |e: keyring::Error| {
+            TwoCaptchaError::Validation(format!(
+                "cannot read the API key of {service}/{user} from the keyring: {e}"
+            ))
+        };
+        let entry = keyring::Entry::new(service, user).map_err(unavailable)?;
+        Self::non_empty(entry.get_password().map_err(unavailable)?)
+    }
+
+    /// Fetch the key from `provider`
+    pub async fn from_secret_provider(provider: &dyn SecretProvider) -> Result<Self> {
+        Self::non_empty(provider.api_key().await?)
+    }
+
+    fn non_empty(key: String) -> Result<Self> {
+        if key.trim().is_empty() {
+            return Err(TwoCaptchaError::Validation(
+                "the stored API key is empty".to_string(),
+            ));
+        }
+        Ok(Self::new(key))
+    }
+}
+
+impl From<String> for ApiKey {
+    fn from(key: String) -> Self {
+        Self::new(key)
+    }
+}
+
+impl From<&str> for ApiKey {
+    fn from(key: &str) -> Self {
+        Self::new(key.to_string())
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("sk_****")
+    }
+}
+
+impl fmt::Display for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("sk_****")
+    }
+}
+
+/// Captcha ids remembered with the key they were submitted under
+const SUBMITTED_KEYS_CAPACITY: usize = 1024;
+
+/// Keys of recently submitted captchas, by captcha id
+///
+/// Results and reports of a captcha must be requested with the key it was
+/// submitted under, which is no longer the current one after
+/// [`TwoCaptcha::set_api_key`](crate::TwoCaptcha::set_api_key).
+#[derive(Debug, Default)]
+pub(crate) struct SubmittedKeys {
+    entries: Mutex<VecDeque<(String, ApiKey)>>,
+}
+
+impl SubmittedKeys {
+    pub(crate) fn insert(&self, id: &str, key: ApiKey) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == SUBMITTED_KEYS_CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back((id.to_string(), key));
+    }
+
+    /// Key captcha `id` was submitted under, if it is still remembered
+    pub(crate) fn get(&self, id: &str) -> Option<ApiKey> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .rev()
+            .find(|(submitted, _)| submitted == id)
+            .map(|(_, key)| key.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_key_debug_is_redacted() {
+        let key = ApiKey::new("0123456789abcdef".to_string());
+        assert_eq!(key.expose(), "0123456789abcdef");
+        assert_eq!(format!("{key:?}"), "sk_****");
+        assert_eq!(key.to_string(), "sk_****");
+    }
+
+    #[cfg(feature = "secrets")]
+    #[tokio::test]
+    async fn test_from_secret_provider() {
+        struct Fixed(&'static str);
+
+        #[async_trait::async_trait]
+        impl SecretProvider for Fixed {
+            async fn api_key(&self) -> Result<String> {
+                Ok(self.0.to_string())
+            }
+        }
+
+        let key = ApiKey::from_secret_provider(&Fixed("0123456789abcdef")).await;
+        assert_eq!(key.unwrap().expose(), "0123456789abcdef");
+        assert!(ApiKey::from_secret_provider(&Fixed(" ")).await.is_err());
+        assert!(ApiKey::from_keyring("twocaptcha-test", "missing-user").is_err());
+    }
+}
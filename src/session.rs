@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::error::{Result, TwoCaptchaError};
+use crate::extras::Extras;
+use crate::solver::TwoCaptcha;
+use crate::types::{CaptchaImage, CaptchaResult, Hint, Proxy, RecaptchaVersion};
+
+/// A sequence of related captchas solved against the same target
+///
+/// Each solve is sent with the id of the previous one as `previousID`, so
+/// 2captcha hands the rounds of a multi-step challenge (e.g. successive
+/// reCAPTCHA grids) to the same worker. Page URL, proxy, user agent and
+/// cookies set on the session are filled into every token task that does
+/// not set them itself, and every round is recorded in [`CaptchaSession::rounds`].
+#[derive(Debug, Clone)]
+pub struct CaptchaSession {
+    solver: TwoCaptcha,
+    url: Option<String>,
+    proxy: Option<Proxy>,
+    user_agent: Option<String>,
+    cookies: Option<String>,
+    previous_id: Option<String>,
+    rounds: Vec<SessionRound>,
+}
+
+/// Record of one solve made through a [`CaptchaSession`]
+#[derive(Debug, Clone)]
+pub struct SessionRound {
+    /// `in.php` method of the task
+    pub method: String,
+    /// Id of the captcha, when it was accepted
+    pub captcha_id: Option<String>,
+    /// Error message, when the solve failed
+    pub error: Option<String>,
+    pub elapsed: Duration,
+}
+
+impl CaptchaSession {
+    pub fn new(solver: TwoCaptcha) -> Self {
+        Self {
+            solver,
+            url: None,
+            proxy: None,
+            user_agent: None,
+            cookies: None,
+            previous_id: None,
+            rounds: Vec::new(),
+        }
+    }
+
+    /// Page the captchas are found on
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Proxy the workers load the page through
+    pub fn with_proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// User agent of the browser automating the page
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Cookies of the page, as `key1:value1;key2:value2`
+    pub fn with_cookies(mut self, cookies: impl Into<String>) -> Self {
+        self.cookies = Some(cookies.into());
+        self
+    }
+
+    /// Id of the last captcha solved in this session
+    pub fn previous_id(&self) -> Option<&str> {
+        self.previous_id.as_deref()
+    }
+
+    /// Solves made through this session, oldest first
+    pub fn rounds(&self) -> &[SessionRound] {
+        &self.rounds
+    }
+
+    /// Solve a normal captcha (image) as the next round
+    pub async fn normal(
+        &mut self,
+        file: impl Into<CaptchaImage>,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult> {
+        let extras = self.chain(params.unwrap_or_default());
+        let start = Instant::now();
+        let result = self.solver.normal(file, Some(extras)).await;
+        self.remember("base64", start, result)
+    }
+
+    /// Solve a grid captcha as the next round
+    pub async fn grid(
+        &mut self,
+        file: impl Into<CaptchaImage>,
+        hint: Option<Hint>,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult> {
+        let extras = self.chain(params.unwrap_or_default());
+        let start = Instant::now();
+        let result = self.solver.grid(file, hint, Some(extras)).await;
+        self.remember("base64", start, result)
+    }
+
+    /// Solve a coordinates captcha as the next round
+    pub async fn coordinates(
+        &mut self,
+        file: impl Into<CaptchaImage>,
+        hint: Option<Hint>,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult> {
+        let extras = self.chain(params.unwrap_or_default());
+        let start = Instant::now();
+        let result = self.solver.coordinates(file, hint, Some(extras)).await;
+        self.remember("base64", start, result)
+    }
+
+    /// Solve a reCAPTCHA on the session page
+    pub async fn recaptcha(
+        &mut self,
+        sitekey: &str,
+        version: Option<RecaptchaVersion>,
+        enterprise: Option<bool>,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult> {
+        let url = self.url()?;
+        let extras = self.fill(params.unwrap_or_default(), "userAgent");
+        let start = Instant::now();
+        let result = self
+            .solver
+            .recaptcha(sitekey, &url, version, enterprise, Some(extras))
+            .await;
+        self.remember("userrecaptcha", start, result)
+    }
+
+    /// Solve an hCaptcha on the session page
+    pub async fn hcaptcha(
+        &mut self,
+        sitekey: &str,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult> {
+        let url = self.url()?;
+        let extras = self.fill(params.unwrap_or_default(), "userAgent");
+        let start = Instant::now();
+        let result = self.solver.hcaptcha(sitekey, &url, Some(extras)).await;
+        self.remember("hcaptcha", start, result)
+    }
+
+    /// Solve a Cloudflare Turnstile on the session page
+    pub async fn turnstile(
+        &mut self,
+        sitekey: &str,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult> {
+        let url = self.url()?;
+        let extras = self.fill(params.unwrap_or_default(), "useragent");
+        let start = Instant::now();
+        let result = self.solver.turnstile(sitekey, &url, Some(extras)).await;
+        self.remember("turnstile", start, result)
+    }
+
+    /// Solve any task as the next round, filling in the session context
+    pub async fn solve(&mut self, mut params: HashMap<String, String>) -> Result<CaptchaResult> {
+        let method = params.get("method").cloned().unwrap_or_default();
+        let user_agent_key = if method == "turnstile" {
+            "useragent"
+        } else {
+            "userAgent"
+        };
+
+        if let Some(url) = &self.url
+            && !params.contains_key("url")
+            && !params.contains_key("pageurl")
+        {
+            params.insert("url".to_string(), url.clone());
+        }
+        self.fill_params(&mut params, user_agent_key);
+        self.chain_params(&mut params);
+
+        let start = Instant::now();
+        let result = self.solver.solve(None, None, params).await;
+        self.remember(&method, start, result)
+    }
+
+    fn url(&self) -> Result<String> {
+        self.url
+            .clone()
+            .ok_or_else(|| TwoCaptchaError::Validation("the session has no page URL".to_string()))
+    }
+
+    /// Extras of the next round, pointing at the previous captcha
+    fn chain(&self, mut extras: Extras) -> Extras {
+        self.chain_params(extras.params_mut());
+        extras
+    }
+
+    /// Add the session context missing from `extras`
+    fn fill(&self, mut extras: Extras, user_agent_key: &str) -> Extras {
+        self.fill_params(extras.params_mut(), user_agent_key);
+        self.chain(extras)
+    }
+
+    fn chain_params(&self, params: &mut HashMap<String, String>) {
+        if let Some(id) = &self.previous_id
+            && !params.contains_key("previousId")
+            && !params.contains_key("previousID")
+        {
+            params.insert("previousId".to_string(), id.clone());
+        }
+    }
+
+    fn fill_params(&self, params: &mut HashMap<String, String>, user_agent_key: &str) {
+        if let Some(proxy) = &self.proxy
+            && !params.contains_key("proxy")
+        {
+            let proxy = serde_json::json!({ "type": proxy.proxy_type, "uri": proxy.uri });
+            params.insert("proxy".to_string(), proxy.to_string());
+        }
+        if let Some(user_agent) = &self.user_agent
+            && !params.contains_key("userAgent")
+            && !params.contains_key("useragent")
+        {
+            params.insert(user_agent_key.to_string(), user_agent.clone());
+        }
+        if let Some(cookies) = &self.cookies
+            && !params.contains_key("cookies")
+        {
+            params.insert("cookies".to_string(), cookies.clone());
+        }
+    }
+
+    fn remember(
+        &mut self,
+        method: &str,
+        start: Instant,
+        result: Result<CaptchaResult>,
+    ) -> Result<CaptchaResult> {
+        let mut round = SessionRound {
+            method: method.to_string(),
+            captcha_id: None,
+            error: None,
+            elapsed: start.elapsed(),
+        };
+
+        match &result {
+            Ok(result) => {
+                self.previous_id = Some(result.captcha_id.clone());
+                round.captcha_id = Some(result.captcha_id.clone());
+            }
+            Err(e) => round.error = Some(e.to_string()),
+        }
+        self.rounds.push(round);
+
+        result
+    }
+}
+
+impl TwoCaptcha {
+    /// Start a session chaining related captchas to the same worker
+    pub fn session(&self) -> CaptchaSession {
+        CaptchaSession::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::TwoCaptchaConfig;
+
+    fn session() -> CaptchaSession {
+        TwoCaptcha::new("test_key".to_string(), TwoCaptchaConfig::default()).session()
+    }
+
+    #[test]
+    fn test_session_threads_previous_id() {
+        let mut session = session();
+
+        let params: HashMap<_, _> = session.chain(Extras::new()).into_iter().collect();
+        assert!(!params.contains_key("previousId"));
+
+        let result = session.remember(
+            "base64",
+            Instant::now(),
+            Ok(CaptchaResult {
+                captcha_id: "111".to_string(),
+                ..Default::default()
+            }),
+        );
+        assert!(result.is_ok());
+        assert_eq!(session.previous_id(), Some("111"));
+        assert_eq!(session.rounds()[0].captcha_id.as_deref(), Some("111"));
+
+        let params: HashMap<_, _> = session.chain(Extras::new()).into_iter().collect();
+        assert_eq!(params["previousId"], "111");
+
+        let explicit = Extras::new().previous_id("222");
+        let params: HashMap<_, _> = session.chain(explicit).into_iter().collect();
+        assert_eq!(params["previousId"], "222");
+    }
+
+    #[test]
+    fn test_session_fills_context() {
+        let session = session()
+            .with_user_agent("Mozilla/5.0")
+            .with_cookies("a:1")
+            .with_proxy(Proxy {
+                proxy_type: "HTTP".to_string(),
+                uri: "1.2.3.4:8080".to_string(),
+            });
+
+        let params: HashMap<_, _> = session
+            .fill(Extras::new().cookies("b:2"), "useragent")
+            .into_iter()
+            .collect();
+        assert_eq!(params["useragent"], "Mozilla/5.0");
+        assert_eq!(params["cookies"], "b:2");
+        assert!(params["proxy"].contains("1.2.3.4:8080"));
+        assert!(session.url().is_err());
+    }
+}
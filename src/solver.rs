@@ -1,65 +1,636 @@
+use arc_swap::ArcSwap;
 use base64::Engine;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tokio::time::sleep;
 
-use crate::api::ApiClient;
-use crate::error::{Result, TwoCaptchaError};
+use crate::api::{ApiClient, ApiPaths, DnsConfig, Endpoint, HttpTransport};
+use crate::api_v2::{self, ApiVersion, AutoVersion};
+use crate::backoff::{self, RandomJitter};
+use crate::breaker::Breaker;
+use crate::config::{CircuitBreaker, ConfigProfile, CoolDown, Scope, SubmitRetry, TimeoutProfile};
+use crate::cooldown::{CoolDownState, CoolDownStatus};
+use crate::dedupe::Deduper;
+use crate::diagnostics::{RedactedConfig, Telemetry, mask_secret};
+use crate::error::{ApiErrorCode, ErrorKind, Result, TwoCaptchaError};
+use crate::extras::Extras;
+use crate::files::FileAccess;
+use crate::json::{JsonParser, JsonRepair};
+use crate::methods::ParamPolicy;
+use crate::metrics::{BandwidthLedger, SolveTimes};
+use crate::polls::{InFlightPolls, PollLeader, PollRole, PollStatus};
+use crate::pricing::PriceTable;
+use crate::recent::RecentResults;
+use crate::secret::{ApiKey, SubmittedKeys};
+use crate::task::{CaptchaTask, Task};
+use crate::trace::new_trace_id;
 use crate::types::{
-    AudioLanguage, Balance, CaptchaResult, ExtendedResponse, Proxy, RecaptchaVersion,
+    Attempt, AttemptLog, AudioLanguage, Balance, BalanceDetails, CaptchaImage, CaptchaResult,
+    ExtendedResponse, Fingerprint, Hint, Proxy, RecaptchaVersion, ReportOutcome, SubmitReceipt,
 };
 use crate::utils::Utils;
+use crate::webhook::Webhook;
 
 /// Configuration options for [`TwoCaptcha`]
-#[derive(Debug, Clone, Default)]
+///
+/// Serializable so it can be embedded in an application's own
+/// configuration. Durations are written as seconds and read from seconds
+/// or strings like `"90s"` or `"2m"`. Fields holding runtime objects
+/// (clients, transports, resolvers, the webhook and JSON repair) are
+/// skipped and must be set in code.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct TwoCaptchaConfig {
     pub soft_id: Option<u32>,
     pub callback: Option<String>,
+    #[serde(with = "crate::config::duration_serde")]
     pub default_timeout: Option<Duration>,
+    #[serde(with = "crate::config::duration_serde")]
     pub recaptcha_timeout: Option<Duration>,
+    #[serde(with = "crate::config::duration_serde")]
     pub polling_interval: Option<Duration>,
+    /// Timeout and polling interval per captcha `method`, e.g. `hcaptcha`,
+    /// merged over [`TimeoutProfile::defaults`]; a timeout passed to a
+    /// solve call still wins
+    pub timeout_profiles: Option<HashMap<String, TimeoutProfile>>,
+    /// Named overrides, e.g. `dev` and `prod`, see [`TwoCaptcha::with_profile`]
+    pub profiles: Option<HashMap<String, ConfigProfile>>,
+    /// API host (default: `2captcha.com`), or a base URL with a scheme,
+    /// port and path prefix such as `http://127.0.0.1:8080/captcha`
     pub server: Option<String>,
+    /// Regional preset for the server and captcha defaults, overridden by
+    /// `server` when both are set
+    pub endpoint: Option<Endpoint>,
+    /// Host `in.php`/`res.php` requests are resent to when `server`
+    /// cannot be connected to, e.g. `rucaptcha.com` (default: none)
+    pub secondary_server: Option<String>,
+    /// Paths of `in.php` and `res.php` on a 2captcha-compatible server
+    pub api_paths: Option<ApiPaths>,
+    /// Bytes of an unexpected response body quoted in errors, with keys
+    /// and tokens masked (default: 512)
+    pub error_body_echo: Option<usize>,
+    /// Protocol to talk to the API with (default: v1, `in.php`/`res.php`)
+    pub api_version: Option<ApiVersion>,
     pub extended_response: Option<bool>,
+    /// Ask `in.php` for JSON responses (`json=1`), so submission errors come
+    /// back with their `error_text` (default: false)
+    pub json_submit: Option<bool>,
+    /// Poll results with `action=get2`, which also returns the price of
+    /// each solve (default: false)
+    pub fetch_price: Option<bool>,
+    /// Guess whether string image inputs are base64 data (default: true)
+    pub detect_base64: Option<bool>,
+    /// Record a SHA-256 of every submitted image/audio (default: false)
+    pub hash_payloads: Option<bool>,
+    /// Answer resubmissions of an image solved within this window from
+    /// memory instead of submitting it again (implies payload hashing)
+    #[serde(with = "crate::config::duration_serde")]
+    pub dedupe_window: Option<Duration>,
+    /// Maximum number of solves running at the same time (default: unlimited)
+    ///
+    /// Only read when the client is created.
+    pub max_in_flight: Option<usize>,
+    /// Maximum number of files read at the same time (default: 4)
+    ///
+    /// Only read when the client is created.
+    pub max_file_reads: Option<usize>,
+    /// Prices used to estimate the cost of each task
+    pub price_table: Option<PriceTable>,
+    /// Reject tasks whose estimated cost exceeds this amount
+    pub max_task_cost: Option<f64>,
+    /// Handling of parameters the [method catalog](crate::methods::catalog)
+    /// does not list for a task, e.g. a misspelt `stie_key` (default: allow)
+    pub param_policy: Option<ParamPolicy>,
+    /// Attempts per solve when a captcha is unsolvable, times out or
+    /// the API is unreachable (default: 1, no retries)
+    pub max_attempts: Option<u32>,
+    /// Submissions per solve when the worker reports the captcha unsolvable,
+    /// each under a fresh captcha ID (default: `max_attempts`)
+    pub max_solve_attempts: Option<u32>,
+    /// Resubmit tasks refused because no worker is free
+    /// (`ERROR_NO_SLOT_AVAILABLE`) instead of failing (default: none)
+    pub submit_retry: Option<SubmitRetry>,
+    /// Fail fast for a while after repeated auth or network failures,
+    /// instead of sending every task to an API that refuses them
+    /// (default: none)
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// Pause submissions and slow down polls for a while when the API
+    /// throttles or bans the client, instead of failing (default: none)
+    pub cool_down: Option<CoolDown>,
+    /// Report a captcha as bad when its solve is dropped before the answer
+    /// arrives, instead of silently abandoning it (default: false)
+    pub report_abandoned: Option<bool>,
+    /// Fallback for JSON responses that fail to parse, e.g.
+    /// [`LenientJson`](crate::json::LenientJson) (default: none, strict)
+    #[serde(skip)]
+    pub json_repair: Option<Arc<dyn JsonRepair>>,
+    /// Forward every solved result to this endpoint
+    #[serde(skip)]
+    pub webhook: Option<Webhook>,
+    /// Ask the API for an `Access-Control-Allow-Origin: *` header, needed to
+    /// read responses cross-origin from a browser (default: false)
+    pub header_acao: Option<bool>,
+    /// Browser fingerprint sent with token captchas and echoed in results
+    pub fingerprint: Option<Fingerprint>,
+    /// DNS overrides for the API host (ignored with a middleware or
+    /// user-provided client)
+    #[serde(skip)]
+    pub dns: Option<DnsConfig>,
+    /// Only connect to an API server presenting one of these certificates
+    /// or public keys (ignored with a middleware or user-provided client)
+    #[cfg(feature = "pinning")]
+    #[serde(skip)]
+    pub tls_pins: Option<Vec<crate::pinning::Pin>>,
+    /// Pre-built `reqwest-middleware` client to send requests through
+    #[cfg(feature = "middleware")]
+    #[serde(skip)]
+    pub middleware_client: Option<reqwest_middleware::ClientWithMiddleware>,
+    /// Pre-configured `reqwest` client to send requests through, e.g. with
+    /// proxies or custom TLS settings
+    #[serde(skip)]
+    pub http_client: Option<reqwest::Client>,
+    /// Custom HTTP layer to send requests through, taking precedence over
+    /// every other HTTP setting
+    #[serde(skip)]
+    pub transport: Option<Arc<dyn HttpTransport>>,
 }
 
-/// Main TwoCaptcha solver client
+/// Timeout of regular captchas when none is configured
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+/// Timeout of reCAPTCHA when none is configured
+pub(crate) const RECAPTCHA_TIMEOUT: Duration = Duration::from_secs(600);
+/// Delay between result polls when none is configured
+pub(crate) const POLLING_INTERVAL: Duration = Duration::from_secs(10);
+/// Failed result requests in a row after which polling gives up
+const MAX_POLL_FAILURES: u32 = 4;
+
+/// Settings that can be swapped at runtime via [`TwoCaptcha::update_config`]
 #[derive(Debug, Clone)]
-pub struct TwoCaptcha {
-    api_key: String,
+struct Settings {
+    api_key: ApiKey,
     soft_id: Option<u32>,
     callback: Option<String>,
     default_timeout: Duration,
     recaptcha_timeout: Duration,
     polling_interval: Duration,
+    timeout_profiles: HashMap<String, TimeoutProfile>,
+    profiles: HashMap<String, ConfigProfile>,
     api_client: ApiClient,
-    max_files: usize,
     extended_response: bool,
+    json_submit: bool,
+    fetch_price: bool,
+    detect_base64: bool,
+    hash_payloads: bool,
+    dedupe_window: Option<Duration>,
+    price_table: Option<PriceTable>,
+    max_task_cost: Option<f64>,
+    param_policy: ParamPolicy,
+    max_attempts: u32,
+    max_solve_attempts: Option<u32>,
+    submit_retry: Option<SubmitRetry>,
+    circuit_breaker: Option<CircuitBreaker>,
+    cool_down: Option<CoolDown>,
+    report_abandoned: bool,
+    json_repair: Option<Arc<dyn JsonRepair>>,
+    fingerprint: Option<Fingerprint>,
+    /// Proxy of token tasks, set on scoped clients
+    proxy: Option<Proxy>,
+    webhook: Option<Webhook>,
+    header_acao: bool,
+    recaptcha_domain: Option<&'static str>,
+    api_version: ApiVersion,
 }
 
-impl TwoCaptcha {
-    /// Create a new TwoCaptcha client
-    pub fn new(api_key: String, config: TwoCaptchaConfig) -> Self {
+impl Settings {
+    fn new(api_key: ApiKey, mut config: TwoCaptchaConfig) -> Self {
+        let api_client = Self::api_client(&mut config);
+        let mut timeout_profiles = TimeoutProfile::defaults();
+        timeout_profiles.extend(config.timeout_profiles.unwrap_or_default());
+
         Self {
             api_key,
             soft_id: config.soft_id.or(Some(4580)),
             callback: config.callback,
-            default_timeout: config.default_timeout.unwrap_or(Duration::from_secs(120)),
-            recaptcha_timeout: config.recaptcha_timeout.unwrap_or(Duration::from_secs(600)),
-            polling_interval: config.polling_interval.unwrap_or(Duration::from_secs(10)),
-            api_client: ApiClient::new(config.server),
-            max_files: 9,
+            default_timeout: config.default_timeout.unwrap_or(DEFAULT_TIMEOUT),
+            recaptcha_timeout: config.recaptcha_timeout.unwrap_or(RECAPTCHA_TIMEOUT),
+            polling_interval: config.polling_interval.unwrap_or(POLLING_INTERVAL),
+            timeout_profiles,
+            profiles: config.profiles.unwrap_or_default(),
+            api_client,
             extended_response: config.extended_response.unwrap_or(false),
+            json_submit: config.json_submit.unwrap_or(false),
+            fetch_price: config.fetch_price.unwrap_or(false),
+            detect_base64: config.detect_base64.unwrap_or(true),
+            hash_payloads: config.hash_payloads.unwrap_or(false),
+            dedupe_window: config.dedupe_window,
+            price_table: config.price_table,
+            max_task_cost: config.max_task_cost,
+            param_policy: config.param_policy.unwrap_or_default(),
+            max_attempts: config.max_attempts.unwrap_or(1).max(1),
+            max_solve_attempts: config.max_solve_attempts.map(|n| n.max(1)),
+            submit_retry: config.submit_retry,
+            circuit_breaker: config.circuit_breaker,
+            cool_down: config.cool_down,
+            report_abandoned: config.report_abandoned.unwrap_or(false),
+            json_repair: config.json_repair,
+            fingerprint: config.fingerprint,
+            proxy: None,
+            webhook: config.webhook,
+            header_acao: config.header_acao.unwrap_or(false),
+            recaptcha_domain: config
+                .endpoint
+                .as_ref()
+                .and_then(Endpoint::recaptcha_domain),
+            api_version: config.api_version.unwrap_or_default(),
+        }
+    }
+
+    /// Override the settings `profile` sets
+    fn apply_profile(&mut self, profile: &ConfigProfile) {
+        if profile.soft_id.is_some() {
+            self.soft_id = profile.soft_id;
+        }
+        if profile.callback.is_some() {
+            self.callback.clone_from(&profile.callback);
+        }
+        self.default_timeout = profile.default_timeout.unwrap_or(self.default_timeout);
+        self.recaptcha_timeout = profile.recaptcha_timeout.unwrap_or(self.recaptcha_timeout);
+        self.polling_interval = profile.polling_interval.unwrap_or(self.polling_interval);
+        self.max_attempts = profile.max_attempts.map_or(self.max_attempts, |n| n.max(1));
+    }
+
+    /// Timeout and polling interval of a solve of `method`, those given to
+    /// the call taking precedence over the method's profile
+    fn timeouts_for(
+        &self,
+        method: &str,
+        timeout: Option<Duration>,
+        polling_interval: Option<Duration>,
+    ) -> (Option<Duration>, Option<Duration>) {
+        let profile = self
+            .timeout_profiles
+            .get(method)
+            .copied()
+            .unwrap_or_default();
+        (
+            timeout.or(profile.timeout),
+            polling_interval.or(profile.polling_interval),
+        )
+    }
+
+    fn api_client(config: &mut TwoCaptchaConfig) -> ApiClient {
+        let mut client = Self::http_client(config);
+        if let Some(secondary) = config.secondary_server.take() {
+            client = client.with_secondary(secondary);
+        }
+        if let Some(paths) = config.api_paths.take() {
+            client = client.with_paths(paths);
+        }
+        if let Some(limit) = config.error_body_echo {
+            client = client.with_body_echo(limit);
+        }
+        client
+    }
+
+    fn http_client(config: &mut TwoCaptchaConfig) -> ApiClient {
+        let server = config
+            .server
+            .take()
+            .or_else(|| config.endpoint.as_ref().map(Endpoint::server));
+
+        if let Some(transport) = config.transport.take() {
+            return ApiClient::with_transport(transport, server);
+        }
+
+        #[cfg(feature = "middleware")]
+        if let Some(client) = config.middleware_client.take() {
+            return ApiClient::with_middleware(client, server);
+        }
+
+        if let Some(client) = config.http_client.take() {
+            return ApiClient::with_client(client, server);
+        }
+
+        #[cfg(feature = "pinning")]
+        if let Some(pins) = config.tls_pins.take() {
+            return ApiClient::with_tls_pins(server, pins, config.dns.take());
+        }
+
+        match config.dns.take() {
+            Some(dns) => ApiClient::with_dns(server, dns),
+            None => ApiClient::new(server),
+        }
+    }
+}
+
+/// Main TwoCaptcha solver client
+///
+/// Clones share their settings, so [`TwoCaptcha::update_config`] on any
+/// clone applies to all of them.
+#[derive(Debug, Clone)]
+pub struct TwoCaptcha {
+    settings: Arc<ArcSwap<Settings>>,
+    max_files: usize,
+    deduper: Arc<Deduper>,
+    in_flight: Option<Arc<Semaphore>>,
+    polls: Arc<InFlightPolls>,
+    recent: Arc<RecentResults>,
+    telemetry: Arc<Mutex<Telemetry>>,
+    solve_times: Arc<SolveTimes>,
+    json: Arc<JsonParser>,
+    auto_version: Arc<AutoVersion>,
+    breaker: Arc<Breaker>,
+    cool_down: Arc<CoolDownState>,
+    keys: Arc<SubmittedKeys>,
+    fs: Arc<FileAccess>,
+}
+
+/// A task normalized to wire parameters, ready to be sent to `in.php`
+#[derive(Clone)]
+struct PreparedTask {
+    params: HashMap<String, String>,
+    files: HashMap<String, String>,
+    payload_hash: Option<String>,
+}
+
+impl TwoCaptcha {
+    /// Create a new TwoCaptcha client
+    pub fn new(api_key: impl Into<ApiKey>, config: TwoCaptchaConfig) -> Self {
+        let in_flight = config
+            .max_in_flight
+            .map(|permits| Arc::new(Semaphore::new(permits)));
+        let fs = Arc::new(
+            config
+                .max_file_reads
+                .map_or_else(FileAccess::default, FileAccess::new),
+        );
+
+        Self {
+            settings: Arc::new(ArcSwap::from_pointee(Settings::new(api_key.into(), config))),
+            max_files: 9,
+            deduper: Arc::default(),
+            in_flight,
+            polls: Arc::default(),
+            recent: Arc::default(),
+            telemetry: Arc::default(),
+            solve_times: Arc::default(),
+            json: Arc::default(),
+            auto_version: Arc::default(),
+            breaker: Arc::default(),
+            cool_down: Arc::default(),
+            keys: Arc::default(),
+            fs,
+        }
+    }
+
+    /// Replace the configuration at runtime
+    ///
+    /// The API key is kept. Solves already in progress keep running; every
+    /// request made after this call uses the new settings.
+    pub fn update_config(&self, config: TwoCaptchaConfig) {
+        let current = self.settings();
+        let mut settings = Settings::new(current.api_key.clone(), config);
+        settings.api_client.share_ledger(&current.api_client);
+        self.settings.store(Arc::new(settings));
+    }
+
+    /// Change how often results are polled, for this client and its clones
+    ///
+    /// Like the other setters below, this only touches one setting, unlike
+    /// [`TwoCaptcha::update_config`]. Solves started afterwards use it;
+    /// running ones keep the interval they started with.
+    pub fn set_polling_interval(&self, interval: Duration) {
+        self.modify_settings(|settings| settings.polling_interval = interval);
+    }
+
+    /// Change the timeout of regular (non-reCAPTCHA) solves
+    pub fn set_default_timeout(&self, timeout: Duration) {
+        self.modify_settings(|settings| settings.default_timeout = timeout);
+    }
+
+    /// Change or remove the client-wide callback URL
+    pub fn set_callback(&self, callback: Option<String>) {
+        self.modify_settings(|settings| settings.callback = callback.clone());
+    }
+
+    /// Switch to another API key, e.g. after rotating it
+    ///
+    /// New submissions use the new key. Captchas already submitted are
+    /// still polled and reported with the key they were submitted under.
+    pub fn set_api_key(&self, api_key: impl Into<ApiKey>) {
+        let api_key = api_key.into();
+        self.modify_settings(|settings| settings.api_key = api_key.clone());
+    }
+
+    /// Apply `modify` to the shared settings, retrying on concurrent updates
+    fn modify_settings(&self, modify: impl Fn(&mut Settings)) {
+        self.settings.rcu(|current| {
+            let mut settings = Settings::clone(current);
+            modify(&mut settings);
+            settings
+        });
+    }
+
+    /// Snapshot of the current settings
+    fn settings(&self) -> Arc<Settings> {
+        self.settings.load_full()
+    }
+
+    /// Return a copy of this client with a different per-task cost cap
+    ///
+    /// Useful to allow a single expensive solve (or forbid one) without
+    /// changing the cap configured for the whole client. The copy is
+    /// detached from later [`TwoCaptcha::update_config`] calls.
+    pub fn with_max_task_cost(&self, max_task_cost: Option<f64>) -> Self {
+        let settings = Settings {
+            max_task_cost,
+            ..(*self.settings()).clone()
+        };
+
+        Self {
+            settings: Arc::new(ArcSwap::from_pointee(settings)),
+            ..self.clone()
+        }
+    }
+
+    /// Return a copy of this client solving for a different browser fingerprint
+    ///
+    /// Detached from later [`TwoCaptcha::update_config`] calls, like
+    /// [`TwoCaptcha::with_max_task_cost`].
+    pub fn with_fingerprint(&self, fingerprint: Option<Fingerprint>) -> Self {
+        let settings = Settings {
+            fingerprint,
+            ..(*self.settings()).clone()
+        };
+
+        Self {
+            settings: Arc::new(ArcSwap::from_pointee(settings)),
+            ..self.clone()
+        }
+    }
+
+    /// Return a copy of this client with the named profile applied
+    ///
+    /// The profile's settings override the current ones; a profile with
+    /// `max_in_flight` gets its own cap on concurrent solves. Detached from
+    /// later [`TwoCaptcha::update_config`] calls, like
+    /// [`TwoCaptcha::with_max_task_cost`].
+    pub fn with_profile(&self, name: &str) -> Result<Self> {
+        let current = self.settings();
+        let Some(profile) = current.profiles.get(name) else {
+            let mut known: Vec<_> = current.profiles.keys().map(String::as_str).collect();
+            known.sort_unstable();
+            return Err(TwoCaptchaError::Validation(format!(
+                "unknown profile `{name}` (configured: {})",
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            )));
+        };
+
+        let mut settings = (*current).clone();
+        settings.apply_profile(profile);
+
+        Ok(Self {
+            settings: Arc::new(ArcSwap::from_pointee(settings)),
+            in_flight: profile
+                .max_in_flight
+                .map(|permits| Arc::new(Semaphore::new(permits)))
+                .or_else(|| self.in_flight.clone()),
+            ..self.clone()
+        })
+    }
+
+    /// Child client with other defaults, e.g. for another target site
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use twocaptcha::{Proxy, TwoCaptcha, TwoCaptchaConfig};
+    ///
+    /// let solver = TwoCaptcha::new("YOUR_API_KEY", TwoCaptchaConfig::default());
+    /// let shop = solver.scoped(|scope| {
+    ///     scope.polling_interval(Duration::from_secs(3)).proxy(Proxy {
+    ///         proxy_type: "HTTPS".to_string(),
+    ///         uri: "login:password@203.0.113.7:3128".to_string(),
+    ///     })
+    /// });
+    /// ```
+    ///
+    /// The child shares the parent's HTTP transport, cap on concurrent
+    /// solves, bandwidth and diagnostics, so it is cheap to create. Like
+    /// [`TwoCaptcha::with_profile`], it is detached from later
+    /// [`TwoCaptcha::update_config`] calls.
+    pub fn scoped(&self, configure: impl FnOnce(Scope) -> Scope) -> Self {
+        let scope = configure(Scope::default());
+        let mut settings = (*self.settings()).clone();
+        settings.apply_profile(&scope.profile);
+        if scope.proxy.is_some() {
+            settings.proxy = scope.proxy;
+        }
+
+        Self {
+            settings: Arc::new(ArcSwap::from_pointee(settings)),
+            ..self.clone()
+        }
+    }
+
+    /// Filesystem accesses made by this client
+    pub fn file_access(&self) -> Arc<FileAccess> {
+        Arc::clone(&self.fs)
+    }
+
+    /// Traffic exchanged with the API by this client
+    pub fn bandwidth(&self) -> Arc<BandwidthLedger> {
+        self.settings().api_client.bandwidth()
+    }
+
+    /// Errors and timings recorded for diagnostics
+    pub(crate) fn telemetry(&self) -> &Mutex<Telemetry> {
+        &self.telemetry
+    }
+
+    /// Current configuration with secrets masked
+    pub(crate) fn redacted_config(&self) -> RedactedConfig {
+        let settings = self.settings();
+
+        RedactedConfig {
+            api_key: mask_secret(settings.api_key.expose()),
+            server: settings.api_client.server().to_string(),
+            soft_id: settings.soft_id,
+            callback: settings.callback.is_some(),
+            default_timeout_ms: settings.default_timeout.as_millis() as u64,
+            recaptcha_timeout_ms: settings.recaptcha_timeout.as_millis() as u64,
+            polling_interval_ms: settings.polling_interval.as_millis() as u64,
+            extended_response: settings.extended_response,
+            detect_base64: settings.detect_base64,
+            hash_payloads: settings.hash_payloads,
+            dedupe_window_ms: settings
+                .dedupe_window
+                .map(|window| window.as_millis() as u64),
+            max_task_cost: settings.max_task_cost,
+            max_attempts: settings.max_attempts,
+            max_solve_attempts: settings.max_solve_attempts,
         }
     }
 
+    /// Record a failed operation for diagnostics
+    fn record_error(&self, operation: &'static str, error: &TwoCaptchaError) {
+        let settings = self.settings();
+        self.telemetry.lock().unwrap().record_error(
+            operation,
+            &error.to_string(),
+            settings.api_key.expose(),
+        );
+    }
+
+    /// Record a failed solve attempt under its trace id
+    fn record_attempt_error(&self, error: &TwoCaptchaError, trace_id: &str) {
+        let settings = self.settings();
+        self.telemetry.lock().unwrap().record_traced_error(
+            "solve",
+            &error.to_string(),
+            settings.api_key.expose(),
+            Some(trace_id),
+        );
+    }
+
+    /// Expected time to solve a captcha of the given `in.php` method
+    ///
+    /// Averages the latest solves of this client, falling back to the
+    /// averages published by 2captcha until the method has been solved.
+    pub fn estimate_solve_time(&self, method: &str) -> Option<Duration> {
+        self.solve_times.estimate(method)
+    }
+
+    /// Number of API responses that only parsed after a JSON repair
+    pub fn json_repairs(&self) -> u64 {
+        self.json.repairs()
+    }
+
+    /// Parse a JSON response, repairing it if configured to
+    fn parse_json<T: serde::de::DeserializeOwned>(&self, body: &str) -> Result<T> {
+        self.json
+            .parse(body, self.settings().json_repair.as_deref())
+    }
+
+    /// Estimate the cost of a task using the configured price table
+    pub fn estimate_cost(&self, params: &HashMap<String, String>) -> Option<f64> {
+        self.settings().price_table.as_ref()?.estimate(params)
+    }
+
     /// Solve a normal captcha (image)
     pub async fn normal(
         &self,
-        file: &str,
-        params: Option<HashMap<String, String>>,
+        file: impl Into<CaptchaImage>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
-        let method = Utils::get_method(file).await?;
+        let method = self.image_params(file.into()).await?;
         let mut all_params = method;
         if let Some(p) = params {
             all_params.extend(p);
@@ -68,34 +639,30 @@ impl TwoCaptcha {
     }
 
     /// Solve an audio captcha
+    ///
+    /// Files and untyped URLs must name an `.mp3`; the audio is always sent
+    /// inline as base64.
     pub async fn audio(
         &self,
-        file: &str,
+        file: impl Into<CaptchaImage>,
         lang: AudioLanguage,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
-        let body = if !file.contains('.') && file.len() > 50 {
-            // It's a base64 string
-            file.to_string()
-        } else if file.ends_with(".mp3") && file.starts_with("http") {
-            // Download from URL
-            let response = reqwest::get(file).await?;
-            if response.status() != 200 {
-                return Err(TwoCaptchaError::Validation(format!(
-                    "File could not be downloaded from url: {file}"
-                )));
+        let file = file.into();
+        let detect_base64 = self.settings().detect_base64;
+        let is_mp3 = match &file {
+            CaptchaImage::Auto(file) if !(detect_base64 && Utils::looks_like_base64(file)) => {
+                Some(file.ends_with(".mp3"))
             }
-            let content = response.bytes().await?;
-            base64::engine::general_purpose::STANDARD.encode(&content)
-        } else if file.ends_with(".mp3") {
-            // Read from file
-            let content = tokio::fs::read(file).await?;
-            base64::engine::general_purpose::STANDARD.encode(&content)
-        } else {
+            CaptchaImage::Path(path) => Some(path.extension().is_some_and(|ext| ext == "mp3")),
+            _ => None,
+        };
+        if is_mp3 == Some(false) {
             return Err(TwoCaptchaError::Validation(
                 "File extension is not .mp3 or it is not a base64 string.".to_string(),
             ));
-        };
+        }
+        let body = Utils::image_body(file, detect_base64, &self.fs).await?;
 
         let mut all_params = HashMap::new();
         all_params.insert("body".to_string(), body);
@@ -110,11 +677,7 @@ impl TwoCaptcha {
     }
 
     /// Solve a text captcha
-    pub async fn text(
-        &self,
-        text: &str,
-        params: Option<HashMap<String, String>>,
-    ) -> Result<CaptchaResult> {
+    pub async fn text(&self, text: &str, params: Option<Extras>) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("text".to_string(), text.to_string());
         all_params.insert("method".to_string(), "post".to_string());
@@ -133,7 +696,7 @@ impl TwoCaptcha {
         url: &str,
         version: Option<RecaptchaVersion>,
         enterprise: Option<bool>,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("googlekey".to_string(), sitekey.to_string());
@@ -157,7 +720,14 @@ impl TwoCaptcha {
             all_params.extend(p);
         }
 
-        self.solve(Some(self.recaptcha_timeout), None, all_params)
+        let settings = self.settings();
+        if let Some(domain) = settings.recaptcha_domain {
+            all_params
+                .entry("domain".to_string())
+                .or_insert_with(|| domain.to_string());
+        }
+
+        self.solve(Some(settings.recaptcha_timeout), None, all_params)
             .await
     }
 
@@ -166,7 +736,7 @@ impl TwoCaptcha {
         &self,
         sitekey: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("publickey".to_string(), sitekey.to_string());
@@ -186,7 +756,7 @@ impl TwoCaptcha {
         gt: &str,
         challenge: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("gt".to_string(), gt.to_string());
@@ -206,7 +776,7 @@ impl TwoCaptcha {
         &self,
         sitekey: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("sitekey".to_string(), sitekey.to_string());
@@ -228,7 +798,7 @@ impl TwoCaptcha {
         s_s_c_web_server_sign: &str,
         s_s_c_web_server_sign2: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("s_s_c_user_id".to_string(), s_s_c_user_id.to_string());
@@ -256,7 +826,7 @@ impl TwoCaptcha {
         &self,
         sitekey: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("captchakey".to_string(), sitekey.to_string());
@@ -273,10 +843,11 @@ impl TwoCaptcha {
     /// Solve grid captcha (image)
     pub async fn grid(
         &self,
-        file: &str,
-        params: Option<HashMap<String, String>>,
+        file: impl Into<CaptchaImage>,
+        hint: Option<Hint>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
-        let method = Utils::get_method(file).await?;
+        let method = self.image_params(file.into()).await?;
         let mut all_params = method;
         all_params.insert("recaptcha".to_string(), "1".to_string());
 
@@ -284,23 +855,19 @@ impl TwoCaptcha {
             all_params.extend(p);
         }
 
-        self.solve(None, None, all_params).await
+        self.solve_with_hint(None, None, all_params, hint).await
     }
 
     /// Solve canvas captcha (image)
+    ///
+    /// Canvas captchas require a text and/or image hint.
     pub async fn canvas(
         &self,
-        file: &str,
-        params: Option<HashMap<String, String>>,
+        file: impl Into<CaptchaImage>,
+        hint: Hint,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
-        let provided_params = params.clone().unwrap_or_default();
-        if !provided_params.contains_key("hintText") && !provided_params.contains_key("hintImg") {
-            return Err(TwoCaptchaError::Validation(
-                "parameters required: hintText and/or hintImg".to_string(),
-            ));
-        }
-
-        let method = Utils::get_method(file).await?;
+        let method = self.image_params(file.into()).await?;
         let mut all_params = method;
         all_params.insert("recaptcha".to_string(), "1".to_string());
         all_params.insert("canvas".to_string(), "1".to_string());
@@ -309,16 +876,18 @@ impl TwoCaptcha {
             all_params.extend(p);
         }
 
-        self.solve(None, None, all_params).await
+        self.solve_with_hint(None, None, all_params, Some(hint))
+            .await
     }
 
     /// Solve coordinates captcha (image)
     pub async fn coordinates(
         &self,
-        file: &str,
-        params: Option<HashMap<String, String>>,
+        file: impl Into<CaptchaImage>,
+        hint: Option<Hint>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
-        let method = Utils::get_method(file).await?;
+        let method = self.image_params(file.into()).await?;
         let mut all_params = method;
         all_params.insert("coordinatescaptcha".to_string(), "1".to_string());
 
@@ -326,20 +895,18 @@ impl TwoCaptcha {
             all_params.extend(p);
         }
 
-        self.solve(None, None, all_params).await
+        self.solve_with_hint(None, None, all_params, hint).await
     }
 
     /// Solve rotate captcha (image)
+    ///
+    /// See [`RotateWidget`](crate::RotateWidget) to apply the answer.
     pub async fn rotate(
         &self,
-        files: &str,
-        params: Option<HashMap<String, String>>,
+        files: impl Into<CaptchaImage>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
-        let file_method = Utils::get_method(files).await?;
-        let mut all_params = HashMap::new();
-        if let Some(file) = file_method.get("file") {
-            all_params.insert("file".to_string(), file.clone());
-        }
+        let mut all_params = self.image_params(files.into()).await?;
         all_params.insert("method".to_string(), "rotatecaptcha".to_string());
 
         if let Some(p) = params {
@@ -353,9 +920,9 @@ impl TwoCaptcha {
     pub async fn rotate_multiple(
         &self,
         files: Vec<String>,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
-        let extracted_files = Utils::extract_files(files, self.max_files)?;
+        let extracted_files = Utils::extract_files(files, self.max_files, &self.fs).await?;
         let mut all_params = HashMap::new();
         all_params.insert("method".to_string(), "rotatecaptcha".to_string());
 
@@ -374,7 +941,7 @@ impl TwoCaptcha {
         &self,
         captcha_id: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("captcha_id".to_string(), captcha_id.to_string());
@@ -389,19 +956,31 @@ impl TwoCaptcha {
     }
 
     /// Solve Lemin Cropped Captcha
+    ///
+    /// `api_server` is the domain the captcha is served from, required by
+    /// newer deployments. Use [`CaptchaResult::lemin_solution`] to read the
+    /// answer.
     pub async fn lemin(
         &self,
         captcha_id: &str,
         div_id: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        api_server: Option<&str>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
+        Utils::check_url("url", url)?;
+
         let mut all_params = HashMap::new();
         all_params.insert("captcha_id".to_string(), captcha_id.to_string());
         all_params.insert("div_id".to_string(), div_id.to_string());
         all_params.insert("url".to_string(), url.to_string());
         all_params.insert("method".to_string(), "lemin".to_string());
 
+        if let Some(api_server) = api_server {
+            Utils::check_api_server("api_server", api_server)?;
+            all_params.insert("api_server".to_string(), api_server.to_string());
+        }
+
         if let Some(p) = params {
             all_params.extend(p);
         }
@@ -415,8 +994,11 @@ impl TwoCaptcha {
         app_id: &str,
         api_server: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
+        Utils::check_api_server("api_server", api_server)?;
+        Utils::check_url("url", url)?;
+
         let mut all_params = HashMap::new();
         all_params.insert("app_id".to_string(), app_id.to_string());
         all_params.insert("api_server".to_string(), api_server.to_string());
@@ -435,7 +1017,7 @@ impl TwoCaptcha {
         &self,
         sitekey: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("sitekey".to_string(), sitekey.to_string());
@@ -456,7 +1038,7 @@ impl TwoCaptcha {
         iv: &str,
         context: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("sitekey".to_string(), sitekey.to_string());
@@ -477,7 +1059,7 @@ impl TwoCaptcha {
         &self,
         sitekey: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("sitekey".to_string(), sitekey.to_string());
@@ -496,7 +1078,7 @@ impl TwoCaptcha {
         &self,
         sitekey: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("sitekey".to_string(), sitekey.to_string());
@@ -515,7 +1097,7 @@ impl TwoCaptcha {
         &self,
         app_id: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("app_id".to_string(), app_id.to_string());
@@ -535,7 +1117,7 @@ impl TwoCaptcha {
         misery_key: &str,
         apikey: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("misery_key".to_string(), misery_key.to_string());
@@ -557,7 +1139,7 @@ impl TwoCaptcha {
         pageurl: &str,
         user_agent: &str,
         proxy: Proxy,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("method".to_string(), "datadome".to_string());
@@ -582,7 +1164,7 @@ impl TwoCaptcha {
         master_url_id: &str,
         pageurl: &str,
         user_agent: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("method".to_string(), "cybersiara".to_string());
@@ -602,7 +1184,7 @@ impl TwoCaptcha {
         &self,
         sitekey: &str,
         url: &str,
-        params: Option<HashMap<String, String>>,
+        params: Option<Extras>,
     ) -> Result<CaptchaResult> {
         let mut all_params = HashMap::new();
         all_params.insert("sitekey".to_string(), sitekey.to_string());
@@ -623,46 +1205,291 @@ impl TwoCaptcha {
         polling_interval: Option<Duration>,
         params: HashMap<String, String>,
     ) -> Result<CaptchaResult> {
-        let id = self.send(params).await?;
-        let mut result = CaptchaResult {
-            captcha_id: id.clone(),
-            code: None,
-            extended: None,
+        self.solve_with_hint(timeout, polling_interval, params, None)
+            .await
+    }
+
+    /// Solve a custom task, see [`CaptchaTask`]
+    pub async fn solve_task(&self, task: &impl CaptchaTask) -> Result<CaptchaResult> {
+        self.solve(None, None, task.to_task().into_params()).await
+    }
+
+    /// Solve a task carrying worker instructions
+    async fn solve_with_hint(
+        &self,
+        timeout: Option<Duration>,
+        polling_interval: Option<Duration>,
+        params: HashMap<String, String>,
+        hint: Option<Hint>,
+    ) -> Result<CaptchaResult> {
+        let start = Instant::now();
+        let fingerprint = self.settings().fingerprint.clone();
+        let result = self
+            .solve_attempts(timeout, polling_interval, params, hint)
+            .await
+            .map(|result| CaptchaResult {
+                fingerprint,
+                ..result
+            });
+
+        self.telemetry
+            .lock()
+            .unwrap()
+            .record_solve(start.elapsed(), result.is_err());
+        if let Ok(result) = &result {
+            self.forward(result);
+        }
+
+        result
+    }
+
+    /// Send a result to the configured webhook in the background
+    fn forward(&self, result: &CaptchaResult) {
+        let Some(webhook) = self.settings().webhook.clone() else {
+            return;
         };
+        let client = self.clone();
+        let result = result.clone();
+        tokio::spawn(async move {
+            if let Err(e) = webhook.deliver(&result).await {
+                client.record_error("webhook", &e);
+            }
+        });
+    }
 
-        if self.callback.is_none() {
-            let timeout = timeout.unwrap_or(self.default_timeout);
-            let sleep_interval = polling_interval.unwrap_or(self.polling_interval);
-
-            let code = self.wait_result(&id, timeout, sleep_interval).await?;
-
-            if self.extended_response {
-                if let Ok(extended) = serde_json::from_str::<ExtendedResponse>(&code) {
-                    let mut extended_map = HashMap::new();
-                    extended_map.insert(
-                        "status".to_string(),
-                        serde_json::Value::Number(extended.status.into()),
-                    );
-                    if let Some(request) = extended.request {
-                        extended_map.insert("code".to_string(), serde_json::Value::String(request));
-                    }
-                    if let Some(cookies) = extended.cookies {
-                        extended_map.insert("cookies".to_string(), serde_json::to_value(cookies)?);
+    /// Solve, trying again on retryable failures up to `max_attempts` times,
+    /// or `max_solve_attempts` times for unsolvable captchas
+    async fn solve_attempts(
+        &self,
+        timeout: Option<Duration>,
+        polling_interval: Option<Duration>,
+        params: HashMap<String, String>,
+        hint: Option<Hint>,
+    ) -> Result<CaptchaResult> {
+        let settings = self.settings();
+        let max_attempts = settings.max_attempts;
+        let max_solve_attempts = settings.max_solve_attempts.unwrap_or(max_attempts);
+        if max_attempts == 1 && max_solve_attempts == 1 {
+            let trace_id = new_trace_id();
+            return match self
+                .run_solve(timeout, polling_interval, params, hint)
+                .await
+            {
+                Ok(result) => Ok(CaptchaResult {
+                    trace_id: Some(trace_id),
+                    ..result
+                }),
+                Err(e) => {
+                    let e = e.with_context(|context| context.trace_id = Some(trace_id.clone()));
+                    self.record_attempt_error(&e, &trace_id);
+                    Err(e)
+                }
+            };
+        }
+
+        let mut log = AttemptLog::default();
+        let mut unsolvable = 0;
+        loop {
+            let start = Instant::now();
+            let trace_id = new_trace_id();
+            let result = self
+                .run_solve(timeout, polling_interval, params.clone(), hint.clone())
+                .await;
+
+            match result {
+                Ok(mut result) => {
+                    log.attempts.push(Attempt {
+                        captcha_id: Some(result.captcha_id.clone()),
+                        error_code: None,
+                        elapsed: start.elapsed(),
+                        trace_id: trace_id.clone(),
+                    });
+                    result.attempts = Some(log);
+                    result.trace_id = Some(trace_id);
+                    return Ok(result);
+                }
+                Err(e) => {
+                    let e = e.with_context(|context| context.trace_id = Some(trace_id.clone()));
+                    self.record_attempt_error(&e, &trace_id);
+                    log.attempts.push(Attempt {
+                        captcha_id: e.captcha_id().map(str::to_string),
+                        error_code: Some(e.attempt_code()),
+                        elapsed: start.elapsed(),
+                        trace_id,
+                    });
+                    let exhausted = if e.kind() == ErrorKind::Unsolvable {
+                        unsolvable += 1;
+                        unsolvable >= max_solve_attempts
+                    } else {
+                        log.attempts.len() >= max_attempts as usize
+                    };
+                    if !e.is_retryable() || exhausted {
+                        return Err(TwoCaptchaError::Attempts {
+                            log,
+                            last: Box::new(e),
+                        });
                     }
-                    extended_map.extend(extended.additional);
-                    result.extended = Some(extended_map);
-                } else {
-                    result.code = Some(code);
                 }
+            }
+        }
+    }
+
+    async fn run_solve(
+        &self,
+        timeout: Option<Duration>,
+        polling_interval: Option<Duration>,
+        params: HashMap<String, String>,
+        hint: Option<Hint>,
+    ) -> Result<CaptchaResult> {
+        let _permit = self.acquire_slot().await?;
+        let task = self.prepare(params, hint).await?;
+        let dedupe_window = self.settings().dedupe_window;
+        let method = task.params.get("method").cloned().unwrap_or_default();
+        let (timeout, polling_interval) =
+            self.settings()
+                .timeouts_for(&method, timeout, polling_interval);
+        let start = Instant::now();
+
+        if let (Some(window), Some(hash)) = (dedupe_window, &task.payload_hash)
+            && let Some(cached) = self.deduper.get(hash, window)
+        {
+            return Ok(cached);
+        }
+
+        let callback = task.params.contains_key("pingback");
+        let with_method =
+            |e: TwoCaptchaError| e.with_context(|context| context.method = Some(method.clone()));
+        let receipt = self.send_retrying(task).await.map_err(with_method)?;
+
+        if callback {
+            return Ok(CaptchaResult {
+                captcha_id: receipt.captcha_id,
+                payload_hash: receipt.payload_hash,
+                ..Default::default()
+            });
+        }
+
+        let guard = AbandonGuard::new(self, &receipt.captcha_id);
+        let result = self
+            .wait(&receipt.captcha_id, timeout, polling_interval)
+            .await;
+        guard.disarm();
+
+        let mut result = result.map_err(with_method)?;
+        result.payload_hash = receipt.payload_hash;
+        self.solve_times.record(&method, start.elapsed());
+
+        if let (Some(window), Some(hash)) = (dedupe_window, &result.payload_hash) {
+            self.deduper.insert(hash.clone(), result.clone(), window);
+        }
+
+        Ok(result)
+    }
+
+    /// Submit a captcha without waiting for the answer
+    ///
+    /// The returned receipt carries the captcha id and any metadata the API
+    /// sent back on submission, so callers can decide whether to wait for the
+    /// result with [`TwoCaptcha::wait`] or abandon the task.
+    pub async fn submit(&self, params: HashMap<String, String>) -> Result<SubmitReceipt> {
+        let receipt = async {
+            let _permit = self.acquire_slot().await?;
+            let task = self.prepare(params, None).await?;
+            self.send_retrying(task).await
+        }
+        .await;
+
+        if let Err(e) = &receipt {
+            self.record_error("submit", e);
+        }
+        receipt
+    }
+
+    /// Wait for the answer of a previously submitted captcha
+    ///
+    /// Answers of recently completed ids are returned from memory.
+    pub async fn wait(
+        &self,
+        captcha_id: &str,
+        timeout: Option<Duration>,
+        polling_interval: Option<Duration>,
+    ) -> Result<CaptchaResult> {
+        if let Some(result) = self.recent.get(captcha_id) {
+            return Ok(result);
+        }
+
+        let mut result = CaptchaResult {
+            captcha_id: captcha_id.to_string(),
+            ..Default::default()
+        };
+
+        let settings = self.settings();
+        let timeout = timeout.unwrap_or(settings.default_timeout);
+        let sleep_interval = polling_interval.unwrap_or(settings.polling_interval);
+
+        let code = self
+            .wait_result(captcha_id, timeout, sleep_interval)
+            .await?;
+
+        if self.api_version_of(&settings, captcha_id) == ApiVersion::V2 {
+            let (answer, fields) = api_v2::solution(&code)?;
+            result.code = Some(answer);
+            result.price = fields.get("cost").and_then(price_of);
+            result.extended = Some(fields.into_iter().collect());
+        } else if settings.extended_response {
+            if let Ok(extended) = self.parse_json::<ExtendedResponse>(&code) {
+                let mut extended_map = HashMap::new();
+                extended_map.insert(
+                    "status".to_string(),
+                    serde_json::Value::Number(extended.status.into()),
+                );
+                if let Some(request) = extended.request {
+                    extended_map.insert("code".to_string(), serde_json::Value::String(request));
+                }
+                if let Some(cookies) = extended.cookies {
+                    extended_map.insert("cookies".to_string(), serde_json::to_value(cookies)?);
+                }
+                extended_map.extend(extended.additional);
+                result.price = extended_map.get("price").and_then(price_of);
+                result.extended = Some(extended_map);
             } else {
                 result.code = Some(code);
             }
+        } else if settings.fetch_price
+            && let Some((answer, price)) = code.rsplit_once('|')
+        {
+            result.code = Some(answer.to_string());
+            result.price = price.parse().ok();
+        } else {
+            result.code = Some(code);
         }
 
+        self.recent.insert(result.clone());
         Ok(result)
     }
 
+    /// Get the answer of an id solved or being solved elsewhere, e.g. by
+    /// another consumer of this client
+    ///
+    /// Like [`TwoCaptcha::wait`] with the default timeout and polling
+    /// interval: the answer comes from memory when the id completed
+    /// recently, and is polled for otherwise.
+    pub async fn resume(&self, captcha_id: &str) -> Result<CaptchaResult> {
+        self.wait(captcha_id, None, None).await
+    }
+
+    /// Where the answer of a captcha waited on by this client stands
+    ///
+    /// Can be called from another task while a solve is running, e.g. for
+    /// a dashboard. `None` when the id is not being polled and did not
+    /// finish recently; timed out waits are not remembered.
+    pub fn status(&self, captcha_id: &str) -> Option<PollStatus> {
+        self.polls.status(captcha_id)
+    }
+
     /// Wait for captcha result with polling
+    ///
+    /// Concurrent waits for the same id share a single poll loop.
     async fn wait_result(
         &self,
         id: &str,
@@ -671,135 +1498,740 @@ impl TwoCaptcha {
     ) -> Result<String> {
         let start = Instant::now();
 
+        loop {
+            match self.polls.join(id) {
+                PollRole::Leader(leader) => {
+                    let result = self
+                        .poll_result(&leader, id, start, timeout, polling_interval)
+                        .await;
+                    let polls = leader.polls();
+                    leader.publish(&result);
+                    return result.map_err(|e| {
+                        e.with_context(|context| {
+                            context.captcha_id = Some(id.to_string());
+                            context.polls = Some(polls);
+                        })
+                    });
+                }
+                PollRole::Follower(mut outcome) => {
+                    let remaining = timeout.saturating_sub(start.elapsed());
+                    match tokio::time::timeout(remaining, outcome.wait_for(Option::is_some)).await {
+                        Ok(Ok(outcome)) => {
+                            if let Some(outcome) = outcome.clone() {
+                                return outcome.into_result().map_err(|e| {
+                                    e.with_context(|context| {
+                                        context.captcha_id = Some(id.to_string());
+                                    })
+                                });
+                            }
+                        }
+                        // The leader went away without an outcome: take over
+                        Ok(Err(_)) => continue,
+                        Err(_) => {
+                            return Err(TwoCaptchaError::timeout(id, timeout));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Poll `res.php` until the captcha is solved or `timeout` since `start`
+    async fn poll_result(
+        &self,
+        leader: &PollLeader,
+        id: &str,
+        start: Instant,
+        timeout: Duration,
+        polling_interval: Duration,
+    ) -> Result<String> {
+        let cool_down = self.settings().cool_down;
+        let mut failures = 0;
         while start.elapsed() < timeout {
+            leader.record_poll();
             match self.get_result(id).await {
-                Ok(result) => return Ok(result),
-                Err(TwoCaptchaError::Network(_)) => {
-                    sleep(polling_interval).await;
-                    continue;
+                Ok(Some(result)) => return Ok(result),
+                Ok(None) => {
+                    failures = 0;
+                    sleep(
+                        self.cool_down
+                            .poll_interval(cool_down.as_ref(), polling_interval),
+                    )
+                    .await;
+                }
+                // The captcha is still being solved on the other end, so an
+                // outage is waited out, backing off, unless it persists
+                Err(e) if e.kind() == ErrorKind::Network && failures + 1 < MAX_POLL_FAILURES => {
+                    failures += 1;
+                    self.record_error("poll", &e);
+                    sleep(polling_interval.saturating_mul(1 << failures)).await;
+                }
+                Err(e) => {
+                    let Some(pause) = cool_down
+                        .as_ref()
+                        .and_then(|config| self.cool_down.enter(config, &e))
+                    else {
+                        return Err(e);
+                    };
+                    sleep(pause.min(timeout.saturating_sub(start.elapsed()))).await;
                 }
-                Err(e) => return Err(e),
             }
         }
 
-        Err(TwoCaptchaError::Timeout(format!(
-            "timeout {} exceeded",
-            timeout.as_secs()
-        )))
+        Err(TwoCaptchaError::timeout(id, timeout))
     }
 
-    /// Send captcha for solving
-    async fn send(&self, mut params: HashMap<String, String>) -> Result<String> {
-        params = self.default_params(params);
-        params = Utils::rename_params(params);
+    /// Send captcha for solving, resubmitting it while no worker is free
+    /// if `submit_retry` is set
+    async fn send_retrying(&self, task: PreparedTask) -> Result<SubmitReceipt> {
+        let page_url = task.params.get("pageurl").cloned();
+        self.send_retrying_inner(task)
+            .await
+            .map_err(|e| match page_url {
+                Some(url) if e.api_code() == Some(ApiErrorCode::PageUrl) => TwoCaptchaError::Api {
+                    code: Some(ApiErrorCode::PageUrl),
+                    message: format!("ERROR_PAGEURL: the API refused pageurl `{url}`"),
+                },
+                _ => e,
+            })
+    }
+
+    async fn send_retrying_inner(&self, task: PreparedTask) -> Result<SubmitReceipt> {
+        let Some(retry) = self.settings().submit_retry else {
+            return self.send_paced(task).await;
+        };
+
+        let jitter = RandomJitter::default();
+        let mut attempt = 1;
+        loop {
+            match self.send_paced(task.clone()).await {
+                Err(e)
+                    if e.api_code() == Some(ApiErrorCode::NoSlotAvailable)
+                        && attempt < retry.attempts() =>
+                {
+                    sleep(backoff::jittered(retry.delay(attempt), &jitter)).await;
+                    attempt += 1;
+                }
+                receipt => return receipt,
+            }
+        }
+    }
+
+    /// Send captcha for solving once no cool-down is running, waiting out
+    /// those it triggers if `cool_down` is set
+    async fn send_paced(&self, task: PreparedTask) -> Result<SubmitReceipt> {
+        let Some(config) = self.settings().cool_down else {
+            return self.guarded(self.send(task)).await;
+        };
+
+        let mut waits = 0;
+        loop {
+            if let Some(status) = self.cool_down.status() {
+                sleep(status.remaining).await;
+            }
+            match self.guarded(self.send(task.clone())).await {
+                Err(e)
+                    if waits < config.max_waits()
+                        && self.cool_down.enter(&config, &e).is_some() =>
+                {
+                    waits += 1;
+                }
+                receipt => return receipt,
+            }
+        }
+    }
+
+    /// Reason and time left of the running cool-down, see
+    /// [`TwoCaptchaConfig::cool_down`]
+    pub fn cool_down(&self) -> Option<CoolDownStatus> {
+        self.cool_down.status()
+    }
+
+    /// Make an API request through the circuit breaker, if one is set
+    async fn guarded<T>(&self, request: impl Future<Output = Result<T>>) -> Result<T> {
+        let Some(config) = self.settings().circuit_breaker else {
+            return request.await;
+        };
+        self.breaker.check(&config)?;
+        let result = request.await;
+        self.breaker.record(&config, &result);
+        result
+    }
+
+    /// Time left before requests are let through again, while the
+    /// [circuit breaker](TwoCaptchaConfig::circuit_breaker) is open
+    pub fn circuit_open_for(&self) -> Option<Duration> {
+        self.breaker.open_for()
+    }
+
+    /// Send captcha for solving, remembering the key it was sent with
+    async fn send(&self, task: PreparedTask) -> Result<SubmitReceipt> {
+        let settings = self.settings();
+        let receipt = self.send_with(&settings, task).await?;
+        self.keys
+            .insert(&receipt.captcha_id, settings.api_key.clone());
+        Ok(receipt)
+    }
+
+    async fn send_with(&self, settings: &Settings, task: PreparedTask) -> Result<SubmitReceipt> {
+        let PreparedTask {
+            mut params,
+            mut files,
+            payload_hash,
+        } = task;
+        let api_client = &settings.api_client;
+        match settings.api_version {
+            ApiVersion::V2 => return self.send_v2(settings, params, files, payload_hash).await,
+            ApiVersion::Auto if !self.auto_version.v2_unavailable() => {
+                match self
+                    .send_v2(
+                        settings,
+                        params.clone(),
+                        files.clone(),
+                        payload_hash.clone(),
+                    )
+                    .await
+                {
+                    Ok(receipt) => {
+                        self.auto_version.insert_v2(&receipt.captcha_id);
+                        return Ok(receipt);
+                    }
+                    Err(e) if self.auto_version.should_fall_back(&e) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            _ => {}
+        }
 
-        let (params, files) = Utils::check_hint_img(params, HashMap::new()).await?;
+        if let Some(path) = params.remove("file") {
+            files.insert("file".to_string(), path);
+        }
 
-        let response = if files.is_empty() {
-            self.api_client.in_(None, params).await?
+        params.insert("key".to_string(), settings.api_key.expose().to_string());
+        if settings.json_submit {
+            params.insert("json".to_string(), "1".to_string());
+        }
+        let (response, server) = if files.is_empty() {
+            api_client.submit(None, params).await?
         } else {
-            // Convert files to bytes
-            let mut file_bytes = HashMap::new();
-            for (key, path) in files {
-                let content = tokio::fs::read(&path).await?;
-                file_bytes.insert(key, content);
+            let file_bytes = self.fs.read_all(files).await?;
+            #[cfg(feature = "image")]
+            crate::precheck::check_uploads(&file_bytes)?;
+            api_client.submit(Some(file_bytes), params).await?
+        };
+
+        let mut receipt = self.parse_submit_response(&response)?;
+        receipt.payload_hash = payload_hash;
+        receipt.server = Some(server);
+
+        Ok(receipt)
+    }
+
+    /// Key the task `id` was submitted under, the current one for ids this
+    /// client did not submit (or no longer remembers)
+    fn key_of(&self, settings: &Settings, id: &str) -> ApiKey {
+        self.keys
+            .get(id)
+            .unwrap_or_else(|| settings.api_key.clone())
+    }
+
+    /// Protocol the task `id` was created with
+    fn api_version_of(&self, settings: &Settings, id: &str) -> ApiVersion {
+        match settings.api_version {
+            ApiVersion::Auto => self.auto_version.version_of(id),
+            version => version,
+        }
+    }
+
+    /// Create the task through the JSON API, which takes images inline
+    async fn send_v2(
+        &self,
+        settings: &Settings,
+        mut params: HashMap<String, String>,
+        files: HashMap<String, String>,
+        payload_hash: Option<String>,
+    ) -> Result<SubmitReceipt> {
+        let mut files = self.fs.read_all(files).await?;
+        if let Some(path) = params.remove("file") {
+            files.insert("file".to_string(), self.fs.read_shared(&path).await?);
+        }
+        #[cfg(feature = "image")]
+        crate::precheck::check_uploads(&files)?;
+        for (key, content) in files {
+            let encoded = base64::engine::general_purpose::STANDARD.encode(content);
+            match key.as_str() {
+                "file" => {
+                    params.insert("body".to_string(), encoded);
+                    params.insert("method".to_string(), "base64".to_string());
+                }
+                "imginstructions" => {
+                    params.insert(key, encoded);
+                }
+                _ => {
+                    return Err(TwoCaptchaError::Validation(format!(
+                        "the JSON API does not accept file batches (`{key}`)"
+                    )));
+                }
+            }
+        }
+
+        let callback = params.remove("pingback");
+        let task = Task::from_params(params).to_official_json()?;
+        let captcha_id = api_v2::create_task(
+            &settings.api_client,
+            settings.api_key.expose(),
+            task,
+            settings.soft_id,
+            callback.as_deref(),
+        )
+        .await?;
+
+        Ok(SubmitReceipt {
+            captcha_id,
+            payload_hash,
+            server: Some(settings.api_client.server().to_string()),
+            metadata: HashMap::new(),
+        })
+    }
+
+    /// Validate and normalize a task into wire parameters
+    async fn prepare(
+        &self,
+        mut params: HashMap<String, String>,
+        hint: Option<Hint>,
+    ) -> Result<PreparedTask> {
+        self.check_cost(&params)?;
+        for key in ["callback", "pingback"] {
+            if let Some(url) = params.get(key) {
+                Utils::check_url(key, url)?;
+            }
+        }
+
+        params = self.default_params(params);
+        let (renamed, conflicts) = Utils::rename_params_checked(params);
+        params = renamed;
+
+        let settings = self.settings();
+        if !conflicts.is_empty() {
+            let mut telemetry = self.telemetry.lock().unwrap();
+            for conflict in conflicts {
+                telemetry.record_error("params", &conflict.to_string(), settings.api_key.expose());
             }
-            self.api_client.in_(Some(file_bytes), params).await?
+        }
+        self.check_params(&params, &settings)?;
+        if let Some(url) = params.get_mut("pageurl") {
+            *url = Utils::normalize_page_url("pageurl", url)?;
+        }
+        if let Some(fingerprint) = &settings.fingerprint {
+            fingerprint.apply(&mut params);
+        }
+        #[cfg(feature = "lang-detect")]
+        if !params.contains_key("lang")
+            && let Some(lang) = ["textinstructions", "textcaptcha"]
+                .iter()
+                .find_map(|key| crate::lang::detect_lang(params.get(*key)?))
+        {
+            params.insert("lang".to_string(), lang.to_string());
+        }
+        let (mut params, mut files) =
+            Utils::check_hint_img(params, HashMap::new(), settings.detect_base64, &self.fs).await?;
+
+        // Typed hints are resolved to their final form, so they skip the
+        // file/base64 guessing applied to raw parameters above
+        if let Some(hint) = hint {
+            params.extend(Utils::hint_params(hint, settings.detect_base64, &self.fs).await?);
+        }
+
+        #[cfg(feature = "image")]
+        crate::precheck::check_params(&params)?;
+
+        let payload_hash = if settings.hash_payloads || settings.dedupe_window.is_some() {
+            Utils::payload_hash(&params, &self.fs).await?
+        } else {
+            None
         };
 
+        // Batches of files (`file_1`, `file_2`, ...) are uploaded, not sent as text
+        let batch: Vec<String> = params
+            .keys()
+            .filter(|key| key.starts_with("file_"))
+            .cloned()
+            .collect();
+        for key in batch {
+            let path = params.remove(&key).unwrap_or_default();
+            files.insert(key, path);
+        }
+
+        Ok(PreparedTask {
+            params,
+            files,
+            payload_hash,
+        })
+    }
+
+    /// API error quoting an unexpected response, cut and redacted
+    fn unrecognized(&self, message: &str, response: &str) -> TwoCaptchaError {
+        let echo = self.settings().api_client.echo(response);
+        TwoCaptchaError::api(format!("{message}{echo}"))
+    }
+
+    /// Turn a `{status: 0, request, error_text}` response into an API error
+    fn json_error(&self, data: &HashMap<String, Value>, response: &str) -> TwoCaptchaError {
+        let Some(code) = data.get("request").and_then(|v| v.as_str()) else {
+            return self.unrecognized("cannot recognize response ", response);
+        };
+        match data.get("error_text").and_then(|v| v.as_str()) {
+            Some(text) if !text.is_empty() => TwoCaptchaError::api(format!("{code}: {text}")),
+            _ => TwoCaptchaError::api(code),
+        }
+    }
+
+    /// Parse an `in.php` response in either the `OK|id` or the `json=1` format
+    fn parse_submit_response(&self, response: &str) -> Result<SubmitReceipt> {
+        if response
+            .trim_start_matches('\u{feff}')
+            .trim_start()
+            .starts_with('{')
+        {
+            let mut data: HashMap<String, Value> = self.parse_json(response)?;
+            if data.get("status").and_then(|v| v.as_i64()) != Some(1) {
+                return Err(self.json_error(&data, response));
+            }
+            data.remove("status");
+            let captcha_id = match data.remove("request") {
+                Some(Value::String(id)) => id,
+                Some(Value::Number(id)) => id.to_string(),
+                _ => {
+                    return Err(self.unrecognized("cannot recognize response ", response));
+                }
+            };
+            return Ok(SubmitReceipt {
+                captcha_id,
+                payload_hash: None,
+                server: None,
+                metadata: data,
+            });
+        }
+
         if !response.starts_with("OK|") {
-            return Err(TwoCaptchaError::Api(format!(
-                "cannot recognize response {response}"
-            )));
+            return Err(self.unrecognized("cannot recognize response ", response));
         }
 
-        Ok(response[3..].to_string())
+        Ok(SubmitReceipt {
+            captcha_id: response[3..].to_string(),
+            payload_hash: None,
+            server: None,
+            metadata: HashMap::new(),
+        })
     }
 
-    /// Get captcha result
-    async fn get_result(&self, id: &str) -> Result<String> {
-        let mut params = HashMap::new();
-        params.insert("key".to_string(), self.api_key.clone());
-        params.insert("action".to_string(), "get".to_string());
+    /// Get captcha result: the answer of captcha `id`, or `None` while it
+    /// is still being solved
+    async fn get_result(&self, id: &str) -> Result<Option<String>> {
+        let settings = self.settings();
+        let key = self.key_of(&settings, id);
+        if self.api_version_of(&settings, id) == ApiVersion::V2 {
+            return self
+                .guarded(api_v2::get_task_result(
+                    &settings.api_client,
+                    key.expose(),
+                    id,
+                ))
+                .await;
+        }
+
+        let action = if settings.fetch_price { "get2" } else { "get" };
+        let mut params = Self::res_params(&settings, &key, action);
         params.insert("id".to_string(), id.to_string());
 
-        if self.extended_response {
+        if settings.extended_response {
             params.insert("json".to_string(), "1".to_string());
         }
 
-        let response = self.api_client.res(params).await?;
-
-        if self.extended_response {
-            let response_data: Value = serde_json::from_str(&response)?;
-            if response_data.get("status").and_then(|v| v.as_i64()) == Some(0) {
-                return Err(TwoCaptchaError::Network("CAPTCHA_NOT_READY".to_string()));
-            }
-            if response_data.get("status").and_then(|v| v.as_i64()) != Some(1) {
-                return Err(TwoCaptchaError::Api(format!(
-                    "Unexpected status in response: {response}"
-                )));
+        let response = self.guarded(settings.api_client.res(params)).await?;
+
+        if settings.extended_response {
+            let response_data: HashMap<String, Value> = self.parse_json(&response)?;
+            match response_data.get("status").and_then(|v| v.as_i64()) {
+                Some(1) => {}
+                Some(0)
+                    if response_data.get("request").and_then(|v| v.as_str())
+                        == Some("CAPCHA_NOT_READY") =>
+                {
+                    return Ok(None);
+                }
+                Some(0) => return Err(self.json_error(&response_data, &response)),
+                _ => {
+                    return Err(self.unrecognized("Unexpected status in response: ", &response));
+                }
             }
-            Ok(response)
+            Ok(Some(response))
         } else {
             if response == "CAPCHA_NOT_READY" {
-                return Err(TwoCaptchaError::Network("CAPTCHA_NOT_READY".to_string()));
+                return Ok(None);
             }
             if !response.starts_with("OK|") {
-                return Err(TwoCaptchaError::Api(format!(
-                    "cannot recognize response {response}"
-                )));
+                return Err(self.unrecognized("cannot recognize response ", &response));
             }
-            Ok(response[3..].to_string())
+            Ok(Some(response[3..].to_string()))
         }
     }
 
     /// Get account balance
     pub async fn balance(&self) -> Result<Balance> {
-        let mut params = HashMap::new();
-        params.insert("key".to_string(), self.api_key.clone());
-        params.insert("action".to_string(), "getbalance".to_string());
+        Ok(Balance(self.balance_details().await?.balance))
+    }
+
+    /// Get account balance with the other fields the API reports
+    ///
+    /// Uses `getBalance` of the JSON API when the client is configured for
+    /// it, `res.php` otherwise.
+    pub async fn balance_details(&self) -> Result<BalanceDetails> {
+        let settings = self.settings();
+        let key = settings.api_key.expose();
+        match settings.api_version {
+            ApiVersion::V2 => return api_v2::get_balance(&settings.api_client, key).await,
+            ApiVersion::Auto if !self.auto_version.v2_unavailable() => {
+                match api_v2::get_balance(&settings.api_client, key).await {
+                    Err(e) if self.auto_version.should_fall_back(&e) => {}
+                    details => return details,
+                }
+            }
+            _ => {}
+        }
 
-        let response = self.api_client.res(params).await?;
+        let params = Self::res_params(&settings, &settings.api_key, "getbalance");
+        let response = settings.api_client.res(params).await?;
         let balance: f64 = response
             .parse()
-            .map_err(|_| TwoCaptchaError::Api(format!("Invalid balance response: {response}")))?;
+            .map_err(|_| self.unrecognized("Invalid balance response: ", &response))?;
 
-        Ok(Balance(balance))
+        Ok(BalanceDetails {
+            balance,
+            ..Default::default()
+        })
     }
 
     /// Report captcha result (good/bad)
-    pub async fn report(&self, id: &str, correct: bool) -> Result<()> {
-        let mut params = HashMap::new();
-        params.insert("key".to_string(), self.api_key.clone());
-        params.insert(
-            "action".to_string(),
-            if correct { "reportgood" } else { "reportbad" }.to_string(),
-        );
+    ///
+    /// Uses `reportCorrect`/`reportIncorrect` of the JSON API for tasks
+    /// created through it.
+    pub async fn report(&self, id: &str, correct: bool) -> Result<ReportOutcome> {
+        let settings = self.settings();
+        let key = self.key_of(&settings, id);
+        if self.api_version_of(&settings, id) == ApiVersion::V2 {
+            return api_v2::report(&settings.api_client, key.expose(), id, correct).await;
+        }
+
+        let action = if correct { "reportgood" } else { "reportbad" };
+        let mut params = Self::res_params(&settings, &key, action);
         params.insert("id".to_string(), id.to_string());
 
-        self.api_client.res(params).await?;
+        let response = settings.api_client.res(params).await?;
+        Ok(if response == "OK_REPORT_RECORDED" {
+            ReportOutcome::Recorded
+        } else {
+            ReportOutcome::Unrecognized(response)
+        })
+    }
+
+    /// Register a URL the API may send answers to
+    ///
+    /// Besides the client-wide `callback`, tasks can name their own with
+    /// [`Extras::callback`], e.g. one per subsystem of an application. The
+    /// API only sends answers to registered URLs.
+    pub async fn add_pingback(&self, url: &str) -> Result<()> {
+        Utils::check_url("pingback", url)?;
+        self.pingback_action("add_pingback", url).await?;
+        Ok(())
+    }
+
+    /// URLs registered with [`TwoCaptcha::add_pingback`]
+    pub async fn pingbacks(&self) -> Result<Vec<String>> {
+        let response = self.pingback_action("get_pingback", "").await?;
+        let list = response.strip_prefix("OK|").unwrap_or(&response);
+        Ok(list
+            .split([',', '|'])
+            .map(str::trim)
+            .filter(|url| !url.is_empty() && *url != "OK")
+            .map(String::from)
+            .collect())
+    }
+
+    /// Unregister a URL, or every URL with `"all"`
+    pub async fn remove_pingback(&self, url: &str) -> Result<()> {
+        self.pingback_action("del_pingback", url).await?;
+        Ok(())
+    }
+
+    async fn pingback_action(&self, action: &str, addr: &str) -> Result<String> {
+        let settings = self.settings();
+        let mut params = Self::res_params(&settings, &settings.api_key, action);
+        if !addr.is_empty() {
+            params.insert("addr".to_string(), addr.to_string());
+        }
+        settings.api_client.res(params).await
+    }
+
+    /// Parameters common to every `res.php` request
+    fn res_params(settings: &Settings, key: &ApiKey, action: &str) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        params.insert("key".to_string(), key.expose().to_string());
+        params.insert("action".to_string(), action.to_string());
+        if settings.header_acao {
+            params.insert("header_acao".to_string(), "1".to_string());
+        }
+        params
+    }
+
+    /// Wait for a free slot when the number of in-flight solves is capped
+    async fn acquire_slot(&self) -> Result<Option<OwnedSemaphorePermit>> {
+        match &self.in_flight {
+            Some(semaphore) => Arc::clone(semaphore)
+                .acquire_owned()
+                .await
+                .map(Some)
+                .map_err(|_| TwoCaptchaError::Shutdown),
+            None => Ok(None),
+        }
+    }
+
+    /// Build request parameters for an image input
+    async fn image_params(&self, image: CaptchaImage) -> Result<HashMap<String, String>> {
+        Utils::image_params(image, self.settings().detect_base64, &self.fs).await
+    }
+
+    /// Reject the task if its estimated cost exceeds the configured cap
+    fn check_cost(&self, params: &HashMap<String, String>) -> Result<()> {
+        if let Some(max_cost) = self.settings().max_task_cost
+            && let Some(cost) = self.estimate_cost(params)
+            && cost > max_cost
+        {
+            return Err(TwoCaptchaError::Validation(format!(
+                "estimated task cost {cost} exceeds the maximum of {max_cost}"
+            )));
+        }
+
         Ok(())
     }
 
     /// Add default parameters
+    ///
+    /// The API key is not among them: it is added by `send` right before
+    /// the request so prepared tasks never hold a copy.
     fn default_params(&self, mut params: HashMap<String, String>) -> HashMap<String, String> {
-        params.insert("key".to_string(), self.api_key.clone());
+        let settings = self.settings();
 
-        if let Some(callback) = &self.callback {
+        if settings.header_acao {
+            params.insert("header_acao".to_string(), "1".to_string());
+        }
+
+        // A callback given with the task takes precedence
+        if let Some(callback) = &settings.callback
+            && !params.contains_key("callback")
+            && !params.contains_key("pingback")
+        {
             params.insert("callback".to_string(), callback.clone());
         }
 
-        if let Some(soft_id) = self.soft_id {
+        if let Some(soft_id) = settings.soft_id {
             params.insert("softId".to_string(), soft_id.to_string());
         }
 
+        if let Some(proxy) = &settings.proxy
+            && !params.contains_key("proxy")
+            && (params.contains_key("pageurl") || params.contains_key("url"))
+        {
+            let proxy = serde_json::json!({ "type": proxy.proxy_type, "uri": proxy.uri });
+            params.insert("proxy".to_string(), proxy.to_string());
+        }
+
         params
     }
+
+    /// Apply `param_policy` to the parameters the method catalog does not list
+    fn check_params(&self, params: &HashMap<String, String>, settings: &Settings) -> Result<()> {
+        if settings.param_policy == ParamPolicy::Allow {
+            return Ok(());
+        }
+        let method = params.get("method").map(String::as_str).unwrap_or_default();
+        let messages = crate::methods::unknown_params(params)
+            .into_iter()
+            .map(|(key, closest)| match closest {
+                Some(closest) => format!(
+                    "unknown parameter `{key}` for method `{method}`, did you mean `{closest}`?"
+                ),
+                None => format!("unknown parameter `{key}` for method `{method}`"),
+            });
+
+        if settings.param_policy == ParamPolicy::Deny {
+            let messages: Vec<String> = messages.collect();
+            if !messages.is_empty() {
+                return Err(TwoCaptchaError::Validation(messages.join("; ")));
+            }
+            return Ok(());
+        }
+        let mut telemetry = self.telemetry.lock().unwrap();
+        for message in messages {
+            telemetry.record_error("params", &message, settings.api_key.expose());
+        }
+        Ok(())
+    }
+}
+
+/// Reports a submitted captcha as bad if dropped while still armed
+///
+/// Held across [`TwoCaptcha::wait`] so a caller dropping the solve future
+/// does not leave a paid captcha behind without telling the API.
+struct AbandonGuard {
+    client: Option<TwoCaptcha>,
+    captcha_id: String,
+}
+
+impl AbandonGuard {
+    fn new(client: &TwoCaptcha, captcha_id: &str) -> Self {
+        Self {
+            client: client.settings().report_abandoned.then(|| client.clone()),
+            captcha_id: captcha_id.to_string(),
+        }
+    }
+
+    /// The wait finished, nothing to report
+    fn disarm(mut self) {
+        self.client = None;
+    }
+}
+
+impl Drop for AbandonGuard {
+    fn drop(&mut self) {
+        let Some(client) = self.client.take() else {
+            return;
+        };
+        // Best effort: without a runtime there is nothing to report with
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let captcha_id = std::mem::take(&mut self.captcha_id);
+            runtime.spawn(async move {
+                if let Err(e) = client.report(&captcha_id, false).await {
+                    client.record_error("report", &e);
+                }
+            });
+        }
+    }
+}
+
+/// Read a price reported as either a JSON number or a string
+fn price_of(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(price) => price.as_f64(),
+        Value::String(price) => price.parse().ok(),
+        _ => None,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
     #[test]
     fn test_twocaptcha_creation() {
@@ -809,8 +2241,423 @@ mod tests {
         };
         let client = TwoCaptcha::new("test_key".to_string(), config);
 
-        assert_eq!(client.api_key, "test_key");
-        assert_eq!(client.soft_id, Some(1234));
+        assert_eq!(client.settings().api_key.expose(), "test_key");
+        assert_eq!(client.settings().soft_id, Some(1234));
         assert_eq!(client.max_files, 9);
     }
+
+    #[test]
+    fn test_header_acao() {
+        let config = TwoCaptchaConfig {
+            header_acao: Some(true),
+            ..Default::default()
+        };
+        let client = TwoCaptcha::new("test_key".to_string(), config);
+
+        let params =
+            TwoCaptcha::res_params(&client.settings(), &client.settings().api_key, "getbalance");
+        assert_eq!(params["header_acao"], "1");
+        assert_eq!(client.default_params(HashMap::new())["header_acao"], "1");
+    }
+
+    #[test]
+    fn test_timeout_profiles() {
+        let mut profiles = HashMap::new();
+        profiles.insert(
+            "turnstile".to_string(),
+            TimeoutProfile {
+                timeout: Some(Duration::from_secs(60)),
+                polling_interval: None,
+            },
+        );
+        let config = TwoCaptchaConfig {
+            timeout_profiles: Some(profiles),
+            ..Default::default()
+        };
+        let settings = TwoCaptcha::new("test_key", config).settings();
+
+        let (timeout, interval) = settings.timeouts_for("hcaptcha", None, None);
+        assert_eq!(timeout, Some(Duration::from_secs(300)));
+        assert_eq!(interval, Some(Duration::from_secs(10)));
+        assert_eq!(
+            settings.timeouts_for("turnstile", None, None),
+            (Some(Duration::from_secs(60)), None)
+        );
+        let explicit = Some(Duration::from_secs(30));
+        assert_eq!(
+            settings.timeouts_for("hcaptcha", explicit, None).0,
+            explicit
+        );
+        assert_eq!(settings.timeouts_for("base64", None, None), (None, None));
+    }
+
+    #[test]
+    fn test_with_profile() {
+        let profile = ConfigProfile {
+            soft_id: Some(7),
+            polling_interval: Some(Duration::from_secs(2)),
+            max_in_flight: Some(3),
+            ..Default::default()
+        };
+        let config = TwoCaptchaConfig {
+            profiles: Some(HashMap::from([("prod".to_string(), profile)])),
+            ..Default::default()
+        };
+        let client = TwoCaptcha::new("test_key", config);
+
+        let prod = client.with_profile("prod").unwrap();
+        let settings = prod.settings();
+        assert_eq!(settings.soft_id, Some(7));
+        assert_eq!(settings.polling_interval, Duration::from_secs(2));
+        assert_eq!(settings.default_timeout, DEFAULT_TIMEOUT);
+        assert_eq!(prod.in_flight.as_ref().unwrap().available_permits(), 3);
+        assert!(client.in_flight.is_none());
+
+        let error = client.with_profile("staging").unwrap_err();
+        assert!(error.to_string().contains("configured: prod"));
+    }
+
+    #[test]
+    fn test_per_task_callback() {
+        let config = TwoCaptchaConfig {
+            callback: Some("https://example.com/default".to_string()),
+            ..Default::default()
+        };
+        let client = TwoCaptcha::new("test_key".to_string(), config);
+
+        assert_eq!(
+            client.default_params(HashMap::new())["callback"],
+            "https://example.com/default"
+        );
+        let mut params = HashMap::new();
+        params.insert(
+            "pingback".to_string(),
+            "https://example.com/other".to_string(),
+        );
+        let params = client.default_params(params);
+        assert_eq!(params["pingback"], "https://example.com/other");
+        assert!(!params.contains_key("callback"));
+    }
+
+    #[tokio::test]
+    async fn test_param_policy() {
+        let client = TwoCaptcha::new(
+            "test_key".to_string(),
+            TwoCaptchaConfig {
+                param_policy: Some(ParamPolicy::Deny),
+                ..Default::default()
+            },
+        );
+        let mut params: HashMap<String, String> = [
+            ("method", "turnstile"),
+            ("url", "https://example.com"),
+            ("sitekey", "0x4AAA"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert!(client.prepare(params.clone(), None).await.is_ok());
+
+        params.insert("stie_key".to_string(), "0x4AAA".to_string());
+        let error = client.prepare(params, None).await.err().unwrap();
+        assert!(error.to_string().contains("did you mean `sitekey`?"));
+    }
+
+    #[tokio::test]
+    async fn test_audio_requires_mp3() {
+        let client = TwoCaptcha::new("test_key".to_string(), TwoCaptchaConfig::default());
+        let error = client
+            .audio(
+                CaptchaImage::path("captcha.wav"),
+                AudioLanguage::English,
+                None,
+            )
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), ErrorKind::Validation);
+
+        let error = client
+            .audio("captcha.wav", AudioLanguage::English, None)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), ErrorKind::Validation);
+    }
+
+    #[test]
+    fn test_scoped_client() {
+        let client = TwoCaptcha::new("test_key", TwoCaptchaConfig::default());
+        let scoped = client.scoped(|scope| {
+            scope.polling_interval(Duration::from_secs(3)).proxy(Proxy {
+                proxy_type: "HTTP".to_string(),
+                uri: "1.2.3.4:8080".to_string(),
+            })
+        });
+        assert_eq!(scoped.settings().polling_interval, Duration::from_secs(3));
+        assert_eq!(client.settings().polling_interval, POLLING_INTERVAL);
+        assert!(Arc::ptr_eq(&client.telemetry, &scoped.telemetry));
+
+        let token: HashMap<String, String> =
+            [("url".to_string(), "https://example.com".to_string())].into();
+        assert!(scoped.default_params(token.clone()).contains_key("proxy"));
+        assert!(!client.default_params(token).contains_key("proxy"));
+        assert!(!scoped.default_params(HashMap::new()).contains_key("proxy"));
+    }
+
+    #[tokio::test]
+    async fn test_submit_retry_on_no_slot() {
+        use crate::scripted::Scripted;
+
+        // Refuses the first two submissions for lack of workers
+        let busy = || {
+            Scripted::new(|request| {
+                Ok(match request.count {
+                    0 | 1 => "ERROR_NO_SLOT_AVAILABLE",
+                    _ => "OK|123",
+                }
+                .to_string())
+            })
+        };
+
+        let client = |max_attempts| {
+            TwoCaptcha::new(
+                "test_key",
+                TwoCaptchaConfig {
+                    transport: Some(Arc::new(busy())),
+                    submit_retry: Some(SubmitRetry {
+                        max_attempts: Some(max_attempts),
+                        delay: Some(Duration::from_millis(1)),
+                        max_delay: None,
+                    }),
+                    ..Default::default()
+                },
+            )
+        };
+        let params: HashMap<String, String> = [
+            ("method".to_string(), "turnstile".to_string()),
+            ("sitekey".to_string(), "0x4AAA".to_string()),
+            ("pageurl".to_string(), "https://example.com".to_string()),
+        ]
+        .into();
+
+        let receipt = client(3).submit(params.clone()).await.unwrap();
+        assert_eq!(receipt.captcha_id, "123");
+        let error = client(2).submit(params).await.unwrap_err();
+        assert_eq!(error.api_code(), Some(ApiErrorCode::NoSlotAvailable));
+    }
+
+    #[tokio::test]
+    async fn test_max_solve_attempts() {
+        use crate::scripted::Scripted;
+
+        // Numbers submissions and reports the first two unsolvable
+        let unsolvable = || {
+            Scripted::new(|request| {
+                if !request.poll {
+                    return Ok(format!("OK|{}", request.count + 1));
+                }
+                Ok(match request.params.get("id").map(String::as_str) {
+                    Some("1" | "2") => "ERROR_CAPTCHA_UNSOLVABLE",
+                    _ => "OK|token",
+                }
+                .to_string())
+            })
+        };
+
+        let client = |max_solve_attempts| {
+            TwoCaptcha::new(
+                "test_key",
+                TwoCaptchaConfig {
+                    transport: Some(Arc::new(unsolvable())),
+                    polling_interval: Some(Duration::from_millis(1)),
+                    max_solve_attempts: Some(max_solve_attempts),
+                    ..Default::default()
+                },
+            )
+        };
+        let params: HashMap<String, String> = [
+            ("method".to_string(), "turnstile".to_string()),
+            ("sitekey".to_string(), "0x4AAA".to_string()),
+            ("pageurl".to_string(), "https://example.com".to_string()),
+        ]
+        .into();
+
+        let result = client(3).solve(None, None, params.clone()).await.unwrap();
+        assert_eq!(result.captcha_id, "3");
+        let attempts = result.attempts.unwrap().attempts;
+        assert_eq!(attempts.len(), 3);
+        assert_eq!(
+            attempts[0].error_code.as_deref(),
+            Some("ERROR_CAPTCHA_UNSOLVABLE")
+        );
+        assert_eq!(attempts[1].captcha_id.as_deref(), Some("2"));
+
+        let error = client(2).solve(None, None, params).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Unsolvable);
+    }
+
+    #[tokio::test]
+    async fn test_poll_network_failures() {
+        use crate::scripted::Scripted;
+
+        // Fails result requests with a 502, for good if `down`, otherwise
+        // twice before the captcha is reported not ready and then solved
+        let flaky = |down| {
+            Scripted::new(move |request| {
+                if !request.poll {
+                    return Ok("OK|1".to_string());
+                }
+                match request.count {
+                    0 | 1 => Err(Scripted::bad_gateway()),
+                    _ if down => Err(Scripted::bad_gateway()),
+                    2 => Ok("CAPCHA_NOT_READY".to_string()),
+                    _ => Ok("OK|token".to_string()),
+                }
+            })
+        };
+
+        let client = |down| {
+            TwoCaptcha::new(
+                "test_key",
+                TwoCaptchaConfig {
+                    transport: Some(Arc::new(flaky(down))),
+                    ..Default::default()
+                },
+            )
+        };
+        let params: HashMap<String, String> = [
+            ("method".to_string(), "turnstile".to_string()),
+            ("sitekey".to_string(), "0x4AAA".to_string()),
+            ("pageurl".to_string(), "https://example.com".to_string()),
+        ]
+        .into();
+
+        let interval = Some(Duration::from_millis(1));
+        let result = client(false).solve(None, interval, params.clone()).await;
+        assert_eq!(result.unwrap().code.as_deref(), Some("token"));
+
+        let error = client(true)
+            .solve(None, interval, params)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Network);
+        assert_eq!(error.context().unwrap().polls, Some(MAX_POLL_FAILURES));
+    }
+
+    #[test]
+    fn test_update_config() {
+        let client = TwoCaptcha::new("test_key".to_string(), TwoCaptchaConfig::default());
+        let clone = client.clone();
+
+        client.update_config(TwoCaptchaConfig {
+            polling_interval: Some(Duration::from_secs(3)),
+            ..Default::default()
+        });
+
+        assert_eq!(clone.settings().polling_interval, Duration::from_secs(3));
+        assert_eq!(clone.settings().api_key.expose(), "test_key");
+
+        client.set_polling_interval(Duration::from_secs(7));
+        client.keys.insert("42", client.settings().api_key.clone());
+        client.set_api_key("rotated_key".to_string());
+        client.set_callback(Some("https://example.com/pingback".to_string()));
+        let settings = clone.settings();
+        assert_eq!(settings.polling_interval, Duration::from_secs(7));
+        assert_eq!(settings.api_key.expose(), "rotated_key");
+        assert_eq!(clone.key_of(&settings, "42").expose(), "test_key");
+        assert_eq!(clone.key_of(&settings, "43").expose(), "rotated_key");
+        assert!(settings.callback.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_max_in_flight() {
+        let config = TwoCaptchaConfig {
+            max_in_flight: Some(1),
+            ..Default::default()
+        };
+        let client = TwoCaptcha::new("test_key".to_string(), config);
+
+        let permit = client.acquire_slot().await.unwrap();
+        assert!(permit.is_some());
+        assert_eq!(client.in_flight.as_ref().unwrap().available_permits(), 0);
+        drop(permit);
+        assert_eq!(client.in_flight.as_ref().unwrap().available_permits(), 1);
+
+        let unlimited = TwoCaptcha::new("test_key".to_string(), TwoCaptchaConfig::default());
+        assert!(unlimited.acquire_slot().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_solve_is_reported() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = TwoCaptchaConfig {
+            server: Some(format!("http://{}", listener.local_addr().unwrap())),
+            report_abandoned: Some(true),
+            ..Default::default()
+        };
+        let client = TwoCaptcha::new("test_key".to_string(), config);
+
+        drop(AbandonGuard::new(&client, "123"));
+        AbandonGuard::new(&client, "456").disarm();
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = vec![0; 4096];
+        let n = stream.read(&mut request).await.unwrap();
+        let request = String::from_utf8_lossy(&request[..n]);
+        assert!(request.contains("action=reportbad"));
+        assert!(request.contains("id=123"));
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 18\r\n\r\nOK_REPORT_RECORDED")
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_check_cost() {
+        let mut price_table = PriceTable::new();
+        price_table.set("userrecaptcha:enterprise", 0.005);
+        let config = TwoCaptchaConfig {
+            price_table: Some(price_table),
+            max_task_cost: Some(0.003),
+            ..Default::default()
+        };
+        let client = TwoCaptcha::new("test_key".to_string(), config);
+
+        let mut params = HashMap::new();
+        params.insert("method".to_string(), "userrecaptcha".to_string());
+        params.insert("enterprise".to_string(), "1".to_string());
+        assert!(client.check_cost(&params).is_err());
+        assert!(client.with_max_task_cost(None).check_cost(&params).is_ok());
+    }
+
+    #[test]
+    fn test_parse_submit_response() {
+        let client = TwoCaptcha::new("test_key".to_string(), TwoCaptchaConfig::default());
+        let receipt = client.parse_submit_response("OK|2122988149").unwrap();
+        assert_eq!(receipt.captcha_id, "2122988149");
+        assert!(receipt.metadata.is_empty());
+
+        let receipt = client
+            .parse_submit_response(r#"{"status":1,"request":"2122988149","cost":"0.00299"}"#)
+            .unwrap();
+        assert_eq!(receipt.captcha_id, "2122988149");
+        assert_eq!(receipt.metadata.get("cost").unwrap(), "0.00299");
+
+        assert!(
+            client
+                .parse_submit_response(r#"{"status":0,"request":"ERROR"}"#)
+                .is_err()
+        );
+
+        let err = client
+            .parse_submit_response(
+                r#"{"status":0,"request":"ERROR_ZERO_BALANCE","error_text":"You don't have funds"}"#,
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "API error: ERROR_ZERO_BALANCE: You don't have funds"
+        );
+    }
 }
@@ -0,0 +1,889 @@
+//! Conversion between `in.php` parameters and the task objects of the
+//! 2captcha JSON API (v2), the format used by the provider's documentation
+//! and other SDKs.
+
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use crate::error::{Result, TwoCaptchaError};
+use crate::methods::ResultKind;
+use crate::utils::Utils;
+
+/// Task type whose wire parameters are known, usually through
+/// `#[derive(CaptchaTask)]` (feature `derive`)
+///
+/// ```ignore
+/// use twocaptcha::CaptchaTask;
+///
+/// #[derive(CaptchaTask)]
+/// #[captcha(method = "hcaptcha")]
+/// struct Checkout {
+///     #[param(name = "sitekey")]
+///     site_key: String,
+///     #[param(name = "pageurl")]
+///     url: String,
+///     invisible: bool,
+///     #[param(name = "userAgent")]
+///     user_agent: Option<String>,
+/// }
+/// ```
+pub trait CaptchaTask {
+    fn to_task(&self) -> Task;
+}
+
+impl CaptchaTask for Task {
+    fn to_task(&self) -> Task {
+        self.clone()
+    }
+}
+
+/// Field type usable in a derived [`CaptchaTask`]
+pub trait ParamValue {
+    /// Wire value, or `None` to leave the parameter out
+    fn to_param(&self) -> Option<String>;
+}
+
+impl ParamValue for String {
+    fn to_param(&self) -> Option<String> {
+        Some(self.clone())
+    }
+}
+
+impl ParamValue for &str {
+    fn to_param(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl ParamValue for bool {
+    fn to_param(&self) -> Option<String> {
+        Some(if *self { "1" } else { "0" }.to_string())
+    }
+}
+
+macro_rules! display_param {
+    ($($ty:ty),*) => {
+        $(impl ParamValue for $ty {
+            fn to_param(&self) -> Option<String> {
+                Some(self.to_string())
+            }
+        })*
+    };
+}
+
+display_param!(u8, u16, u32, u64, usize, i32, i64, f32, f64);
+
+impl<T: ParamValue> ParamValue for Option<T> {
+    fn to_param(&self) -> Option<String> {
+        self.as_ref()?.to_param()
+    }
+}
+
+/// JSON type of a task field
+#[derive(Clone, Copy)]
+enum Kind {
+    Str,
+    Bool,
+    Int,
+    Float,
+}
+
+/// Mapping between one `in.php` method variant and a v2 task type
+pub(crate) struct Schema {
+    /// v2 task type, without the `Proxyless` suffix
+    task_type: &'static str,
+    /// Whether the type comes in a `Proxyless` variant
+    proxyless: bool,
+    /// `in.php` parameters identifying the variant
+    markers: &'static [(&'static str, &'static str)],
+    /// Fixed v2 fields identifying the variant
+    constants: &'static [(&'static str, i64)],
+    /// `in.php` name, v2 name (dotted for nested objects, empty to drop) and type
+    fields: &'static [(&'static str, &'static str, Kind)],
+    /// `in.php` fields the API refuses the task without
+    required: &'static [&'static str],
+    result: ResultKind,
+}
+
+impl Schema {
+    pub(crate) fn task_type(&self) -> &'static str {
+        self.task_type
+    }
+
+    pub(crate) fn markers(&self) -> &'static [(&'static str, &'static str)] {
+        self.markers
+    }
+
+    pub(crate) fn required(&self) -> &'static [&'static str] {
+        self.required
+    }
+
+    /// Fields the API accepts besides the required ones
+    pub(crate) fn optional(&self) -> impl Iterator<Item = &'static str> {
+        self.fields
+            .iter()
+            .filter(|(v1, v2, _)| !v2.is_empty() && !self.required.contains(v1))
+            .map(|(v1, _, _)| *v1)
+    }
+
+    pub(crate) fn result(&self) -> ResultKind {
+        self.result
+    }
+}
+
+const IMAGE_HINTS: [(&str, &str, Kind); 2] = [
+    ("textinstructions", "comment", Kind::Str),
+    ("imginstructions", "imgInstructions", Kind::Str),
+];
+
+pub(crate) const SCHEMAS: &[Schema] = &[
+    Schema {
+        task_type: "ImageToTextTask",
+        proxyless: false,
+        markers: &[("method", "base64")],
+        constants: &[],
+        required: &["body"],
+        result: ResultKind::Text,
+        fields: &[
+            ("body", "body", Kind::Str),
+            ("phrase", "phrase", Kind::Bool),
+            ("regsense", "case", Kind::Bool),
+            ("numeric", "numeric", Kind::Int),
+            ("calc", "math", Kind::Bool),
+            ("min_len", "minLength", Kind::Int),
+            ("max_len", "maxLength", Kind::Int),
+            IMAGE_HINTS[0],
+            IMAGE_HINTS[1],
+        ],
+    },
+    Schema {
+        task_type: "GridTask",
+        proxyless: false,
+        markers: &[("method", "base64"), ("recaptcha", "1")],
+        constants: &[],
+        required: &["body"],
+        result: ResultKind::Text,
+        fields: &[
+            ("body", "body", Kind::Str),
+            ("recaptcharows", "rows", Kind::Int),
+            ("recaptchacols", "columns", Kind::Int),
+            IMAGE_HINTS[0],
+            IMAGE_HINTS[1],
+        ],
+    },
+    Schema {
+        task_type: "DrawAroundTask",
+        proxyless: false,
+        markers: &[("method", "base64"), ("recaptcha", "1"), ("canvas", "1")],
+        constants: &[],
+        required: &["body"],
+        result: ResultKind::Coordinates,
+        fields: &[("body", "body", Kind::Str), IMAGE_HINTS[0], IMAGE_HINTS[1]],
+    },
+    Schema {
+        task_type: "CoordinatesTask",
+        proxyless: false,
+        markers: &[("method", "base64"), ("coordinatescaptcha", "1")],
+        constants: &[],
+        required: &["body"],
+        result: ResultKind::Coordinates,
+        fields: &[
+            ("body", "body", Kind::Str),
+            ("min_clicks", "minClicks", Kind::Int),
+            ("max_clicks", "maxClicks", Kind::Int),
+            IMAGE_HINTS[0],
+            IMAGE_HINTS[1],
+        ],
+    },
+    Schema {
+        task_type: "RotateTask",
+        proxyless: false,
+        markers: &[("method", "rotatecaptcha")],
+        constants: &[],
+        required: &["body"],
+        result: ResultKind::Text,
+        fields: &[
+            ("body", "body", Kind::Str),
+            ("angle", "angle", Kind::Int),
+            IMAGE_HINTS[0],
+            IMAGE_HINTS[1],
+        ],
+    },
+    Schema {
+        task_type: "AudioTask",
+        proxyless: false,
+        markers: &[("method", "audio")],
+        constants: &[],
+        required: &["body", "lang"],
+        result: ResultKind::Text,
+        fields: &[("body", "body", Kind::Str), ("lang", "lang", Kind::Str)],
+    },
+    Schema {
+        task_type: "TextCaptchaTask",
+        proxyless: false,
+        markers: &[("method", "post")],
+        constants: &[],
+        required: &["textcaptcha"],
+        result: ResultKind::Text,
+        fields: &[("textcaptcha", "comment", Kind::Str)],
+    },
+    Schema {
+        task_type: "RecaptchaV2Task",
+        proxyless: true,
+        markers: &[("method", "userrecaptcha")],
+        constants: &[],
+        required: &["pageurl", "googlekey"],
+        result: ResultKind::Token,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("googlekey", "websiteKey", Kind::Str),
+            ("invisible", "isInvisible", Kind::Bool),
+            ("data-s", "recaptchaDataSValue", Kind::Str),
+            ("userAgent", "userAgent", Kind::Str),
+            ("cookies", "cookies", Kind::Str),
+            ("domain", "apiDomain", Kind::Str),
+            ("version", "", Kind::Str),
+            ("enterprise", "", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "RecaptchaV3Task",
+        proxyless: true,
+        markers: &[("method", "userrecaptcha"), ("version", "v3")],
+        constants: &[],
+        required: &["pageurl", "googlekey"],
+        result: ResultKind::Token,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("googlekey", "websiteKey", Kind::Str),
+            ("min_score", "minScore", Kind::Float),
+            ("action", "pageAction", Kind::Str),
+            ("enterprise", "isEnterprise", Kind::Bool),
+            ("domain", "apiDomain", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "RecaptchaV2EnterpriseTask",
+        proxyless: true,
+        markers: &[("method", "userrecaptcha"), ("enterprise", "1")],
+        constants: &[],
+        required: &["pageurl", "googlekey"],
+        result: ResultKind::Token,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("googlekey", "websiteKey", Kind::Str),
+            ("invisible", "isInvisible", Kind::Bool),
+            ("data-s", "enterprisePayload.s", Kind::Str),
+            ("userAgent", "userAgent", Kind::Str),
+            ("cookies", "cookies", Kind::Str),
+            ("domain", "apiDomain", Kind::Str),
+            ("version", "", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "HCaptchaTask",
+        proxyless: true,
+        markers: &[("method", "hcaptcha")],
+        constants: &[],
+        required: &["pageurl", "sitekey"],
+        result: ResultKind::Token,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("sitekey", "websiteKey", Kind::Str),
+            ("invisible", "isInvisible", Kind::Bool),
+            ("data", "enterprisePayload.rqdata", Kind::Str),
+            ("userAgent", "userAgent", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "FunCaptchaTask",
+        proxyless: true,
+        markers: &[("method", "funcaptcha")],
+        constants: &[],
+        required: &["pageurl", "publickey"],
+        result: ResultKind::Token,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("publickey", "websitePublicKey", Kind::Str),
+            ("surl", "funcaptchaApiJSSubdomain", Kind::Str),
+            ("data", "data", Kind::Str),
+            ("userAgent", "userAgent", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "GeeTestTask",
+        proxyless: true,
+        markers: &[("method", "geetest")],
+        constants: &[],
+        required: &["pageurl", "gt", "challenge"],
+        result: ResultKind::Json,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("gt", "gt", Kind::Str),
+            ("challenge", "challenge", Kind::Str),
+            ("api_server", "geetestApiServerSubdomain", Kind::Str),
+            ("userAgent", "userAgent", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "GeeTestTask",
+        proxyless: true,
+        markers: &[("method", "geetest_v4")],
+        constants: &[("version", 4)],
+        required: &["pageurl", "captcha_id"],
+        result: ResultKind::Json,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("captcha_id", "initParameters.captcha_id", Kind::Str),
+            ("userAgent", "userAgent", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "KeyCaptchaTask",
+        proxyless: true,
+        markers: &[("method", "keycaptcha")],
+        constants: &[],
+        required: &[
+            "pageurl",
+            "s_s_c_user_id",
+            "s_s_c_session_id",
+            "s_s_c_web_server_sign",
+            "s_s_c_web_server_sign2",
+        ],
+        result: ResultKind::Token,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("s_s_c_user_id", "s_s_c_user_id", Kind::Str),
+            ("s_s_c_session_id", "s_s_c_session_id", Kind::Str),
+            ("s_s_c_web_server_sign", "s_s_c_web_server_sign", Kind::Str),
+            (
+                "s_s_c_web_server_sign2",
+                "s_s_c_web_server_sign2",
+                Kind::Str,
+            ),
+        ],
+    },
+    Schema {
+        task_type: "CapyTask",
+        proxyless: true,
+        markers: &[("method", "capy")],
+        constants: &[],
+        required: &["pageurl", "captchakey"],
+        result: ResultKind::Json,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("captchakey", "websiteKey", Kind::Str),
+            ("userAgent", "userAgent", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "LeminTask",
+        proxyless: true,
+        markers: &[("method", "lemin")],
+        constants: &[],
+        required: &["pageurl", "captcha_id", "div_id"],
+        result: ResultKind::Json,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("captcha_id", "captchaId", Kind::Str),
+            ("div_id", "divId", Kind::Str),
+            ("api_server", "leminApiServerSubdomain", Kind::Str),
+            ("userAgent", "userAgent", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "TurnstileTask",
+        proxyless: true,
+        markers: &[("method", "turnstile")],
+        constants: &[],
+        required: &["pageurl", "sitekey"],
+        result: ResultKind::Token,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("sitekey", "websiteKey", Kind::Str),
+            ("action", "action", Kind::Str),
+            ("data", "data", Kind::Str),
+            ("pagedata", "pagedata", Kind::Str),
+            ("useragent", "userAgent", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "AmazonTask",
+        proxyless: true,
+        markers: &[("method", "amazon_waf")],
+        constants: &[],
+        required: &["pageurl", "sitekey", "iv", "context"],
+        result: ResultKind::Json,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("sitekey", "websiteKey", Kind::Str),
+            ("iv", "iv", Kind::Str),
+            ("context", "context", Kind::Str),
+            ("challenge_script", "challengeScript", Kind::Str),
+            ("captcha_script", "captchaScript", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "MtCaptchaTask",
+        proxyless: true,
+        markers: &[("method", "mt_captcha")],
+        constants: &[],
+        required: &["pageurl", "sitekey"],
+        result: ResultKind::Token,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("sitekey", "websiteKey", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "FriendlyCaptchaTask",
+        proxyless: true,
+        markers: &[("method", "friendly_captcha")],
+        constants: &[],
+        required: &["pageurl", "sitekey"],
+        result: ResultKind::Token,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("sitekey", "websiteKey", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "CutCaptchaTask",
+        proxyless: true,
+        markers: &[("method", "cutcaptcha")],
+        constants: &[],
+        required: &["pageurl", "misery_key", "api_key"],
+        result: ResultKind::Token,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("misery_key", "miseryKey", Kind::Str),
+            ("api_key", "dataApiKey", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "TencentTask",
+        proxyless: true,
+        markers: &[("method", "tencent")],
+        constants: &[],
+        required: &["pageurl", "app_id"],
+        result: ResultKind::Json,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("app_id", "appId", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "AtbCaptchaTask",
+        proxyless: true,
+        markers: &[("method", "atb_captcha")],
+        constants: &[],
+        required: &["pageurl", "app_id", "api_server"],
+        result: ResultKind::Json,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("app_id", "appId", Kind::Str),
+            ("api_server", "apiServer", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "DataDomeSliderTask",
+        proxyless: false,
+        markers: &[("method", "datadome")],
+        constants: &[],
+        required: &["pageurl", "captcha_url", "userAgent"],
+        result: ResultKind::Token,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("captcha_url", "captchaUrl", Kind::Str),
+            ("userAgent", "userAgent", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "AntiCyberSiAraTask",
+        proxyless: true,
+        markers: &[("method", "cybersiara")],
+        constants: &[],
+        required: &["pageurl", "master_url_id", "userAgent"],
+        result: ResultKind::Token,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("master_url_id", "SlideMasterUrlId", Kind::Str),
+            ("userAgent", "userAgent", Kind::Str),
+        ],
+    },
+    Schema {
+        task_type: "YandexSmartCaptchaTask",
+        proxyless: true,
+        markers: &[("method", "yandex")],
+        constants: &[],
+        required: &["pageurl", "sitekey"],
+        result: ResultKind::Token,
+        fields: &[
+            ("pageurl", "websiteURL", Kind::Str),
+            ("sitekey", "websiteKey", Kind::Str),
+            ("userAgent", "userAgent", Kind::Str),
+        ],
+    },
+];
+
+/// Request-level `in.php` parameters that are not part of a v2 task object
+const REQUEST_PARAMS: &[&str] = &["key", "soft_id", "json", "pingback", "header_acao"];
+
+/// A captcha task, held as the parameters passed to [`TwoCaptcha::solve`]
+///
+/// Parameter aliases accepted by the solver (`url`, `caseSensitive`, a
+/// JSON `proxy`, ...) may be used.
+///
+/// [`TwoCaptcha::solve`]: crate::TwoCaptcha::solve
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Task {
+    params: HashMap<String, String>,
+}
+
+impl Task {
+    pub fn from_params(params: HashMap<String, String>) -> Self {
+        Self { params }
+    }
+
+    pub fn params(&self) -> &HashMap<String, String> {
+        &self.params
+    }
+
+    pub fn into_params(self) -> HashMap<String, String> {
+        self.params
+    }
+
+    /// Convert to a task object of the 2captcha JSON API
+    ///
+    /// Request-level parameters (API key, soft id, callback) are left out.
+    /// Parameters without a v2 equivalent are copied under the same name.
+    pub fn to_official_json(&self) -> Result<Value> {
+        let mut params = Utils::rename_params(self.params.clone());
+        let schema = Self::schema_for(&params)?;
+
+        for (key, _) in schema.markers {
+            params.remove(*key);
+        }
+        for key in REQUEST_PARAMS {
+            params.remove(*key);
+        }
+
+        let proxy = match (params.remove("proxy"), params.remove("proxytype")) {
+            (Some(uri), Some(proxy_type)) => Some((uri, proxy_type)),
+            _ => None,
+        };
+        let task_type = match (&proxy, schema.proxyless) {
+            (None, true) => format!("{}Proxyless", schema.task_type),
+            _ => schema.task_type.to_string(),
+        };
+
+        let mut task = Map::new();
+        task.insert("type".to_string(), Value::from(task_type));
+        for (key, value) in schema.constants {
+            task.insert(key.to_string(), Value::from(*value));
+        }
+        for (v1, v2, kind) in schema.fields {
+            if let Some(value) = params.remove(*v1)
+                && !v2.is_empty()
+            {
+                insert_path(&mut task, v2, to_json(v1, &value, *kind)?);
+            }
+        }
+        if let Some((uri, proxy_type)) = proxy {
+            insert_proxy(&mut task, &uri, &proxy_type)?;
+        }
+        for (key, value) in params {
+            task.entry(key).or_insert(Value::String(value));
+        }
+
+        Ok(Value::Object(task))
+    }
+
+    /// Build a task from a task object of the 2captcha JSON API
+    pub fn from_official_json(task: &Value) -> Result<Self> {
+        let mut task = task
+            .as_object()
+            .cloned()
+            .ok_or_else(|| TwoCaptchaError::Validation("task must be a JSON object".to_string()))?;
+        let task_type = match task.remove("type") {
+            Some(Value::String(task_type)) => task_type,
+            _ => {
+                return Err(TwoCaptchaError::Validation(
+                    "task has no `type`".to_string(),
+                ));
+            }
+        };
+        let base_type = task_type.strip_suffix("Proxyless").unwrap_or(&task_type);
+
+        let schema = SCHEMAS
+            .iter()
+            .filter(|schema| schema.task_type == base_type)
+            .filter(|schema| {
+                schema
+                    .constants
+                    .iter()
+                    .all(|(key, value)| task.get(*key).and_then(Value::as_i64) == Some(*value))
+            })
+            .max_by_key(|schema| schema.constants.len())
+            .ok_or_else(|| {
+                TwoCaptchaError::Validation(format!("unsupported task type `{task_type}`"))
+            })?;
+
+        let mut params = HashMap::new();
+        for (key, value) in schema.markers {
+            params.insert(key.to_string(), value.to_string());
+        }
+        for (key, _) in schema.constants {
+            task.remove(*key);
+        }
+        for (v1, v2, _) in schema.fields {
+            if let Some(value) = take_path(&mut task, v2) {
+                params.insert(v1.to_string(), from_json(value));
+            }
+        }
+        if let Some(proxy) = take_proxy(&mut task) {
+            params.insert("proxy".to_string(), proxy.to_string());
+        }
+        for (key, value) in task {
+            params.insert(key, from_json(value));
+        }
+
+        Ok(Self { params })
+    }
+
+    /// Most specific schema whose markers all match `params`
+    fn schema_for(params: &HashMap<String, String>) -> Result<&'static Schema> {
+        SCHEMAS
+            .iter()
+            .filter(|schema| {
+                schema
+                    .markers
+                    .iter()
+                    .all(|(key, value)| params.get(*key).map(String::as_str) == Some(*value))
+            })
+            .fold(None, |best: Option<&Schema>, schema| match best {
+                Some(best) if best.markers.len() >= schema.markers.len() => Some(best),
+                _ => Some(schema),
+            })
+            .ok_or_else(|| {
+                TwoCaptchaError::Validation(format!(
+                    "no v2 task type for method `{}`",
+                    params.get("method").map(String::as_str).unwrap_or_default()
+                ))
+            })
+    }
+}
+
+fn to_json(key: &str, value: &str, kind: Kind) -> Result<Value> {
+    let invalid = || TwoCaptchaError::Validation(format!("invalid value `{value}` for `{key}`"));
+
+    Ok(match kind {
+        Kind::Str => Value::from(value),
+        Kind::Bool => Value::from(matches!(value, "1" | "true")),
+        Kind::Int => Value::from(value.parse::<i64>().map_err(|_| invalid())?),
+        Kind::Float => Value::from(value.parse::<f64>().map_err(|_| invalid())?),
+    })
+}
+
+fn from_json(value: Value) -> String {
+    match value {
+        Value::String(value) => value,
+        Value::Bool(value) => if value { "1" } else { "0" }.to_string(),
+        value => value.to_string(),
+    }
+}
+
+fn insert_path(task: &mut Map<String, Value>, path: &str, value: Value) {
+    match path.split_once('.') {
+        Some((head, rest)) => {
+            let child = task
+                .entry(head)
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(child) = child {
+                insert_path(child, rest, value);
+            }
+        }
+        None => {
+            task.insert(path.to_string(), value);
+        }
+    }
+}
+
+fn take_path(task: &mut Map<String, Value>, path: &str) -> Option<Value> {
+    match path.split_once('.') {
+        Some((head, rest)) => {
+            let Value::Object(child) = task.get_mut(head)? else {
+                return None;
+            };
+            let value = take_path(child, rest);
+            if child.is_empty() {
+                task.remove(head);
+            }
+            value
+        }
+        None if path.is_empty() => None,
+        None => task.remove(path),
+    }
+}
+
+/// Split an `in.php` proxy (`login:password@host:port`) into v2 fields
+fn insert_proxy(task: &mut Map<String, Value>, uri: &str, proxy_type: &str) -> Result<()> {
+    let invalid = || TwoCaptchaError::Validation(format!("invalid proxy `{uri}`"));
+
+    let (credentials, address) = match uri.rsplit_once('@') {
+        Some((credentials, address)) => (Some(credentials), address),
+        None => (None, uri),
+    };
+    let (host, port) = address.rsplit_once(':').ok_or_else(invalid)?;
+    let port: u16 = port.parse().map_err(|_| invalid())?;
+
+    task.insert(
+        "proxyType".to_string(),
+        Value::from(proxy_type.to_lowercase()),
+    );
+    task.insert("proxyAddress".to_string(), Value::from(host));
+    task.insert("proxyPort".to_string(), Value::from(port));
+    if let Some((login, password)) = credentials.and_then(|c| c.split_once(':')) {
+        task.insert("proxyLogin".to_string(), Value::from(login));
+        task.insert("proxyPassword".to_string(), Value::from(password));
+    }
+    Ok(())
+}
+
+/// Gather v2 proxy fields into the JSON proxy accepted by the solver
+fn take_proxy(task: &mut Map<String, Value>) -> Option<Value> {
+    let proxy_type = from_json(task.remove("proxyType")?).to_uppercase();
+    let host = task.remove("proxyAddress").map(from_json)?;
+    let port = task.remove("proxyPort").map(from_json)?;
+    let login = task.remove("proxyLogin").map(from_json);
+    let password = task.remove("proxyPassword").map(from_json);
+
+    let uri = match (login, password) {
+        (Some(login), Some(password)) => format!("{login}:{password}@{host}:{port}"),
+        _ => format!("{host}:{port}"),
+    };
+    Some(serde_json::json!({ "type": proxy_type, "uri": uri }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn task(pairs: &[(&str, &str)]) -> Task {
+        Task::from_params(
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_recaptcha_v3_to_official_json() {
+        let task = task(&[
+            ("method", "userrecaptcha"),
+            ("version", "v3"),
+            ("googlekey", "6LfB5_IbAAAAAMCtsjEHEHKqcB9iQocwwxTiihJu"),
+            ("url", "https://2captcha.com/demo/recaptcha-v3"),
+            ("score", "0.3"),
+            ("action", "verify"),
+            ("key", "API_KEY"),
+        ]);
+
+        assert_eq!(
+            task.to_official_json().unwrap(),
+            json!({
+                "type": "RecaptchaV3TaskProxyless",
+                "websiteURL": "https://2captcha.com/demo/recaptcha-v3",
+                "websiteKey": "6LfB5_IbAAAAAMCtsjEHEHKqcB9iQocwwxTiihJu",
+                "minScore": 0.3,
+                "pageAction": "verify",
+            })
+        );
+    }
+
+    #[test]
+    fn test_official_json_round_trip() {
+        let official = json!({
+            "type": "GeeTestTask",
+            "websiteURL": "https://2captcha.com/demo/geetest-v4",
+            "version": 4,
+            "initParameters": { "captcha_id": "e392e1d7fd421dc63325744d5a2b9c73" },
+            "proxyType": "http",
+            "proxyAddress": "1.2.3.4",
+            "proxyPort": 8080,
+            "proxyLogin": "user",
+            "proxyPassword": "pass",
+        });
+
+        let task = Task::from_official_json(&official).unwrap();
+        assert_eq!(task.params()["method"], "geetest_v4");
+        assert_eq!(
+            task.params()["captcha_id"],
+            "e392e1d7fd421dc63325744d5a2b9c73"
+        );
+        assert_eq!(task.to_official_json().unwrap(), official);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_captcha_task() {
+        #[derive(crate::CaptchaTask)]
+        #[captcha(method = "hcaptcha")]
+        struct Checkout {
+            #[param(name = "sitekey")]
+            site_key: String,
+            #[param(name = "pageurl")]
+            url: &'static str,
+            invisible: bool,
+            #[param(name = "userAgent")]
+            user_agent: Option<String>,
+            #[param(skip)]
+            _note: u32,
+        }
+
+        let task = Checkout {
+            site_key: "KEY".to_string(),
+            url: "https://example.com",
+            invisible: true,
+            user_agent: None,
+            _note: 7,
+        }
+        .to_task();
+
+        assert_eq!(task.params()["method"], "hcaptcha");
+        assert_eq!(task.params()["sitekey"], "KEY");
+        assert_eq!(task.params()["pageurl"], "https://example.com");
+        assert_eq!(task.params()["invisible"], "1");
+        assert_eq!(task.params().len(), 4);
+    }
+
+    #[test]
+    fn test_grid_schema_is_most_specific() {
+        let task = task(&[
+            ("method", "base64"),
+            ("recaptcha", "1"),
+            ("body", "aGVsbG8="),
+            ("rows", "3"),
+        ]);
+
+        let official = task.to_official_json().unwrap();
+        assert_eq!(official["type"], "GridTask");
+        assert_eq!(official["rows"], 3);
+        assert!(Task::from_official_json(&json!({ "type": "UnknownTask" })).is_err());
+    }
+
+    #[test]
+    fn test_required_params_are_schema_fields() {
+        for schema in SCHEMAS {
+            for required in schema.required {
+                assert!(
+                    schema.fields.iter().any(|(v1, _, _)| v1 == required),
+                    "{} requires unknown `{required}`",
+                    schema.task_type
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Distinguishes ids generated within the same hasher state
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Random version 4 UUID identifying one solve attempt, e.g.
+/// `5f0c6a1e-8d3b-4c2a-9e71-0b6d2f4a8c13`
+pub(crate) fn new_trace_id() -> String {
+    let half = || {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        hasher.finish()
+    };
+    let (high, low) = (half(), half());
+    // Version 4, variant 10xx
+    let high = (high & !0xf000) | 0x4000;
+    let low = (low & !(0xc << 60)) | (0x8 << 60);
+
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_ids_are_uuids() {
+        let id = new_trace_id();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        assert!(matches!(&id[19..20], "8" | "9" | "a" | "b"));
+        assert_ne!(id, new_trace_id());
+    }
+}
@@ -0,0 +1,241 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::error::Result;
+use crate::extras::Extras;
+use crate::solver::TwoCaptcha;
+use crate::types::{Balance, CaptchaImage, CaptchaResult, RecaptchaVersion, ReportOutcome};
+
+/// High-level solving interface implemented by [`TwoCaptcha`]
+///
+/// Depend on `dyn CaptchaSolver` (or a generic bound) in application code to
+/// swap in a stub during tests. See the matching [`TwoCaptcha`] methods for
+/// the meaning of each parameter.
+#[async_trait]
+pub trait CaptchaSolver: Send + Sync {
+    /// Solve a normal captcha (image)
+    async fn normal(&self, file: CaptchaImage, params: Option<Extras>) -> Result<CaptchaResult>;
+
+    /// Solve a text captcha
+    async fn text(&self, text: &str, params: Option<Extras>) -> Result<CaptchaResult>;
+
+    /// Solve reCAPTCHA (v2, v3)
+    async fn recaptcha(
+        &self,
+        sitekey: &str,
+        url: &str,
+        version: Option<RecaptchaVersion>,
+        enterprise: Option<bool>,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult>;
+
+    /// Solve hCaptcha
+    async fn hcaptcha(
+        &self,
+        sitekey: &str,
+        url: &str,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult>;
+
+    /// Solve Cloudflare Turnstile
+    async fn turnstile(
+        &self,
+        sitekey: &str,
+        url: &str,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult>;
+
+    /// Solve FunCaptcha
+    async fn funcaptcha(
+        &self,
+        sitekey: &str,
+        url: &str,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult>;
+
+    /// Solve GeeTest captcha
+    async fn geetest(
+        &self,
+        gt: &str,
+        challenge: &str,
+        url: &str,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult>;
+
+    /// Solve a task from raw request parameters
+    async fn solve(
+        &self,
+        timeout: Option<Duration>,
+        polling_interval: Option<Duration>,
+        params: HashMap<String, String>,
+    ) -> Result<CaptchaResult>;
+
+    /// Get the account balance
+    async fn balance(&self) -> Result<Balance>;
+
+    /// Report whether an answer was correct
+    async fn report(&self, id: &str, correct: bool) -> Result<ReportOutcome>;
+}
+
+#[async_trait]
+impl CaptchaSolver for TwoCaptcha {
+    async fn normal(&self, file: CaptchaImage, params: Option<Extras>) -> Result<CaptchaResult> {
+        TwoCaptcha::normal(self, file, params).await
+    }
+
+    async fn text(&self, text: &str, params: Option<Extras>) -> Result<CaptchaResult> {
+        TwoCaptcha::text(self, text, params).await
+    }
+
+    async fn recaptcha(
+        &self,
+        sitekey: &str,
+        url: &str,
+        version: Option<RecaptchaVersion>,
+        enterprise: Option<bool>,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult> {
+        TwoCaptcha::recaptcha(self, sitekey, url, version, enterprise, params).await
+    }
+
+    async fn hcaptcha(
+        &self,
+        sitekey: &str,
+        url: &str,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult> {
+        TwoCaptcha::hcaptcha(self, sitekey, url, params).await
+    }
+
+    async fn turnstile(
+        &self,
+        sitekey: &str,
+        url: &str,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult> {
+        TwoCaptcha::turnstile(self, sitekey, url, params).await
+    }
+
+    async fn funcaptcha(
+        &self,
+        sitekey: &str,
+        url: &str,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult> {
+        TwoCaptcha::funcaptcha(self, sitekey, url, params).await
+    }
+
+    async fn geetest(
+        &self,
+        gt: &str,
+        challenge: &str,
+        url: &str,
+        params: Option<Extras>,
+    ) -> Result<CaptchaResult> {
+        TwoCaptcha::geetest(self, gt, challenge, url, params).await
+    }
+
+    async fn solve(
+        &self,
+        timeout: Option<Duration>,
+        polling_interval: Option<Duration>,
+        params: HashMap<String, String>,
+    ) -> Result<CaptchaResult> {
+        TwoCaptcha::solve(self, timeout, polling_interval, params).await
+    }
+
+    async fn balance(&self) -> Result<Balance> {
+        TwoCaptcha::balance(self).await
+    }
+
+    async fn report(&self, id: &str, correct: bool) -> Result<ReportOutcome> {
+        TwoCaptcha::report(self, id, correct).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::TwoCaptchaError;
+
+    /// Stub answering every token captcha with the same token
+    struct StubSolver;
+
+    #[async_trait]
+    impl CaptchaSolver for StubSolver {
+        async fn normal(&self, _: CaptchaImage, _: Option<Extras>) -> Result<CaptchaResult> {
+            Err(TwoCaptchaError::Validation("unsupported".to_string()))
+        }
+
+        async fn text(&self, _: &str, _: Option<Extras>) -> Result<CaptchaResult> {
+            Err(TwoCaptchaError::Validation("unsupported".to_string()))
+        }
+
+        async fn recaptcha(
+            &self,
+            _: &str,
+            _: &str,
+            _: Option<RecaptchaVersion>,
+            _: Option<bool>,
+            _: Option<Extras>,
+        ) -> Result<CaptchaResult> {
+            self.solve(None, None, HashMap::new()).await
+        }
+
+        async fn hcaptcha(&self, _: &str, _: &str, _: Option<Extras>) -> Result<CaptchaResult> {
+            self.solve(None, None, HashMap::new()).await
+        }
+
+        async fn turnstile(&self, _: &str, _: &str, _: Option<Extras>) -> Result<CaptchaResult> {
+            self.solve(None, None, HashMap::new()).await
+        }
+
+        async fn funcaptcha(&self, _: &str, _: &str, _: Option<Extras>) -> Result<CaptchaResult> {
+            self.solve(None, None, HashMap::new()).await
+        }
+
+        async fn geetest(
+            &self,
+            _: &str,
+            _: &str,
+            _: &str,
+            _: Option<Extras>,
+        ) -> Result<CaptchaResult> {
+            self.solve(None, None, HashMap::new()).await
+        }
+
+        async fn solve(
+            &self,
+            _: Option<Duration>,
+            _: Option<Duration>,
+            _: HashMap<String, String>,
+        ) -> Result<CaptchaResult> {
+            Ok(CaptchaResult {
+                captcha_id: "1".to_string(),
+                code: Some("TOKEN".to_string()),
+                ..Default::default()
+            })
+        }
+
+        async fn balance(&self) -> Result<Balance> {
+            Ok(Balance(1.0))
+        }
+
+        async fn report(&self, _: &str, _: bool) -> Result<ReportOutcome> {
+            Ok(ReportOutcome::Recorded)
+        }
+    }
+
+    async fn login(solver: &dyn CaptchaSolver) -> Result<String> {
+        let result = solver
+            .turnstile("0x4AAAAAAAChNiVJM_WtShFf", "https://example.com", None)
+            .await?;
+        Ok(result.code.unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_stub_solver() {
+        assert_eq!(login(&StubSolver).await.unwrap(), "TOKEN");
+    }
+}
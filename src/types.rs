@@ -1,5 +1,51 @@
+use crate::answer::{CaseMode, answers_match};
+use crate::error::{Result, TwoCaptchaError};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Image input for image based captchas
+///
+/// Plain strings convert into [`CaptchaImage::Auto`], which guesses whether
+/// the value is base64 data, a URL or a file path. Use the explicit
+/// constructors to skip the guesswork.
+#[derive(Debug, Clone)]
+pub enum CaptchaImage {
+    Auto(String),
+    Base64(String),
+    Path(PathBuf),
+    Url(String),
+}
+
+impl CaptchaImage {
+    /// Base64 encoded image data
+    pub fn base64(body: impl Into<String>) -> Self {
+        CaptchaImage::Base64(body.into())
+    }
+
+    /// Path to an image file
+    pub fn path(path: impl Into<PathBuf>) -> Self {
+        CaptchaImage::Path(path.into())
+    }
+
+    /// URL to download the image from
+    pub fn url(url: impl Into<String>) -> Self {
+        CaptchaImage::Url(url.into())
+    }
+}
+
+impl From<&str> for CaptchaImage {
+    fn from(file: &str) -> Self {
+        CaptchaImage::Auto(file.to_string())
+    }
+}
+
+impl From<String> for CaptchaImage {
+    fn from(file: String) -> Self {
+        CaptchaImage::Auto(file)
+    }
+}
 
 /// Proxy configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,33 +55,473 @@ pub struct Proxy {
     pub uri: String,
 }
 
+/// Browser fingerprint of the scraper a token will be used from
+///
+/// Set it with [`TwoCaptcha::with_fingerprint`](crate::TwoCaptcha::with_fingerprint)
+/// to have workers solve with the same user agent. Only the user agent has
+/// an API parameter, and only for token captchas; the rest is echoed in
+/// [`CaptchaResult::fingerprint`] to keep the solve context together.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fingerprint {
+    pub user_agent: Option<String>,
+    pub accept_language: Option<String>,
+    pub platform: Option<String>,
+    pub timezone: Option<String>,
+}
+
+impl Fingerprint {
+    /// Parameter carrying the user agent for `method`, if it takes one
+    pub(crate) fn user_agent_key(method: &str) -> Option<&'static str> {
+        match method {
+            "turnstile" => Some("useragent"),
+            "userrecaptcha" | "hcaptcha" | "funcaptcha" | "geetest" | "geetest_v4"
+            | "amazon_waf" | "datadome" | "cybersiara" => Some("userAgent"),
+            _ => None,
+        }
+    }
+
+    /// Add the API parameters of this fingerprint unless already set
+    pub(crate) fn apply(&self, params: &mut HashMap<String, String>) {
+        let method = params.get("method").map(String::as_str).unwrap_or_default();
+        if let Some(user_agent) = &self.user_agent
+            && let Some(key) = Self::user_agent_key(method)
+            && !params.contains_key("userAgent")
+            && !params.contains_key("useragent")
+        {
+            params.insert(key.to_string(), user_agent.clone());
+        }
+    }
+}
+
+/// Instructions shown to the worker alongside an image captcha
+///
+/// Sent as `textinstructions` and/or `imginstructions`.
+#[derive(Debug, Clone)]
+pub enum Hint {
+    Text(String),
+    Image(CaptchaImage),
+    Both { text: String, image: CaptchaImage },
+}
+
+/// Browser cookies given to reCAPTCHA workers and returned with their answers
+///
+/// Sent as the `cookies` parameter in the `name1:value1;name2:value2`
+/// format, see [`Extras::cookies`](crate::Extras::cookies). Cookies a worker
+/// returns in an extended response ([`CaptchaResult::cookies`]) can be
+/// passed to the next solve as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Cookies(BTreeMap<String, String>);
+
+impl Cookies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace a cookie
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.0.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Read cookies in the `name1:value1;name2:value2` format
+    pub fn parse(param: &str) -> Self {
+        Self(
+            param
+                .split(';')
+                .filter_map(|cookie| cookie.split_once(':'))
+                .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                .filter(|(name, _)| !name.is_empty())
+                .collect(),
+        )
+    }
+
+    /// Cookies in the `name1:value1;name2:value2` format of the API
+    pub fn to_param(&self) -> String {
+        self.0
+            .iter()
+            .map(|(name, value)| format!("{name}:{value}"))
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+}
+
+impl From<HashMap<String, String>> for Cookies {
+    fn from(cookies: HashMap<String, String>) -> Self {
+        Self(cookies.into_iter().collect())
+    }
+}
+
+impl From<Cookies> for String {
+    fn from(cookies: Cookies) -> Self {
+        cookies.to_param()
+    }
+}
+
 /// Extended response structure when json=1 is used
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtendedResponse {
     pub status: i32,
     pub request: Option<String>,
     pub code: Option<String>,
-    pub cookies: Option<HashMap<String, String>>,
+    pub cookies: Option<Cookies>,
     #[serde(flatten)]
     pub additional: HashMap<String, serde_json::Value>,
 }
 
 /// Standard captcha solution result
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CaptchaResult {
     #[serde(rename = "captchaId")]
     pub captcha_id: String,
     pub code: Option<String>,
+    /// SHA-256 of the submitted image/audio, when payload hashing is enabled
+    #[serde(
+        rename = "payloadHash",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub payload_hash: Option<String>,
+    /// Price charged for the solve, when the API reported it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<f64>,
     #[serde(flatten)]
     pub extended: Option<HashMap<String, serde_json::Value>>,
+    /// Fingerprint the task was solved for, see [`Fingerprint`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<Fingerprint>,
+    /// Attempts made to get this answer, when retries are enabled
+    #[serde(skip)]
+    pub attempts: Option<AttemptLog>,
+    /// Id of the solve attempt that produced the answer, to correlate it
+    /// with the attempt's errors and with what the answer is used for
+    #[serde(rename = "traceId", default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+}
+
+impl CaptchaResult {
+    /// Check whether the answer matches `expected` after normalization
+    ///
+    /// See [`normalize_answer`](crate::answer::normalize_answer) for the
+    /// normalization applied. Returns false when there is no answer.
+    pub fn matches(&self, expected: &str, mode: CaseMode) -> bool {
+        self.code
+            .as_deref()
+            .is_some_and(|code| answers_match(code, expected, mode))
+    }
+
+    /// Serialize for storage, tagged with [`RESULT_FORMAT_VERSION`]
+    pub fn to_persisted_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&PersistedResult {
+            version: RESULT_FORMAT_VERSION,
+            result: self.clone(),
+        })?)
+    }
+
+    /// Read a result stored by [`CaptchaResult::to_persisted_json`]
+    ///
+    /// Untagged JSON written before versioning is read with
+    /// [`CaptchaResult::from_legacy_json`].
+    pub fn from_persisted_json(json: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        match value.get("version").map(serde_json::Value::as_u64) {
+            None => Self::from_legacy_json(json),
+            Some(Some(version)) if version == u64::from(RESULT_FORMAT_VERSION) => {
+                Ok(serde_json::from_value::<PersistedResult>(value)?
+                    .result
+                    .without_empty_extended())
+            }
+            Some(version) => Err(TwoCaptchaError::Validation(format!(
+                "unsupported result format version {}",
+                version.map_or_else(|| "(invalid)".to_string(), |v| v.to_string())
+            ))),
+        }
+    }
+
+    /// Read a result serialized directly with serde by older versions
+    pub fn from_legacy_json(json: &str) -> Result<Self> {
+        let result: Self = serde_json::from_str(json)?;
+        Ok(result.without_empty_extended())
+    }
+
+    /// Flattened maps deserialize as empty rather than absent
+    fn without_empty_extended(mut self) -> Self {
+        if self.extended.as_ref().is_some_and(HashMap::is_empty) {
+            self.extended = None;
+        }
+        self
+    }
+
+    /// Cookies returned by the worker in an extended response
+    pub fn cookies(&self) -> Option<Cookies> {
+        let cookies = self.extended.as_ref()?.get("cookies")?;
+        serde_json::from_value(cookies.clone()).ok()
+    }
+
+    /// Parse the answer of a Lemin captcha
+    pub fn lemin_solution(&self) -> Result<LeminSolution> {
+        let code = self
+            .code
+            .as_deref()
+            .ok_or_else(|| TwoCaptchaError::Validation("captcha has no answer".to_string()))?;
+        Ok(serde_json::from_str(code)?)
+    }
+}
+
+/// Version of the format written by [`CaptchaResult::to_persisted_json`]
+pub const RESULT_FORMAT_VERSION: u32 = 1;
+
+/// Versioned envelope of a stored [`CaptchaResult`]
+#[derive(Serialize, Deserialize)]
+struct PersistedResult {
+    version: u32,
+    #[serde(flatten)]
+    result: CaptchaResult,
+}
+
+/// Answer of a Lemin captcha, to submit with the protected form
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeminSolution {
+    pub answer: String,
+    pub challenge_id: String,
+}
+
+/// Outcome of every attempt of a solve retried with `max_attempts` or
+/// `max_solve_attempts`
+#[derive(Debug, Clone, Default)]
+pub struct AttemptLog {
+    pub attempts: Vec<Attempt>,
+}
+
+/// A single attempt of a solve
+#[derive(Debug, Clone)]
+pub struct Attempt {
+    /// Id of the submitted captcha, also on failures after submission
+    pub captcha_id: Option<String>,
+    /// Error code of the failure (e.g. `ERROR_CAPTCHA_UNSOLVABLE`, `TIMEOUT`)
+    pub error_code: Option<String>,
+    pub elapsed: Duration,
+    /// Random UUID generated for the attempt, also set on its result
+    /// ([`CaptchaResult::trace_id`]) and on the errors recorded for it
+    pub trace_id: String,
+}
+
+/// Task object of the JSON API (v2), tagged with its `type`
+///
+/// Convert to a [`Task`](crate::Task) with [`V2Task::into_task`] to solve it
+/// with [`TwoCaptcha::solve`](crate::TwoCaptcha::solve) on either protocol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum V2Task {
+    RecaptchaV2Task(RecaptchaV2Task),
+    RecaptchaV2TaskProxyless(RecaptchaV2TaskProxyless),
+    RecaptchaV3TaskProxyless(RecaptchaV3TaskProxyless),
+    HCaptchaTask(HCaptchaTask),
+    HCaptchaTaskProxyless(HCaptchaTaskProxyless),
+    TurnstileTask(TurnstileTask),
+    TurnstileTaskProxyless(TurnstileTaskProxyless),
+    ImageToTextTask(ImageToTextTask),
+}
+
+impl V2Task {
+    /// Task object as sent to `createTask`
+    pub fn to_json(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    /// Convert to `in.php` parameters
+    pub fn into_task(self) -> Result<crate::Task> {
+        crate::Task::from_official_json(&self.to_json()?)
+    }
+}
+
+/// Proxy fields of the JSON API task types without `Proxyless`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskProxy {
+    /// `http`, `socks4` or `socks5`
+    pub proxy_type: String,
+    pub proxy_address: String,
+    pub proxy_port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_login: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_password: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecaptchaV2TaskProxyless {
+    #[serde(rename = "websiteURL")]
+    pub website_url: String,
+    pub website_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recaptcha_data_s_value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_invisible: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cookies: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_domain: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecaptchaV2Task {
+    #[serde(flatten)]
+    pub task: RecaptchaV2TaskProxyless,
+    #[serde(flatten)]
+    pub proxy: TaskProxy,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecaptchaV3TaskProxyless {
+    #[serde(rename = "websiteURL")]
+    pub website_url: String,
+    pub website_key: String,
+    pub min_score: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_action: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_enterprise: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_domain: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HCaptchaTaskProxyless {
+    #[serde(rename = "websiteURL")]
+    pub website_url: String,
+    pub website_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_invisible: Option<bool>,
+    /// Enterprise fields such as `rqdata`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enterprise_payload: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HCaptchaTask {
+    #[serde(flatten)]
+    pub task: HCaptchaTaskProxyless,
+    #[serde(flatten)]
+    pub proxy: TaskProxy,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TurnstileTaskProxyless {
+    #[serde(rename = "websiteURL")]
+    pub website_url: String,
+    pub website_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagedata: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnstileTask {
+    #[serde(flatten)]
+    pub task: TurnstileTaskProxyless,
+    #[serde(flatten)]
+    pub proxy: TaskProxy,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageToTextTask {
+    /// Base64 encoded image
+    pub body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phrase: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case: Option<bool>,
+    /// 1 digits only, 2 letters only, 3 either, 4 both
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub numeric: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub math: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub img_instructions: Option<String>,
+}
+
+/// Receipt returned when a captcha is accepted for solving
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitReceipt {
+    #[serde(rename = "captchaId")]
+    pub captcha_id: String,
+    /// SHA-256 of the submitted image/audio, when payload hashing is enabled
+    #[serde(
+        rename = "payloadHash",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub payload_hash: Option<String>,
+    /// API server that accepted the submission, which differs from the
+    /// configured one after a failover to `secondary_server`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// Extra fields returned by the API on submission (e.g. cost estimate)
+    #[serde(flatten)]
+    pub metadata: HashMap<String, serde_json::Value>,
 }
 
 /// Balance response
 #[derive(Debug, Clone)]
 pub struct Balance(pub f64);
 
+/// What the API answered to a report of a correct or incorrect answer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportOutcome {
+    /// The report was accepted
+    Recorded,
+    /// The API answered without an error but not as documented; the body
+    /// is kept for inspection
+    Unrecognized(String),
+}
+
+/// Balance with everything else the API reports alongside it
+///
+/// The JSON API may add fields such as task counts to `getBalance`; they
+/// are kept in `extra`. The legacy API only reports the balance.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BalanceDetails {
+    pub balance: f64,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
 /// Audio captcha supported languages
-#[derive(Debug, Clone)]
+///
+/// Languages added by the API later can be used through `Other` with their
+/// code until they get a variant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum AudioLanguage {
     English,
     Russian,
@@ -43,10 +529,11 @@ pub enum AudioLanguage {
     Greek,
     Portuguese,
     French,
+    Other(String),
 }
 
 impl AudioLanguage {
-    pub fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &str {
         match self {
             AudioLanguage::English => "en",
             AudioLanguage::Russian => "ru",
@@ -54,38 +541,183 @@ impl AudioLanguage {
             AudioLanguage::Greek => "el",
             AudioLanguage::Portuguese => "pt",
             AudioLanguage::French => "fr",
+            AudioLanguage::Other(code) => code,
         }
     }
 }
 
 impl std::str::FromStr for AudioLanguage {
-    type Err = ();
+    type Err = std::convert::Infallible;
 
+    /// Parse a language code, falling back to `Other` for unknown codes
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        match s {
-            "en" => Ok(AudioLanguage::English),
-            "ru" => Ok(AudioLanguage::Russian),
-            "de" => Ok(AudioLanguage::German),
-            "el" => Ok(AudioLanguage::Greek),
-            "pt" => Ok(AudioLanguage::Portuguese),
-            "fr" => Ok(AudioLanguage::French),
-            _ => Err(()),
-        }
+        Ok(match s {
+            "en" => AudioLanguage::English,
+            "ru" => AudioLanguage::Russian,
+            "de" => AudioLanguage::German,
+            "el" => AudioLanguage::Greek,
+            "pt" => AudioLanguage::Portuguese,
+            "fr" => AudioLanguage::French,
+            other => AudioLanguage::Other(other.to_string()),
+        })
     }
 }
 
 /// reCAPTCHA version
-#[derive(Debug, Clone)]
+///
+/// Versions added by the API later can be used through `Other` until they
+/// get a variant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum RecaptchaVersion {
     V2,
     V3,
+    Other(String),
 }
 
 impl RecaptchaVersion {
-    pub fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &str {
         match self {
             RecaptchaVersion::V2 => "v2",
             RecaptchaVersion::V3 => "v3",
+            RecaptchaVersion::Other(version) => version,
+        }
+    }
+}
+
+impl std::str::FromStr for RecaptchaVersion {
+    type Err = std::convert::Infallible;
+
+    /// Parse a version, falling back to `Other` for unknown versions
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "v2" => RecaptchaVersion::V2,
+            "v3" => RecaptchaVersion::V3,
+            other => RecaptchaVersion::Other(other.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lemin_solution() {
+        let result = CaptchaResult {
+            code: Some(r#"{"answer":"0xaxakx0xaxaax","challenge_id":"e0348984-92ec"}"#.to_string()),
+            ..Default::default()
+        };
+        let solution = result.lemin_solution().unwrap();
+        assert_eq!(solution.answer, "0xaxakx0xaxaax");
+        assert_eq!(solution.challenge_id, "e0348984-92ec");
+
+        assert!(CaptchaResult::default().lemin_solution().is_err());
+    }
+
+    #[test]
+    fn test_cookies_round_trip() {
+        let cookies = Cookies::parse("SID:abc; HSID : def;broken");
+        assert_eq!(cookies.get("HSID"), Some("def"));
+        assert_eq!(cookies.to_param(), "HSID:def;SID:abc");
+
+        let result = CaptchaResult {
+            extended: Some(HashMap::from([(
+                "cookies".to_string(),
+                serde_json::json!({ "SID": "abc", "HSID": "def" }),
+            )])),
+            ..Default::default()
+        };
+        assert_eq!(result.cookies(), Some(cookies.clone()));
+
+        let extras = crate::Extras::new().cookies(result.cookies().unwrap());
+        assert!(extras.contains("cookies"));
+        let extended: ExtendedResponse = serde_json::from_str(
+            r#"{"status":1,"request":"token","cookies":{"SID":"abc","HSID":"def"}}"#,
+        )
+        .unwrap();
+        assert_eq!(extended.cookies, Some(cookies));
+    }
+
+    #[test]
+    fn test_fingerprint_params() {
+        let fingerprint = Fingerprint {
+            user_agent: Some("Mozilla/5.0".to_string()),
+            ..Default::default()
+        };
+
+        let mut params = HashMap::from([("method".to_string(), "turnstile".to_string())]);
+        fingerprint.apply(&mut params);
+        assert_eq!(params["useragent"], "Mozilla/5.0");
+
+        let mut params = HashMap::from([("method".to_string(), "post".to_string())]);
+        fingerprint.apply(&mut params);
+        assert_eq!(params.len(), 1);
+    }
+
+    #[test]
+    fn test_enum_round_trip() {
+        let lang: AudioLanguage = "ja".parse().unwrap();
+        assert_eq!(lang, AudioLanguage::Other("ja".to_string()));
+        assert_eq!(lang.as_str(), "ja");
+        assert_eq!(
+            "fr".parse::<AudioLanguage>().unwrap(),
+            AudioLanguage::French
+        );
+
+        let version: RecaptchaVersion = "v3".parse().unwrap();
+        assert_eq!(version, RecaptchaVersion::V3);
+        assert_eq!("v4".parse::<RecaptchaVersion>().unwrap().as_str(), "v4");
+    }
+
+    /// Payloads from the JSON API documentation
+    const V2_FIXTURES: &[&str] = &[
+        r#"{"type":"RecaptchaV2TaskProxyless","websiteURL":"https://2captcha.com/demo/recaptcha-v2","websiteKey":"6LfD3PIbAAAAAJs_eEHvoOl75_83eXSqpPSRFJ_u","isInvisible":false}"#,
+        r#"{"type":"RecaptchaV2Task","websiteURL":"https://2captcha.com/demo/recaptcha-v2","websiteKey":"6LfD3PIbAAAAAJs_eEHvoOl75_83eXSqpPSRFJ_u","proxyType":"http","proxyAddress":"1.2.3.4","proxyPort":8080,"proxyLogin":"user23","proxyPassword":"p4$w0rd"}"#,
+        r#"{"type":"RecaptchaV3TaskProxyless","websiteURL":"https://2captcha.com/demo/recaptcha-v3","websiteKey":"6Lcyqq8oAAAAAJE7eVJ3aZp_hnJcI6LgGdYD8lge","minScore":0.9,"pageAction":"demo_action","isEnterprise":false}"#,
+        r#"{"type":"HCaptchaTaskProxyless","websiteURL":"https://2captcha.com/demo/hcaptcha","websiteKey":"f7de0da3-3303-44e8-ab48-fa32ff8ccc7b","isInvisible":false,"enterprisePayload":{"rqdata":"rq"}}"#,
+        r#"{"type":"TurnstileTaskProxyless","websiteURL":"https://2captcha.com/demo/cloudflare-turnstile","websiteKey":"3x00000000000000000000FF"}"#,
+        r#"{"type":"TurnstileTask","websiteURL":"https://2captcha.com/demo/cloudflare-turnstile","websiteKey":"3x00000000000000000000FF","action":"managed","userAgent":"Mozilla/5.0","proxyType":"socks5","proxyAddress":"1.2.3.4","proxyPort":1080}"#,
+        r#"{"type":"ImageToTextTask","body":"R0lGODlhAQABAIAAAP///wAAACwAAAAAAQABAAACAkQBADs=","phrase":false,"case":true,"numeric":0,"math":false,"minLength":1,"maxLength":5,"comment":"enter the text you see on the image"}"#,
+    ];
+
+    #[test]
+    fn test_v2_task_round_trip() {
+        for fixture in V2_FIXTURES {
+            let expected: serde_json::Value = serde_json::from_str(fixture).unwrap();
+            let task: V2Task = serde_json::from_value(expected.clone()).unwrap();
+            assert_eq!(task.to_json().unwrap(), expected, "{fixture}");
         }
+
+        let task = V2Task::TurnstileTaskProxyless(TurnstileTaskProxyless {
+            website_url: "https://example.com".to_string(),
+            website_key: "0x4AAAAAAAChNiVJM_WtShFf".to_string(),
+            ..Default::default()
+        });
+        let params = task.into_task().unwrap().into_params();
+        assert_eq!(params["method"], "turnstile");
+        assert_eq!(params["sitekey"], "0x4AAAAAAAChNiVJM_WtShFf");
+    }
+
+    #[test]
+    fn test_persisted_json() {
+        let result = CaptchaResult {
+            captcha_id: "123".to_string(),
+            code: Some("answer".to_string()),
+            ..Default::default()
+        };
+        let json = result.to_persisted_json().unwrap();
+        assert!(json.contains(r#""version":1"#));
+
+        let restored = CaptchaResult::from_persisted_json(&json).unwrap();
+        assert_eq!(restored.code.as_deref(), Some("answer"));
+        assert!(restored.extended.is_none());
+
+        let legacy = r#"{"captchaId":"123","code":"answer","cost":"0.00299"}"#;
+        let restored = CaptchaResult::from_persisted_json(legacy).unwrap();
+        assert_eq!(restored.captcha_id, "123");
+        assert_eq!(restored.extended.unwrap()["cost"], "0.00299");
+
+        assert!(CaptchaResult::from_persisted_json(r#"{"version":99}"#).is_err());
     }
 }
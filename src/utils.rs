@@ -1,65 +1,202 @@
 use crate::error::{Result, TwoCaptchaError};
+use crate::files::FileAccess;
+use crate::types::{CaptchaImage, Hint};
 use base64::Engine;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::fmt;
 use std::path::Path;
 
+/// Parameter aliases accepted by the solver and their `in.php` names
+pub const PARAM_ALIASES: &[(&str, &str)] = &[
+    ("caseSensitive", "regsense"),
+    ("minLen", "min_len"),
+    ("maxLen", "max_len"),
+    ("minLength", "min_len"),
+    ("maxLength", "max_len"),
+    ("hintText", "textinstructions"),
+    ("hintImg", "imginstructions"),
+    ("url", "pageurl"),
+    ("score", "min_score"),
+    ("text", "textcaptcha"),
+    ("rows", "recaptcharows"),
+    ("cols", "recaptchacols"),
+    ("previousId", "previousID"),
+    ("canSkip", "can_no_answer"),
+    ("apiServer", "api_server"),
+    ("softId", "soft_id"),
+    ("callback", "pingback"),
+    ("datas", "data-s"),
+];
+
+/// Two spellings of the same parameter given with different values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParamConflict {
+    pub alias: &'static str,
+    pub canonical: &'static str,
+}
+
+impl fmt::Display for ParamConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` conflicts with another value given for `{}`",
+            self.alias, self.canonical
+        )
+    }
+}
+
 /// Utility functions for file handling and encoding
 pub struct Utils;
 
 impl Utils {
     /// Determine method for file input (base64 or file path)
-    pub async fn get_method(file: &str) -> Result<HashMap<String, String>> {
+    pub async fn get_method(file: &str, fs: &FileAccess) -> Result<HashMap<String, String>> {
         if file.is_empty() {
             return Err(TwoCaptchaError::Validation("File required".to_string()));
         }
 
-        // Check if it's a base64 string (no dots and length > 50)
-        if !file.contains('.') && file.len() > 50 {
-            let mut result = HashMap::new();
-            result.insert("method".to_string(), "base64".to_string());
-            result.insert("body".to_string(), file.to_string());
-            return Ok(result);
+        if Self::looks_like_base64(file) {
+            return Ok(Self::base64_params(file));
         }
 
         // Check if it's a URL
         if file.starts_with("http") {
-            let response = reqwest::get(file).await?;
-            if response.status() != 200 {
-                return Err(TwoCaptchaError::Validation(format!(
-                    "File could not be downloaded from url: {file}"
-                )));
+            return Self::url_params(file).await;
+        }
+
+        Self::path_params(Path::new(file), fs).await
+    }
+
+    /// Whether an untyped file input is taken for base64 data rather than a
+    /// path or URL (no dots and longer than 50 characters)
+    pub(crate) fn looks_like_base64(file: &str) -> bool {
+        !file.contains('.') && file.len() > 50
+    }
+
+    /// Build request parameters for an image input
+    ///
+    /// When `detect_base64` is false, [`CaptchaImage::Auto`] inputs are only
+    /// ever treated as a URL or a file path.
+    pub async fn image_params(
+        image: CaptchaImage,
+        detect_base64: bool,
+        fs: &FileAccess,
+    ) -> Result<HashMap<String, String>> {
+        match image {
+            CaptchaImage::Auto(file) if detect_base64 => Self::get_method(&file, fs).await,
+            CaptchaImage::Auto(file) if file.is_empty() => {
+                Err(TwoCaptchaError::Validation("File required".to_string()))
+            }
+            CaptchaImage::Auto(file) if file.starts_with("http") => Self::url_params(&file).await,
+            CaptchaImage::Auto(file) => Self::path_params(Path::new(&file), fs).await,
+            CaptchaImage::Base64(body) if body.is_empty() => {
+                Err(TwoCaptchaError::Validation("File required".to_string()))
             }
-            let content = response.bytes().await?;
-            let encoded = base64::engine::general_purpose::STANDARD.encode(&content);
+            CaptchaImage::Base64(body) => Ok(Self::base64_params(&body)),
+            CaptchaImage::Path(path) => Self::path_params(&path, fs).await,
+            CaptchaImage::Url(url) => Self::url_params(&url).await,
+        }
+    }
+
+    /// Build `textinstructions`/`imginstructions` parameters for a hint
+    ///
+    /// Image hints are always sent inline as base64.
+    pub async fn hint_params(
+        hint: Hint,
+        detect_base64: bool,
+        fs: &FileAccess,
+    ) -> Result<HashMap<String, String>> {
+        let (text, image) = match hint {
+            Hint::Text(text) => (Some(text), None),
+            Hint::Image(image) => (None, Some(image)),
+            Hint::Both { text, image } => (Some(text), Some(image)),
+        };
 
-            let mut result = HashMap::new();
-            result.insert("method".to_string(), "base64".to_string());
-            result.insert("body".to_string(), encoded);
-            return Ok(result);
+        let mut result = HashMap::new();
+        if let Some(text) = text {
+            result.insert("textinstructions".to_string(), text);
         }
+        if let Some(image) = image {
+            let body = Self::image_body(image, detect_base64, fs).await?;
+            result.insert("imginstructions".to_string(), body);
+        }
+
+        Ok(result)
+    }
 
+    /// Resolve an image input to base64 data, reading files inline
+    pub async fn image_body(
+        image: CaptchaImage,
+        detect_base64: bool,
+        fs: &FileAccess,
+    ) -> Result<String> {
+        let mut image_params = Self::image_params(image, detect_base64, fs).await?;
+        match (image_params.remove("body"), image_params.remove("file")) {
+            (Some(body), _) => Ok(body),
+            (None, Some(file)) => {
+                let content = fs.read(&file).await?;
+                Ok(base64::engine::general_purpose::STANDARD.encode(&content))
+            }
+            (None, None) => Err(TwoCaptchaError::Validation(
+                "Image could not be loaded".to_string(),
+            )),
+        }
+    }
+
+    fn base64_params(body: &str) -> HashMap<String, String> {
+        let mut result = HashMap::new();
+        result.insert("method".to_string(), "base64".to_string());
+        result.insert("body".to_string(), body.to_string());
+        result
+    }
+
+    async fn url_params(url: &str) -> Result<HashMap<String, String>> {
+        let response = reqwest::get(url).await?;
+        if response.status() != 200 {
+            return Err(TwoCaptchaError::Validation(format!(
+                "File could not be downloaded from url: {url}"
+            )));
+        }
+        let content = response.bytes().await?;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&content);
+
+        Ok(Self::base64_params(&encoded))
+    }
+
+    async fn path_params(path: &Path, fs: &FileAccess) -> Result<HashMap<String, String>> {
         // Check if file exists
-        if !Path::new(file).exists() {
+        if !fs.exists(path).await {
             return Err(TwoCaptchaError::Validation(format!(
-                "File not found: {file}"
+                "File not found: {}",
+                path.display()
             )));
         }
 
         let mut result = HashMap::new();
         result.insert("method".to_string(), "post".to_string());
-        result.insert("file".to_string(), file.to_string());
+        result.insert("file".to_string(), path.to_string_lossy().into_owned());
         Ok(result)
     }
 
     /// Extract and validate multiple files
-    pub fn extract_files(files: Vec<String>, max_files: usize) -> Result<HashMap<String, String>> {
+    pub async fn extract_files(
+        files: Vec<String>,
+        max_files: usize,
+        fs: &FileAccess,
+    ) -> Result<HashMap<String, String>> {
         if files.len() > max_files {
             return Err(TwoCaptchaError::Validation(format!(
                 "Too many files (max: {max_files})"
             )));
         }
 
-        let not_exists: Vec<&String> = files.iter().filter(|f| !Path::new(f).exists()).collect();
+        let mut not_exists = Vec::new();
+        for file in &files {
+            if !fs.exists(file).await {
+                not_exists.push(file);
+            }
+        }
 
         if !not_exists.is_empty() {
             return Err(TwoCaptchaError::Validation(format!(
@@ -75,30 +212,83 @@ impl Utils {
         Ok(result)
     }
 
+    /// Check that `value` is an absolute http(s) URL
+    pub fn check_url(name: &str, value: &str) -> Result<()> {
+        let url = url::Url::parse(value)?;
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+            return Err(TwoCaptchaError::Validation(format!(
+                "{name} must be an http(s) URL, got `{value}`"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Normalize a page URL before submission: the scheme is required,
+    /// international host names are converted to punycode and the fragment,
+    /// which never reaches the server, is dropped. URLs that need none of
+    /// this are returned unchanged
+    pub fn normalize_page_url(name: &str, value: &str) -> Result<String> {
+        let value = value.trim();
+        let mut url = url::Url::parse(value).map_err(|_| {
+            TwoCaptchaError::Validation(format!(
+                "{name} must be an absolute http(s) URL including the scheme, got `{value}`"
+            ))
+        })?;
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+            return Err(TwoCaptchaError::Validation(format!(
+                "{name} must be an http(s) URL, got `{value}`"
+            )));
+        }
+
+        if url.fragment().is_none() && value.is_ascii() {
+            return Ok(value.to_string());
+        }
+        url.set_fragment(None);
+        Ok(url.into())
+    }
+
+    /// Check that `value` is a host name, optionally given as an http(s) URL
+    pub fn check_api_server(name: &str, value: &str) -> Result<()> {
+        if value.contains("://") {
+            return Self::check_url(name, value);
+        }
+
+        match url::Url::parse(&format!("https://{value}")) {
+            Ok(url) if url.host_str().is_some() && url.path() == "/" && !value.contains('/') => {
+                Ok(())
+            }
+            _ => Err(TwoCaptchaError::Validation(format!(
+                "{name} must be a host name or URL, got `{value}`"
+            ))),
+        }
+    }
+
     /// Check and process hint image
     pub async fn check_hint_img(
         mut params: HashMap<String, String>,
         mut files: HashMap<String, String>,
+        detect_base64: bool,
+        fs: &FileAccess,
     ) -> Result<(HashMap<String, String>, HashMap<String, String>)> {
         if let Some(hint) = params.remove("imginstructions") {
             // Check if it's a base64 string
-            if !hint.contains('.') && hint.len() > 50 {
+            if detect_base64 && Self::looks_like_base64(&hint) {
                 params.insert("imginstructions".to_string(), hint);
                 return Ok((params, files));
             }
 
             // Check if file exists
-            if !Path::new(&hint).exists() {
+            if !fs.exists(&hint).await {
                 return Err(TwoCaptchaError::Validation(format!(
                     "File not found: {hint}"
                 )));
             }
 
             // If files is empty, add the main file
-            if files.is_empty() {
-                if let Some(file) = params.remove("file") {
-                    files.insert("file".to_string(), file);
-                }
+            if files.is_empty()
+                && let Some(file) = params.remove("file")
+            {
+                files.insert("file".to_string(), file);
             }
 
             files.insert("imginstructions".to_string(), hint);
@@ -107,56 +297,105 @@ impl Utils {
         Ok((params, files))
     }
 
+    /// Compute the SHA-256 (hex) of the image/audio payload of a request
+    ///
+    /// Base64 bodies are decoded first, so the same image hashes identically
+    /// whether it was sent inline or as a file. Requests with several files
+    /// hash all of them in key order. Returns `None` for payload-less tasks.
+    pub async fn payload_hash(
+        params: &HashMap<String, String>,
+        fs: &FileAccess,
+    ) -> Result<Option<String>> {
+        let mut keys: Vec<&String> = params
+            .keys()
+            .filter(|k| *k == "body" || *k == "file" || k.starts_with("file_"))
+            .collect();
+        if keys.is_empty() {
+            return Ok(None);
+        }
+        keys.sort();
+
+        let mut hasher = Sha256::new();
+        for key in keys {
+            let value = &params[key];
+            if key == "body" {
+                hasher.update(base64::engine::general_purpose::STANDARD.decode(value)?);
+            } else {
+                hasher.update(fs.read(value).await?);
+            }
+        }
+
+        let hash = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        Ok(Some(hash))
+    }
+
+    /// Canonical `in.php` name of a parameter, resolving aliases
+    pub fn canonical_param(name: &str) -> &str {
+        PARAM_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map_or(name, |(_, canonical)| canonical)
+    }
+
+    /// Aliases accepted for a canonical `in.php` parameter
+    pub fn param_aliases(canonical: &str) -> Vec<&'static str> {
+        PARAM_ALIASES
+            .iter()
+            .filter(|(_, name)| *name == canonical)
+            .map(|(alias, _)| *alias)
+            .collect()
+    }
+
     /// Rename parameters to match 2captcha API expectations
-    pub fn rename_params(mut params: HashMap<String, String>) -> HashMap<String, String> {
-        let replacements = [
-            ("caseSensitive", "regsense"),
-            ("minLen", "min_len"),
-            ("maxLen", "max_len"),
-            ("minLength", "min_len"),
-            ("maxLength", "max_len"),
-            ("hintText", "textinstructions"),
-            ("hintImg", "imginstructions"),
-            ("url", "pageurl"),
-            ("score", "min_score"),
-            ("text", "textcaptcha"),
-            ("rows", "recaptcharows"),
-            ("cols", "recaptchacols"),
-            ("previousId", "previousID"),
-            ("canSkip", "can_no_answer"),
-            ("apiServer", "api_server"),
-            ("softId", "soft_id"),
-            ("callback", "pingback"),
-            ("datas", "data-s"),
-        ];
+    pub fn rename_params(params: HashMap<String, String>) -> HashMap<String, String> {
+        Self::rename_params_checked(params).0
+    }
 
+    /// Rename parameters, also returning the aliases that clashed with
+    /// another spelling of the same parameter
+    ///
+    /// The canonical name wins over its aliases, as in [`Utils::rename_params`].
+    pub fn rename_params_checked(
+        mut params: HashMap<String, String>,
+    ) -> (HashMap<String, String>, Vec<ParamConflict>) {
         let mut new_params = HashMap::new();
+        let mut conflicts = Vec::new();
 
         // Apply replacements
-        for (old_key, new_key) in &replacements {
-            if let Some(value) = params.remove(*old_key) {
-                new_params.insert(new_key.to_string(), value);
+        for (alias, canonical) in PARAM_ALIASES {
+            if let Some(value) = params.remove(*alias) {
+                let other = params
+                    .get(*canonical)
+                    .or_else(|| new_params.get(*canonical));
+                if other.is_some_and(|other| *other != value) {
+                    conflicts.push(ParamConflict { alias, canonical });
+                }
+                new_params.insert(canonical.to_string(), value);
             }
         }
 
         // Handle proxy separately
         if let Some(proxy_str) = params.remove("proxy") {
             // Parse proxy format: {"type": "HTTPS", "uri": "login:password@IP_address:PORT"}
-            if let Ok(proxy_data) = serde_json::from_str::<serde_json::Value>(&proxy_str) {
-                if let (Some(uri), Some(proxy_type)) = (
+            if let Ok(proxy_data) = serde_json::from_str::<serde_json::Value>(&proxy_str)
+                && let (Some(uri), Some(proxy_type)) = (
                     proxy_data.get("uri").and_then(|v| v.as_str()),
                     proxy_data.get("type").and_then(|v| v.as_str()),
-                ) {
-                    new_params.insert("proxy".to_string(), uri.to_string());
-                    new_params.insert("proxytype".to_string(), proxy_type.to_string());
-                }
+                )
+            {
+                new_params.insert("proxy".to_string(), uri.to_string());
+                new_params.insert("proxytype".to_string(), proxy_type.to_string());
             }
         }
 
         // Add remaining params
         new_params.extend(params);
 
-        new_params
+        (new_params, conflicts)
     }
 }
 
@@ -167,15 +406,97 @@ mod tests {
     #[tokio::test]
     async fn test_get_method_base64() {
         let base64_string = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8/5+hHgAHggJ/PchI7wAAAABJRU5ErkJggg==";
-        let result = Utils::get_method(base64_string).await.unwrap();
+        let result = Utils::get_method(base64_string, &FileAccess::default())
+            .await
+            .unwrap();
+        assert_eq!(result.get("method").unwrap(), "base64");
+        assert_eq!(result.get("body").unwrap(), base64_string);
+    }
+
+    #[tokio::test]
+    async fn test_image_params_without_detection() {
+        let base64_string = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8/5+hHgAHggJ/PchI7wAAAABJRU5ErkJggg==";
+        let result = Utils::image_params(
+            CaptchaImage::from(base64_string),
+            false,
+            &FileAccess::default(),
+        )
+        .await;
+        assert!(result.is_err());
+
+        let result = Utils::image_params(
+            CaptchaImage::base64(base64_string),
+            false,
+            &FileAccess::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result.get("method").unwrap(), "base64");
         assert_eq!(result.get("body").unwrap(), base64_string);
     }
 
     #[test]
-    fn test_extract_files() {
+    fn test_check_url() {
+        assert!(Utils::check_url("url", "https://2captcha.com/demo").is_ok());
+        assert!(Utils::check_url("url", "ftp://2captcha.com").is_err());
+        assert!(Utils::check_url("url", "2captcha.com/demo").is_err());
+
+        assert_eq!(
+            Utils::normalize_page_url("pageurl", "https://ace.fusionist.io").unwrap(),
+            "https://ace.fusionist.io"
+        );
+        assert_eq!(
+            Utils::normalize_page_url("pageurl", " https://bücher.example/a?q=1#top").unwrap(),
+            "https://xn--bcher-kva.example/a?q=1"
+        );
+        assert!(Utils::normalize_page_url("pageurl", "2captcha.com/demo").is_err());
+
+        assert!(Utils::check_api_server("api_server", "api.leminnow.com").is_ok());
+        assert!(Utils::check_api_server("api_server", "https://cap.aisecurius.com").is_ok());
+        assert!(Utils::check_api_server("api_server", "api.leminnow.com/path").is_err());
+        assert!(Utils::check_api_server("api_server", "bad host").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_payload_hash() {
+        let mut params = HashMap::new();
+        params.insert("method".to_string(), "base64".to_string());
+        params.insert("body".to_string(), "aGVsbG8=".to_string());
+
+        let hash = Utils::payload_hash(&params, &FileAccess::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            hash.as_deref(),
+            Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+        );
+
+        params.remove("body");
+        assert_eq!(
+            Utils::payload_hash(&params, &FileAccess::default())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hint_params() {
+        let hint = Hint::Both {
+            text: "Click on the cats".to_string(),
+            image: CaptchaImage::base64("aGVsbG8="),
+        };
+        let result = Utils::hint_params(hint, false, &FileAccess::default())
+            .await
+            .unwrap();
+        assert_eq!(result.get("textinstructions").unwrap(), "Click on the cats");
+        assert_eq!(result.get("imginstructions").unwrap(), "aGVsbG8=");
+    }
+
+    #[tokio::test]
+    async fn test_extract_files() {
         let files = vec!["test1.txt".to_string(), "test2.txt".to_string()];
-        let result = Utils::extract_files(files, 5);
+        let result = Utils::extract_files(files, 5, &FileAccess::default()).await;
         // This will fail because files don't exist, but tests the validation logic
         assert!(result.is_err());
     }
@@ -192,4 +513,27 @@ mod tests {
         assert_eq!(result.get("min_len").unwrap(), "5");
         assert_eq!(result.get("pageurl").unwrap(), "https://example.com");
     }
+
+    #[test]
+    fn test_rename_params_conflicts() {
+        let params = HashMap::from([
+            ("url".to_string(), "https://a.example".to_string()),
+            ("pageurl".to_string(), "https://b.example".to_string()),
+            ("minLen".to_string(), "4".to_string()),
+            ("min_len".to_string(), "4".to_string()),
+        ]);
+        let (result, conflicts) = Utils::rename_params_checked(params);
+        assert_eq!(result["pageurl"], "https://b.example");
+        assert_eq!(
+            conflicts,
+            [ParamConflict {
+                alias: "url",
+                canonical: "pageurl"
+            }]
+        );
+
+        assert_eq!(Utils::canonical_param("hintText"), "textinstructions");
+        assert_eq!(Utils::canonical_param("sitekey"), "sitekey");
+        assert_eq!(Utils::param_aliases("max_len"), ["maxLen", "maxLength"]);
+    }
 }
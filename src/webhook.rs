@@ -0,0 +1,151 @@
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use reqwest::header::CONTENT_TYPE;
+use sha2::Sha256;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::backoff::{self, JitterSource, RandomJitter};
+use crate::error::{Result, TwoCaptchaError};
+use crate::types::CaptchaResult;
+
+/// Header carrying the signature of a delivery
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// Endpoint solved results are forwarded to
+///
+/// Every result of [`TwoCaptcha::solve`](crate::TwoCaptcha::solve) (and the
+/// methods built on it) is POSTed in the background as the JSON of
+/// [`CaptchaResult::to_persisted_json`]. With a secret, the body is signed
+/// with HMAC-SHA256, sent as `X-Signature: sha256=<hex>`.
+#[derive(Clone)]
+pub struct Webhook {
+    pub url: String,
+    pub secret: Option<String>,
+    /// Deliveries attempted before giving up (default: 3)
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after each one (default: 1s)
+    ///
+    /// Each delay is shortened by a random factor of up to half.
+    pub retry_delay: Duration,
+    client: Client,
+    jitter: Arc<dyn JitterSource>,
+}
+
+impl Webhook {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            secret: None,
+            max_attempts: 3,
+            retry_delay: Duration::from_secs(1),
+            client: Client::new(),
+            jitter: Arc::new(RandomJitter::default()),
+        }
+    }
+
+    /// Draw the jitter of retry delays from `source`, e.g. a
+    /// [`SeededJitter`](crate::backoff::SeededJitter) in tests
+    pub fn with_jitter(mut self, source: impl JitterSource + 'static) -> Self {
+        self.jitter = Arc::new(source);
+        self
+    }
+
+    /// Sign deliveries with `secret`
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// POST a result, retrying failed deliveries
+    pub(crate) async fn deliver(&self, result: &CaptchaResult) -> Result<()> {
+        let body = result.to_persisted_json()?;
+        let signature = self
+            .secret
+            .as_deref()
+            .map(|secret| signature(secret, &body));
+
+        let mut delay = self.retry_delay;
+        let mut attempt = 1;
+        loop {
+            let mut request = self
+                .client
+                .post(&self.url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            if let Some(signature) = &signature {
+                request = request.header(SIGNATURE_HEADER, signature);
+            }
+
+            let error = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => TwoCaptchaError::Network {
+                    status: Some(response.status()),
+                    body_snippet: String::new(),
+                    headers: crate::api::headers_of_interest(response.headers()),
+                    source: None,
+                },
+                Err(e) => e.into(),
+            };
+            if attempt >= self.max_attempts {
+                return Err(error);
+            }
+
+            tokio::time::sleep(backoff::jittered(delay, self.jitter.as_ref())).await;
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+impl fmt::Debug for Webhook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Webhook")
+            .field("url", &self.url)
+            .field("secret", &self.secret.as_ref().map(|_| "****"))
+            .field("max_attempts", &self.max_attempts)
+            .field("retry_delay", &self.retry_delay)
+            .field("jitter", &self.jitter)
+            .finish()
+    }
+}
+
+/// `sha256=<hex HMAC-SHA256 of body>`
+pub(crate) fn signature(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body.as_bytes());
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("sha256={hex}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backoff::SeededJitter;
+
+    #[test]
+    fn test_signature() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            signature("Jefe", "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deliver_gives_up() {
+        let webhook = Webhook {
+            max_attempts: 2,
+            retry_delay: Duration::from_millis(1),
+            ..Webhook::new("http://127.0.0.1:9/hook").with_jitter(SeededJitter::new(1))
+        };
+        assert!(webhook.deliver(&CaptchaResult::default()).await.is_err());
+    }
+}
@@ -0,0 +1,527 @@
+//! Wire format tests
+//!
+//! Every solver method is run against a local mock of the 2captcha API and
+//! the parameters it posts to `in.php` are compared with a golden file in
+//! `tests/golden/`. The golden files are snapshots of what this crate
+//! sent when they were last written, not captures of the official Python
+//! client: a mismatch means a parameter was renamed, dropped or added
+//! since, and the diff of a rewritten file should be reviewed against the
+//! API documentation.
+//!
+//! Uploaded files are recorded as `{"upload": "<base64 content>"}`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to rewrite the golden files after an
+//! intentional change.
+
+use base64::Engine;
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use twocaptcha::{
+    ApiVersion, AudioLanguage, CaptchaImage, Extras, Hint, Proxy, RecaptchaVersion, ReportOutcome,
+    TwoCaptcha, TwoCaptchaConfig,
+};
+
+const IMAGE: &str = "iVBORw0KGgoAAAANSUhEUgAAAAgAAAAICAAAAADhZOFXAAAAE0lEQVR42mP4zwCBDAz/0SBJIgB8BB/hHeoAAQAAAABJRU5ErkJggg==";
+
+/// Minimal HTTP/1.1 server answering like the 2captcha API
+struct MockServer {
+    url: String,
+    submissions: Arc<Mutex<Vec<Map<String, Value>>>>,
+}
+
+impl MockServer {
+    async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let submissions = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = Arc::clone(&submissions);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let recorded = Arc::clone(&recorded);
+                tokio::spawn(async move { Self::handle(stream, recorded).await });
+            }
+        });
+
+        Self { url, submissions }
+    }
+
+    async fn handle(mut stream: TcpStream, recorded: Arc<Mutex<Vec<Map<String, Value>>>>) {
+        let mut data = Vec::new();
+        let mut buf = [0u8; 8192];
+        let header_end = loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            if n == 0 {
+                return;
+            }
+            data.extend_from_slice(&buf[..n]);
+            if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+
+        let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+        let content_length = head
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().ok())?
+            })
+            .unwrap_or(0);
+        while data.len() < header_end + content_length {
+            let n = stream.read(&mut buf).await.unwrap();
+            data.extend_from_slice(&buf[..n]);
+        }
+
+        let path = head.split_whitespace().nth(1).unwrap_or_default();
+        let body = if path == "/createTask" {
+            let request: Value = serde_json::from_slice(&data[header_end..]).unwrap();
+            let task = request["task"].as_object().cloned().unwrap();
+            recorded.lock().unwrap().push(task);
+            r#"{"errorId":0,"taskId":1234567890}"#
+        } else if path == "/reportIncorrect" {
+            r#"{"errorId":0,"status":"success"}"#
+        } else if path == "/getBalance" {
+            r#"{"errorId":0,"balance":12.5,"tasksInQueue":2}"#
+        } else if path == "/getTaskResult" {
+            r#"{"errorId":0,"status":"ready","solution":{"token":"answer"},"cost":"0.00145"}"#
+        } else if path.starts_with("/in.php") {
+            let body = &data[header_end..];
+            let form = match Self::boundary(&head) {
+                Some(boundary) => Self::multipart_fields(body, &boundary),
+                None => url::form_urlencoded::parse(body)
+                    .map(|(k, v)| (k.into_owned(), Value::String(v.into_owned())))
+                    .collect(),
+            };
+            recorded.lock().unwrap().push(form);
+            "OK|1234567890"
+        } else if path.contains("action=get2") {
+            "OK|answer|0.00099"
+        } else {
+            "OK|answer"
+        };
+
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+    }
+
+    /// Boundary of a `multipart/form-data` request, from its headers
+    fn boundary(head: &str) -> Option<String> {
+        head.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            if !name.eq_ignore_ascii_case("content-type") {
+                return None;
+            }
+            let (_, boundary) = value.trim().split_once("multipart/form-data; boundary=")?;
+            Some(boundary.trim_matches('"').to_string())
+        })
+    }
+
+    /// Fields of a `multipart/form-data` body, uploads encoded as base64
+    fn multipart_fields(body: &[u8], boundary: &str) -> Map<String, Value> {
+        let delimiter = format!("\r\n--{boundary}");
+        let mut body = [b"\r\n".as_slice(), body].concat();
+        let mut fields = Map::new();
+        while let Some(start) = find(&body, delimiter.as_bytes()) {
+            body.drain(..start + delimiter.len());
+            if body.starts_with(b"--") {
+                break;
+            }
+            let Some(end) = find(&body, delimiter.as_bytes()) else {
+                break;
+            };
+            let part = &body[..end];
+            let header_end = find(part, b"\r\n\r\n").unwrap() + 4;
+            let headers = String::from_utf8_lossy(&part[..header_end]);
+            let content = &part[header_end..];
+            let name = headers
+                .split("name=\"")
+                .nth(1)
+                .and_then(|rest| rest.split('"').next())
+                .unwrap()
+                .to_string();
+            let value = if headers.contains("filename=") {
+                json!({ "upload": base64::engine::general_purpose::STANDARD.encode(content) })
+            } else {
+                Value::String(String::from_utf8_lossy(content).into_owned())
+            };
+            fields.insert(name, value);
+        }
+        fields
+    }
+
+    fn solver(&self) -> TwoCaptcha {
+        let config = TwoCaptchaConfig {
+            server: Some(self.url.clone()),
+            ..Default::default()
+        };
+        TwoCaptcha::new("API_KEY".to_string(), config)
+    }
+
+    fn v2_solver(&self) -> TwoCaptcha {
+        let config = TwoCaptchaConfig {
+            server: Some(self.url.clone()),
+            api_version: Some(ApiVersion::V2),
+            ..Default::default()
+        };
+        TwoCaptcha::new("API_KEY".to_string(), config)
+    }
+
+    fn last_submission(&self) -> Map<String, Value> {
+        self.submissions.lock().unwrap().last().cloned().unwrap()
+    }
+}
+
+/// Compare a captured submission with `tests/golden/<name>.json`
+fn assert_golden(name: &str, submission: Map<String, Value>) {
+    let path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "golden",
+        &format!("{name}.json"),
+    ]
+    .iter()
+    .collect();
+    let actual = Value::Object(submission);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        let json = serde_json::to_string_pretty(&actual).unwrap();
+        std::fs::write(&path, json + "\n").unwrap();
+        return;
+    }
+
+    let expected: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(actual, expected, "wire format of {name} changed");
+}
+
+/// Position of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Directory holding image files written by one test, removed on drop
+struct ImageDir(PathBuf);
+
+impl ImageDir {
+    fn new(test: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("twocaptcha_wire_{}_{test}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    /// Write [`IMAGE`] to `name` in the directory
+    fn image(&self, name: &str) -> PathBuf {
+        let path = self.0.join(name);
+        let content = base64::engine::general_purpose::STANDARD
+            .decode(IMAGE)
+            .unwrap();
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+}
+
+impl Drop for ImageDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn params(pairs: &[(&str, &str)]) -> Option<Extras> {
+    let params: HashMap<String, String> = pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    Some(Extras::try_from(params).unwrap())
+}
+
+macro_rules! wire_test {
+    ($name:ident, |$solver:ident| $call:expr) => {
+        #[tokio::test]
+        async fn $name() {
+            let server = MockServer::start().await;
+            let $solver = server.solver();
+            let result = $call.await.unwrap();
+            assert_eq!(result.captcha_id, "1234567890");
+            assert_golden(stringify!($name), server.last_submission());
+        }
+    };
+}
+
+wire_test!(normal, |solver| solver.normal(
+    CaptchaImage::base64(IMAGE),
+    params(&[("caseSensitive", "1"), ("minLen", "4"), ("lang", "en")])
+));
+
+wire_test!(audio, |solver| solver.audio(
+    IMAGE,
+    AudioLanguage::English,
+    None
+));
+
+wire_test!(rotate_base64, |solver| solver
+    .rotate(CaptchaImage::base64(IMAGE), params(&[("angle", "40")])));
+
+#[tokio::test]
+async fn rotate() {
+    let server = MockServer::start().await;
+    let images = ImageDir::new("rotate");
+    let result = server
+        .solver()
+        .rotate(CaptchaImage::path(images.image("rotate.png")), None)
+        .await
+        .unwrap();
+
+    assert_eq!(result.captcha_id, "1234567890");
+    assert_golden("rotate", server.last_submission());
+}
+
+#[tokio::test]
+async fn rotate_multiple() {
+    let server = MockServer::start().await;
+    let images = ImageDir::new("rotate_multiple");
+    let files = ["first.png", "second.png"]
+        .map(|name| images.image(name).display().to_string())
+        .to_vec();
+    let result = server
+        .solver()
+        .rotate_multiple(files, params(&[("angle", "40")]))
+        .await
+        .unwrap();
+
+    assert_eq!(result.captcha_id, "1234567890");
+    assert_golden("rotate_multiple", server.last_submission());
+}
+
+wire_test!(text, |solver| solver
+    .text("If tomorrow is Saturday, what day is today?", None));
+
+wire_test!(recaptcha_v2, |solver| solver.recaptcha(
+    "6Le-wvkSAAAAAPBMRTvw0Q4Muexq9bi0DJwx_mJ-",
+    "https://www.google.com/recaptcha/api2/demo",
+    Some(RecaptchaVersion::V2),
+    Some(false),
+    params(&[("invisible", "1")])
+));
+
+wire_test!(recaptcha_v3, |solver| solver.recaptcha(
+    "6LfB5_IbAAAAAMCtsjEHEHKqcB9iQocwwxTiihJu",
+    "https://2captcha.com/demo/recaptcha-v3",
+    Some(RecaptchaVersion::V3),
+    Some(false),
+    params(&[("action", "verify"), ("score", "0.3")])
+));
+
+wire_test!(recaptcha_enterprise, |solver| solver.recaptcha(
+    "6Lf26sUnAAAAAIKLuWNYgRsFUfmI-3Lex3xT5N-s",
+    "https://2captcha.com/demo/recaptcha-v2-enterprise",
+    Some(RecaptchaVersion::V2),
+    Some(true),
+    params(&[("datas", "S_VALUE")])
+));
+
+wire_test!(funcaptcha, |solver| solver.funcaptcha(
+    "69A21A01-CC7B-B9C6-0F9A-E7FA06677FFC",
+    "https://client-demo.arkoselabs.com/solo-animals",
+    params(&[("surl", "https://client-api.arkoselabs.com")])
+));
+
+wire_test!(geetest, |solver| solver.geetest(
+    "f3bf6dbdcf7886856696502e1d55e00c",
+    "12345678abc90123d45678ef90123a456b",
+    "https://2captcha.com/demo/geetest",
+    params(&[("apiServer", "api-na.geetest.com")])
+));
+
+wire_test!(geetest_v4, |solver| solver.geetest_v4(
+    "e392e1d7fd421dc63325744d5a2b9c73",
+    "https://2captcha.com/demo/geetest-v4",
+    None
+));
+
+wire_test!(hcaptcha, |solver| solver.hcaptcha(
+    "c0421d06-b92e-47fc-ab9a-5caa43c04538",
+    "https://2captcha.com/demo/hcaptcha",
+    None
+));
+
+wire_test!(keycaptcha, |solver| solver.keycaptcha(
+    "184015",
+    "0917788cad24ad3a69813c4fcd556061",
+    "02f7f9669f1269595c4c69bcd4a3c52e",
+    "d888700f6f324ec0f32b44c32c50bde1",
+    "https://2captcha.com/demo/keycaptcha",
+    None
+));
+
+wire_test!(capy, |solver| solver.capy(
+    "PUZZLE_Cz04hZLjuZRMYC3ee10C32D3uNms5w",
+    "https://www.mysite.com/page/with/capy",
+    params(&[("apiServer", "https://jp.api.capy.me/")])
+));
+
+wire_test!(grid, |solver| solver.grid(
+    CaptchaImage::base64(IMAGE),
+    Some(Hint::Text("Select all images with an Orange".to_string())),
+    params(&[("rows", "3"), ("cols", "3")])
+));
+
+wire_test!(canvas, |solver| solver.canvas(
+    CaptchaImage::base64(IMAGE),
+    Hint::Text("Draw around apple".to_string()),
+    None
+));
+
+wire_test!(coordinates, |solver| solver.coordinates(
+    CaptchaImage::base64(IMAGE),
+    Some(Hint::Text(
+        "Select all photos containing the boat".to_string()
+    )),
+    None
+));
+
+wire_test!(lemin, |solver| solver.lemin(
+    "CROPPED_5a29582_ca114c2f3314482c84cd32fc7d2feb63",
+    "lemin-cropped-captcha",
+    "https://2captcha.com/demo/lemin",
+    Some("api.leminnow.com"),
+    None
+));
+
+wire_test!(atb_captcha, |solver| solver.atb_captcha(
+    "af25e409b33d722a95e56a230ff8771c",
+    "https://cap.aisecurius.com",
+    "http://mysite.com/",
+    None
+));
+
+wire_test!(turnstile_challenge, |solver| solver.turnstile(
+    "0x4AAAAAAAChNiVJM_WtShFf",
+    "https://ace.fusionist.io",
+    params(&[
+        ("action", "managed"),
+        ("data", "80001aa1affffc21"),
+        ("pagedata", "3gAFo2l0aM4AA"),
+        ("useragent", "Mozilla/5.0")
+    ])
+));
+
+wire_test!(amazon_waf, |solver| solver.amazon_waf(
+    "AQIDAHjcYu/GjX+QlghicBgQ/7bFaQZ+m5FKCMDnO+vTp9wYvQ",
+    "CgAHbCe2GgAAAAAj",
+    "9BUgmlm48F92WUoqv97a49ZuEJJ50TCk9MVr3C7WMtQ0X6flVbufM4n8mjFLmbLVAPgaQ1Jydeaja94iAS49ljb",
+    "https://non-existent-example.execute-api.us-east-1.amazonaws.com/latest",
+    None
+));
+
+wire_test!(mtcaptcha, |solver| solver.mtcaptcha(
+    "MTPublic-KzqLY1cKH",
+    "https://2captcha.com/demo/mtcaptcha",
+    None
+));
+
+wire_test!(friendly_captcha, |solver| solver.friendly_captcha(
+    "FCMGEMUD2KTDSQ5H",
+    "https://friendlycaptcha.com/demo",
+    None
+));
+
+wire_test!(tencent, |solver| solver.tencent(
+    "197326679",
+    "https://mysite.com/page/with/tencent",
+    None
+));
+
+wire_test!(cutcaptcha, |solver| solver.cutcaptcha(
+    "a1488b66da00bf332a1488993a5443c79047e752",
+    "SAb83IIB",
+    "https://example.cc/foo/bar.html",
+    None
+));
+
+wire_test!(datadome, |solver| solver.datadome(
+    "https://geo.captcha-delivery.com/captcha/?initialCid=AHrlqAAAAAMA9UvsL58YLqIAXNLFPg%3D%3D",
+    "https://mysite.com/page/with/datadome",
+    "Mozilla/5.0",
+    Proxy {
+        proxy_type: "HTTP".to_string(),
+        uri: "login:password@1.2.3.4:8888".to_string(),
+    },
+    None
+));
+
+wire_test!(cybersiara, |solver| solver.cybersiara(
+    "tpjOCKjjpdzv3d8Ub2E9COEWKt1vl1Mv",
+    "https://demo.mycybersiara.com/",
+    "Mozilla/5.0",
+    None
+));
+
+wire_test!(yandex_smart, |solver| solver.yandex_smart(
+    "FEXfAbHQsToo97VidNVk3j4dC74nGW1DgdxjtNB9",
+    "https://captcha-api.yandex.ru/demo",
+    None
+));
+
+#[tokio::test]
+async fn turnstile_json_api() {
+    let server = MockServer::start().await;
+    let result = server
+        .v2_solver()
+        .turnstile("0x4AAAAAAAChNiVJM_WtShFf", "https://ace.fusionist.io", None)
+        .await
+        .unwrap();
+
+    assert_eq!(result.captcha_id, "1234567890");
+    assert_eq!(result.code.as_deref(), Some("answer"));
+    assert_eq!(result.price, Some(0.00145));
+    assert_eq!(result.extended.unwrap()["cost"], "0.00145");
+    assert_eq!(result.trace_id.map(|id| id.len()), Some(36));
+    assert_golden("turnstile_json_api", server.last_submission());
+}
+
+#[tokio::test]
+async fn balance_json_api() {
+    let server = MockServer::start().await;
+    let details = server.v2_solver().balance_details().await.unwrap();
+
+    assert_eq!(details.balance, 12.5);
+    assert_eq!(details.extra["tasksInQueue"], 2);
+}
+
+#[tokio::test]
+async fn report_json_api() {
+    let server = MockServer::start().await;
+    let outcome = server
+        .v2_solver()
+        .report("1234567890", false)
+        .await
+        .unwrap();
+
+    assert_eq!(outcome, ReportOutcome::Recorded);
+}
+
+#[tokio::test]
+async fn price_from_get2() {
+    let server = MockServer::start().await;
+    let config = TwoCaptchaConfig {
+        server: Some(server.url.clone()),
+        fetch_price: Some(true),
+        ..Default::default()
+    };
+    let result = TwoCaptcha::new("API_KEY".to_string(), config)
+        .normal(CaptchaImage::base64(IMAGE), None)
+        .await
+        .unwrap();
+
+    assert_eq!(result.code.as_deref(), Some("answer"));
+    assert_eq!(result.price, Some(0.00099));
+}
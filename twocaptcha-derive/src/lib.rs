@@ -0,0 +1,105 @@
+//! `#[derive(CaptchaTask)]` for the `twocaptcha` crate
+//!
+//! Use it through the `derive` feature of `twocaptcha`, which re-exports the
+//! macro next to the `CaptchaTask` trait.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, LitStr, parse_macro_input};
+
+/// Implement `twocaptcha::CaptchaTask` for a struct with named fields
+///
+/// The struct needs `#[captcha(method = "...")]`. Every field is sent under
+/// its own name unless renamed with `#[param(name = "...")]`, or left out
+/// with `#[param(skip)]`. Field types must implement
+/// `twocaptcha::task::ParamValue`; `None` options are not sent.
+#[proc_macro_derive(CaptchaTask, attributes(captcha, param))]
+pub fn derive_captcha_task(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let method = method(&input)?;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "CaptchaTask can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "CaptchaTask needs a struct with named fields",
+        ));
+    };
+
+    let mut inserts = Vec::new();
+    for field in &fields.named {
+        let ident = field.ident.as_ref().expect("named field");
+        let mut name = ident.to_string();
+        let mut skip = false;
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("param")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `name = \"...\"` or `skip`"))
+                }
+            })?;
+        }
+
+        if !skip {
+            inserts.push(quote! {
+                if let ::std::option::Option::Some(value) =
+                    ::twocaptcha::task::ParamValue::to_param(&self.#ident)
+                {
+                    params.insert(#name.to_string(), value);
+                }
+            });
+        }
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::twocaptcha::CaptchaTask for #ident #ty_generics #where_clause {
+            fn to_task(&self) -> ::twocaptcha::Task {
+                let mut params = ::std::collections::HashMap::new();
+                params.insert("method".to_string(), #method.to_string());
+                #(#inserts)*
+                ::twocaptcha::Task::from_params(params)
+            }
+        }
+    })
+}
+
+/// Value of the required `#[captcha(method = "...")]` attribute
+fn method(input: &DeriveInput) -> syn::Result<String> {
+    let mut method = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("captcha")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("method") {
+                method = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `method = \"...\"`"))
+            }
+        })?;
+    }
+
+    method.ok_or_else(|| {
+        syn::Error::new_spanned(
+            &input.ident,
+            "missing `#[captcha(method = \"...\")]` attribute",
+        )
+    })
+}